@@ -5,373 +5,6683 @@
  * security measures including replay protection, access control, and audit logging.
  */
 
+#![allow(clippy::result_large_err)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use std::mem::size_of;
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::{
+    get_return_data, invoke, invoke_signed, MAX_RETURN_DATA,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address, AssociatedToken};
+use anchor_spl::metadata::{self, Metadata};
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use mpl_token_metadata::state::DataV2;
 
 declare_id!("SchdTrnsfrProgram11111111111111111111111111");
 
-#[program]
-pub mod scheduled_transfer {
-    use super::*;
+/// Maximum number of milestones an arbitrated transfer may be split into
+pub const MAX_MILESTONES: usize = 5;
 
-    /// Schedule a transfer to be executed after a specific timestamp
-    pub fn schedule_transfer(
-        ctx: Context<ScheduleTransfer>,
-        amount: u64,
-        execute_after: i64,
-        nonce: [u8; 32],
-        memo: String,
-    ) -> Result<()> {
-        let transfer_account = &mut ctx.accounts.transfer_account;
-        let sender = &ctx.accounts.sender;
-        let clock = Clock::get()?;
+/// Maximum number of keepers that may be whitelisted on a single transfer
+pub const MAX_ALLOWED_EXECUTORS: usize = 5;
 
-        // Security validations
-        require!(amount > 0, TransferError::InvalidAmount);
-        require!(execute_after > clock.unix_timestamp, TransferError::InvalidExecutionTime);
-        require!(memo.len() <= 200, TransferError::MemoTooLong);
-
-        // Validate execution time is not too far in the future (max 30 days)
-        let max_future_time = clock.unix_timestamp + (30 * 24 * 60 * 60);
-        require!(execute_after <= max_future_time, TransferError::ExecutionTimeTooFar);
-
-        // Initialize the scheduled transfer
-        transfer_account.sender = sender.key();
-        transfer_account.recipient = ctx.accounts.recipient.key();
-        transfer_account.amount = amount;
-        transfer_account.token_mint = ctx.accounts.token_mint.key();
-        transfer_account.execute_after = execute_after;
-        transfer_account.created_at = clock.unix_timestamp;
-        transfer_account.executed = false;
-        transfer_account.cancelled = false;
-        transfer_account.nonce = nonce;
-        transfer_account.memo = memo;
-        transfer_account.bump = *ctx.bumps.get("transfer_account").unwrap();
-
-        // Transfer tokens to escrow
-        if ctx.accounts.token_mint.key() == System::id() {
-            // SOL transfer to escrow
-            let transfer_instruction = anchor_lang::system_program::Transfer {
-                from: sender.to_account_info(),
-                to: transfer_account.to_account_info(),
-            };
+/// Bytes a transfer account holds for its memo unless scheduled through
+/// `schedule_transfer_large_memo`
+pub const DEFAULT_MEMO_CAPACITY: usize = 200;
 
-            anchor_lang::system_program::transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    transfer_instruction,
-                ),
-                amount,
-            )?;
-        } else {
-            // SPL Token transfer to escrow
-            let transfer_instruction = Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.escrow_token_account.to_account_info(),
-                authority: sender.to_account_info(),
-            };
+/// Largest `memo_capacity` accepted by `schedule_transfer_large_memo`
+pub const MAX_MEMO_CAPACITY: usize = 4096;
 
-            token::transfer(
-                CpiContext::new(
-                    ctx.accounts.token_program.to_account_info(),
-                    transfer_instruction,
-                ),
-                amount,
-            )?;
-        }
+/// Instruction data sent to a transfer's condition program: the Anchor sighash of
+/// `check_condition`. The program receives the transfer account (read-only) and must
+/// set return data `[1]` to approve execution.
+pub const CHECK_CONDITION_DISCRIMINATOR: [u8; 8] = [222, 148, 186, 203, 220, 200, 52, 194];
 
-        emit!(TransferScheduled {
-            transfer_id: transfer_account.key(),
-            sender: sender.key(),
-            recipient: ctx.accounts.recipient.key(),
-            amount,
-            token_mint: ctx.accounts.token_mint.key(),
-            execute_after,
-            nonce,
-        });
+/// Instruction data prefix sent to the configured swap program: the Anchor sighash
+/// of `swap`, followed by `amount_in` and `min_out` as little-endian u64s. The
+/// program receives the transfer PDA (signer), the escrow (source), the payee's
+/// output token account (destination) and the token program, then any route
+/// accounts passed in `remaining_accounts`.
+pub const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
 
-        Ok(())
+/// Instruction data prefix sent to a transfer's notify program after each payout:
+/// the Anchor sighash of `on_funds_received`, followed by the transfer id and the
+/// amount paid as a little-endian u64. The program receives the transfer account
+/// and the payee (both read-only) and acknowledges by setting return data `[1]`.
+pub const ON_FUNDS_RECEIVED_DISCRIMINATOR: [u8; 8] = [77, 117, 12, 74, 155, 54, 227, 182];
+
+/// Maximum number of transfers created by a single `batch_schedule` (compute bound)
+pub const MAX_BATCH_SIZE: usize = 5;
+
+/// Maximum number of transfers read by a single `get_transfers_batch`; the
+/// serialized result must also fit the return data buffer, so long memos lower it
+pub const MAX_TRANSFER_INFO_BATCH: usize = 6;
+
+/// Seconds a transfer scheduled with `require_confirmation` must wait before
+/// `confirm_schedule`, so the confirmation is a deliberate second step
+pub const MIN_CONFIRMATION_DELAY_SECONDS: i64 = 30;
+
+/// Seconds a `require_ack` payee has after execution to `acknowledge_receipt`
+/// before the held funds can go back to the sender
+pub const ACK_WINDOW_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// Seconds an `emergency_freeze` must stand before the sender can `unfreeze`
+pub const UNFREEZE_DELAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// Seconds past `execute_after` after which the authority may `mark_unexecutable`
+pub const UNEXECUTABLE_AFTER_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// Seconds past `execute_after` the payee has to reopen a closed token account
+/// before the authority may `reclaim_undeliverable`
+pub const RECLAIM_GRACE_SECONDS: i64 = 3 * 24 * 60 * 60;
+
+/// Layout version stamped on every new `ScheduledTransfer`, so later upgrades can
+/// tell accounts written by older program versions apart
+pub const PROGRAM_VERSION: u16 = 1;
+
+/// Monthly recurrences are computed on UTC calendar days
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Maximum number of addresses on the program-wide recipient denylist
+pub const MAX_DENIED_RECIPIENTS: usize = 100;
+
+/// Format a base-unit amount as a decimal string (e.g. `1_500_000, 6` -> `"1.5"`).
+///
+/// Locale-independent: always uses `.` as the separator, never groups digits,
+/// and strips trailing fractional zeros.
+pub fn format_amount(amount: u64, decimals: u8) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals as usize;
+
+    if decimals == 0 {
+        return digits;
     }
 
-    /// Execute a scheduled transfer after the execution time has passed
-    pub fn execute_scheduled_transfer(
-        ctx: Context<ExecuteScheduledTransfer>,
-    ) -> Result<()> {
-        let transfer_account = &mut ctx.accounts.transfer_account;
-        let clock = Clock::get()?;
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
 
-        // Security validations
-        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
-        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
-        require!(
-            clock.unix_timestamp >= transfer_account.execute_after,
-            TransferError::ExecutionTimeNotReached
-        );
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
 
-        // Verify recipient matches
-        require!(
-            transfer_account.recipient == ctx.accounts.recipient.key(),
-            TransferError::InvalidRecipient
-        );
+#[cfg(test)]
+mod tests {
+    use super::format_amount;
 
-        // Mark as executed before transfer to prevent reentrancy
-        transfer_account.executed = true;
-        transfer_account.executed_at = clock.unix_timestamp;
+    #[test]
+    fn test_format_amount_zero() {
+        assert_eq!(format_amount(0, 0), "0");
+        assert_eq!(format_amount(0, 6), "0");
+        assert_eq!(format_amount(0, 9), "0");
+    }
 
-        // Execute the transfer
-        if transfer_account.token_mint == System::id() {
-            // SOL transfer from escrow
-            let transfer_lamports = transfer_account.amount;
+    #[test]
+    fn test_format_amount_whole() {
+        assert_eq!(format_amount(42, 0), "42");
+        assert_eq!(format_amount(1_000_000, 6), "1");
+        assert_eq!(format_amount(2_000_000_000, 9), "2");
+        assert_eq!(format_amount(u64::MAX, 0), "18446744073709551615");
+    }
 
-            **transfer_account.to_account_info().try_borrow_mut_lamports()? -= transfer_lamports;
-            **ctx.accounts.recipient.to_account_info().try_borrow_mut_lamports()? += transfer_lamports;
+    #[test]
+    fn test_format_amount_fractional() {
+        assert_eq!(format_amount(1_500_000, 6), "1.5");
+        assert_eq!(format_amount(1, 6), "0.000001");
+        assert_eq!(format_amount(500_000_000, 9), "0.5");
+        assert_eq!(format_amount(123_456_789, 9), "0.123456789");
+        assert_eq!(format_amount(1_050, 2), "10.5");
+        assert_eq!(format_amount(u64::MAX, 9), "18446744073.709551615");
+    }
+}
 
-        } else {
-            // SPL Token transfer from escrow
-            let seeds = &[
-                b"transfer",
-                transfer_account.sender.as_ref(),
-                transfer_account.nonce.as_ref(),
-                &[transfer_account.bump],
-            ];
-            let signer = &[&seeds[..]];
+/// The SPL Memo program; its instruction data is the memo's UTF-8 bytes
+pub mod spl_memo {
+    anchor_lang::declare_id!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+}
 
-            let transfer_instruction = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.recipient_token_account.to_account_info(),
-                authority: transfer_account.to_account_info(),
-            };
+/// Mirror a transfer memo through the SPL Memo program so explorers and wallets show it
+fn log_spl_memo<'info>(memo_program: &Option<AccountInfo<'info>>, memo: &str) -> Result<()> {
+    let memo_program = memo_program
+        .as_ref()
+        .ok_or(TransferError::MemoProgramRequired)?;
 
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    transfer_instruction,
-                    signer,
-                ),
-                transfer_account.amount,
-            )?;
-        }
+    let memo_instruction = Instruction {
+        program_id: spl_memo::ID,
+        accounts: vec![],
+        data: memo.as_bytes().to_vec(),
+    };
+    invoke(&memo_instruction, std::slice::from_ref(memo_program))?;
+    Ok(())
+}
 
-        emit!(TransferExecuted {
-            transfer_id: transfer_account.key(),
-            sender: transfer_account.sender,
-            recipient: transfer_account.recipient,
-            amount: transfer_account.amount,
-            token_mint: transfer_account.token_mint,
-            executed_at: clock.unix_timestamp,
-        });
+/// Walk a sender's transfer list from `cursor`, returning up to `page_size` transfer
+/// addresses and the cursor for the following page (`None` once the list is exhausted).
+///
+/// `fetch` resolves a transfer address to its account, e.g. via an RPC lookup.
+pub fn next_page<F>(
+    cursor: Option<Pubkey>,
+    page_size: usize,
+    mut fetch: F,
+) -> (Vec<Pubkey>, Option<Pubkey>)
+where
+    F: FnMut(&Pubkey) -> Option<ScheduledTransfer>,
+{
+    let mut page = Vec::with_capacity(page_size);
+    let mut cursor = cursor;
 
-        Ok(())
+    while page.len() < page_size {
+        let Some(key) = cursor else { break };
+        let Some(transfer) = fetch(&key) else {
+            return (page, None);
+        };
+        page.push(key);
+        cursor = transfer.next;
     }
 
-    /// Cancel a scheduled transfer (only by sender before execution)
-    pub fn cancel_scheduled_transfer(
-        ctx: Context<CancelScheduledTransfer>,
-    ) -> Result<()> {
-        let transfer_account = &mut ctx.accounts.transfer_account;
-        let sender = &ctx.accounts.sender;
+    (page, cursor)
+}
 
-        // Security validations
-        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
-        require!(!transfer_account.cancelled, TransferError::AlreadyCancelled);
-        require!(
-            transfer_account.sender == sender.key(),
-            TransferError::UnauthorizedCancellation
-        );
+/// Address of the transfer `sender` would create with `nonce`. Clients can look it
+/// up before scheduling: if the account exists, the nonce is already used.
+pub fn transfer_address(sender: &Pubkey, nonce: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[b"transfer", sender.as_ref(), nonce.as_ref()], &ID).0
+}
 
-        // Mark as cancelled
-        transfer_account.cancelled = true;
-        transfer_account.cancelled_at = Clock::get()?.unix_timestamp;
+/// Canonical escrow for an SPL transfer: the associated token account of the
+/// transfer PDA for `mint`
+pub fn escrow_ata(transfer_account: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(transfer_account, mint)
+}
 
-        // Refund tokens to sender
-        if transfer_account.token_mint == System::id() {
-            // SOL refund
-            let refund_lamports = transfer_account.amount;
+/// Account size for a `batch_schedule` entry, sized to its memo
+pub fn compute_space(params: &ScheduleParams) -> usize {
+    ScheduledTransfer::space(params.memo.len(), 0)
+}
 
-            **transfer_account.to_account_info().try_borrow_mut_lamports()? -= refund_lamports;
-            **sender.to_account_info().try_borrow_mut_lamports()? += refund_lamports;
+/// Nonce a transfer must use while the config's `require_derived_nonce` is set:
+/// `sha256(sender || recipient || amount || execute_after)`, integers little-endian
+pub fn derived_nonce(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    amount: u64,
+    execute_after: i64,
+) -> [u8; 32] {
+    hashv(&[
+        sender.as_ref(),
+        recipient.as_ref(),
+        &amount.to_le_bytes(),
+        &execute_after.to_le_bytes(),
+    ])
+    .to_bytes()
+}
 
-        } else {
-            // SPL Token refund
-            let seeds = &[
-                b"transfer",
-                transfer_account.sender.as_ref(),
-                transfer_account.nonce.as_ref(),
-                &[transfer_account.bump],
-            ];
-            let signer = &[&seeds[..]];
+/// Calls `schedule_transfer` from another program, typically with one of the
+/// caller's PDAs as `sender` signed for by `signer_seeds`. The PDA must be a
+/// system account holding enough lamports for the transfer and escrow rent, and
+/// `account_infos` must hold every account in `schedule_accounts` plus this
+/// program (which also stands in for omitted optional accounts). Unlike the
+/// generated `cpi::schedule_transfer`, this does not require the `cpi` feature.
+pub fn schedule_transfer_cpi<'info>(
+    schedule_accounts: accounts::ScheduleTransfer,
+    account_infos: &[AccountInfo<'info>],
+    args: instruction::ScheduleTransfer,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let schedule_instruction = Instruction {
+        program_id: ID,
+        accounts: schedule_accounts.to_account_metas(None),
+        data: anchor_lang::InstructionData::data(&args),
+    };
+    invoke_signed(&schedule_instruction, account_infos, signer_seeds)?;
+    Ok(())
+}
 
-            let transfer_instruction = Transfer {
-                from: ctx.accounts.escrow_token_account.to_account_info(),
-                to: ctx.accounts.sender_token_account.to_account_info(),
-                authority: transfer_account.to_account_info(),
-            };
+/// Enforce `derived_nonce` when the config requires it, so an identical
+/// transfer can only be scheduled once
+fn check_derived_nonce(
+    config: Option<&ProgramConfig>,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    amount: u64,
+    execute_after: i64,
+    nonce: &[u8; 32],
+) -> Result<()> {
+    if !config.map_or(false, |config| config.require_derived_nonce) {
+        return Ok(());
+    }
 
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    transfer_instruction,
-                    signer,
-                ),
-                transfer_account.amount,
-            )?;
+    let expected = derived_nonce(sender, recipient, amount, execute_after);
+    if nonce != &expected {
+        msg!("Nonce not derived from transfer parameters: expected={:?}", expected);
+        return err!(TransferError::NonceNotDerived);
+    }
+
+    Ok(())
+}
+
+/// Validation shared by every path that creates a scheduled transfer
+#[allow(clippy::too_many_arguments)]
+fn validate_schedule(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    amount: u64,
+    execute_after: i64,
+    memo: &str,
+    memo_capacity: usize,
+    keeper_fee_lamports: u64,
+    config: Option<&ProgramConfig>,
+    now: i64,
+) -> Result<()> {
+    // Log the offending values before each failure; keepers only see the error code
+    if amount == 0 {
+        msg!("Invalid amount: amount={}", amount);
+        return err!(TransferError::InvalidAmount);
+    }
+    // Zero or negative timestamps are client bugs, not merely stale times
+    if execute_after <= 0 {
+        msg!("Execution time not positive: execute_after={}", execute_after);
+        return err!(TransferError::NonPositiveExecutionTime);
+    }
+    if execute_after <= now {
+        msg!("Execution time in the past: now={} execute_after={}", now, execute_after);
+        return err!(TransferError::InvalidExecutionTime);
+    }
+    // The account only has room for `memo_capacity` bytes, so the byte cap always
+    // applies; deployments may also cap characters, which is what users actually count
+    if memo.len() > memo_capacity {
+        msg!("Memo too long: len={} max={}", memo.len(), memo_capacity);
+        return err!(TransferError::MemoTooLong);
+    }
+    let max_memo_chars = config.map_or(0, |config| config.max_memo_chars);
+    if max_memo_chars > 0 && memo.chars().count() > max_memo_chars as usize {
+        msg!(
+            "Memo has too many characters: chars={} max={}",
+            memo.chars().count(),
+            max_memo_chars
+        );
+        return err!(TransferError::MemoTooManyChars);
+    }
+
+    // Compliance deployments can insist every transfer carries a memo
+    let require_memo = config.map_or(false, |config| config.require_memo);
+    require!(!require_memo || !memo.is_empty(), TransferError::MemoRequired);
+
+    check_self_transfer(config, sender, recipient)?;
+
+    // Validate execution time is not too far in the future (max 30 days)
+    let max_future_time = now + (30 * 24 * 60 * 60);
+    if execute_after > max_future_time {
+        msg!(
+            "Execution time too far: execute_after={} max={}",
+            execute_after,
+            max_future_time
+        );
+        return err!(TransferError::ExecutionTimeTooFar);
+    }
+
+    // Every transfer must be worth a keeper's while to execute
+    let min_keeper_fee = config.map_or(0, |config| config.min_keeper_fee_lamports);
+    if keeper_fee_lamports < min_keeper_fee {
+        msg!(
+            "Keeper fee too low: keeper_fee={} min={}",
+            keeper_fee_lamports,
+            min_keeper_fee
+        );
+        return err!(TransferError::KeeperFeeTooLow);
+    }
+
+    // Large transfers must stay pending long enough for monitoring to react
+    if let Some(config) = config.filter(|config| is_large_transfer(config, amount)) {
+        let earliest = now
+            .saturating_add(config.min_escrow_seconds)
+            .saturating_add(config.large_transfer_extra_delay);
+        if execute_after < earliest {
+            msg!(
+                "Large transfer needs a longer delay: execute_after={} earliest={}",
+                execute_after,
+                earliest
+            );
+            return err!(TransferError::LargeTransferDelayRequired);
         }
+    }
 
-        emit!(TransferCancelled {
-            transfer_id: transfer_account.key(),
-            sender: transfer_account.sender,
-            amount: transfer_account.amount,
-            cancelled_at: transfer_account.cancelled_at,
-        });
+    Ok(())
+}
 
-        Ok(())
+/// Days since 1970-01-01 of a proleptic Gregorian (UTC) date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`: `(year, month, day)`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)
+}
+
+/// `timestamp` moved `months` calendar months later in UTC, keeping the time of
+/// day and clamping the day of month (e.g. Jan 31 + 1 month = Feb 28/29)
+fn add_calendar_months(timestamp: i64, months: i64) -> Result<i64> {
+    let days = timestamp.div_euclid(SECONDS_PER_DAY);
+    let seconds_of_day = timestamp.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+
+    let month_count = (year * 12 + month - 1)
+        .checked_add(months)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+    let year = month_count.div_euclid(12);
+    let month = month_count.rem_euclid(12) + 1;
+    let day = day.min(days_in_month(year, month));
+
+    days_from_civil(year, month, day)
+        .checked_mul(SECONDS_PER_DAY)
+        .and_then(|seconds| seconds.checked_add(seconds_of_day))
+        .ok_or(TransferError::ArithmeticOverflow.into())
+}
+
+/// Whether `amount` reaches the configured large-transfer threshold (0 disables it)
+fn is_large_transfer(config: &ProgramConfig, amount: u64) -> bool {
+    config.large_transfer_threshold > 0 && amount >= config.large_transfer_threshold
+}
+
+/// Self-transfers are almost always a client bug; only allow when configured
+fn check_self_transfer(
+    config: Option<&ProgramConfig>,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+) -> Result<()> {
+    let allow_self_transfer = config.map_or(false, |config| config.allow_self_transfer);
+    require!(
+        allow_self_transfer || recipient != sender,
+        TransferError::SelfTransferNotAllowed
+    );
+    Ok(())
+}
+
+/// Lamports sent to a program-owned account are usually stranded, so SOL
+/// recipients must be wallets unless the sender vouches for the PDA
+fn check_sol_recipient(
+    token_mint: &Pubkey,
+    recipient: &AccountInfo,
+    recipient_is_pda: bool,
+) -> Result<()> {
+    if *token_mint == System::id() && !recipient_is_pda && *recipient.owner != System::id() {
+        msg!(
+            "SOL recipient is program-owned: recipient={} owner={}",
+            recipient.key(),
+            recipient.owner
+        );
+        return err!(TransferError::InvalidRecipient);
     }
+    Ok(())
+}
 
-    /// Get transfer information (view function)
-    pub fn get_transfer_info(
-        ctx: Context<GetTransferInfo>,
-    ) -> Result<TransferInfo> {
-        let transfer_account = &ctx.accounts.transfer_account;
+/// Reject recipients on the admin-managed denylist; until the denylist PDA is
+/// initialized nobody is denied
+fn check_recipient_allowed(denylist: &AccountInfo, recipient: &Pubkey) -> Result<()> {
+    if denylist.owner != &crate::ID || denylist.data_is_empty() {
+        return Ok(());
+    }
 
-        Ok(TransferInfo {
-            sender: transfer_account.sender,
-            recipient: transfer_account.recipient,
-            amount: transfer_account.amount,
-            token_mint: transfer_account.token_mint,
-            execute_after: transfer_account.execute_after,
-            created_at: transfer_account.created_at,
-            executed: transfer_account.executed,
-            executed_at: transfer_account.executed_at,
-            cancelled: transfer_account.cancelled,
-            cancelled_at: transfer_account.cancelled_at,
-            memo: transfer_account.memo.clone(),
-        })
+    let denylist = RecipientDenylist::try_deserialize(&mut &denylist.try_borrow_data()?[..])?;
+    if denylist.denied.contains(recipient) {
+        msg!("Recipient denied: recipient={}", recipient);
+        return err!(TransferError::RecipientDenied);
     }
+
+    Ok(())
 }
 
-#[derive(Accounts)]
-#[instruction(amount: u64, execute_after: i64, nonce: [u8; 32])]
-pub struct ScheduleTransfer<'info> {
-    #[account(
-        init,
-        payer = sender,
-        space = 8 + ScheduledTransfer::INIT_SPACE,
-        seeds = [b"transfer", sender.key().as_ref(), nonce.as_ref()],
-        bump
-    )]
-    pub transfer_account: Account<'info, ScheduledTransfer>,
+/// Enforce a recipient's opt-in minimum gap between transfers scheduled to them,
+/// then start the next gap; without a policy account the recipient never opted in
+fn check_recipient_cooldown(policy: &AccountInfo, now: i64) -> Result<()> {
+    if policy.owner != &crate::ID || policy.data_is_empty() {
+        return Ok(());
+    }
 
-    #[account(mut)]
-    pub sender: Signer<'info>,
+    let mut recipient_policy =
+        RecipientPolicy::try_deserialize(&mut &policy.try_borrow_data()?[..])?;
+    if recipient_policy.cooldown_seconds == 0 {
+        return Ok(());
+    }
+    let available_at = recipient_policy
+        .last_scheduled_at
+        .saturating_add(recipient_policy.cooldown_seconds);
+    if now < available_at {
+        msg!("Recipient cooldown active: now={} available_at={}", now, available_at);
+        return err!(TransferError::RecipientCooldownActive);
+    }
 
-    /// CHECK: This is validated in the instruction
-    pub recipient: AccountInfo<'info>,
+    recipient_policy.last_scheduled_at = now;
+    recipient_policy.try_serialize(&mut &mut policy.try_borrow_mut_data()?[..])?;
 
-    /// CHECK: Token mint account
-    pub token_mint: AccountInfo<'info>,
+    Ok(())
+}
 
-    #[account(
-        mut,
-        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount,
-        constraint = sender_token_account.mint == token_mint.key() @ TransferError::InvalidTokenMint
-    )]
-    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+/// Recipient checks shared by every path that schedules a new transfer
+fn validate_recipient(
+    denylist: &AccountInfo,
+    recipient_policy: &AccountInfo,
+    recipient: &AccountInfo,
+    token_mint: &Pubkey,
+    recipient_is_pda: bool,
+    now: i64,
+) -> Result<()> {
+    check_recipient_allowed(denylist, &recipient.key())?;
+    check_recipient_cooldown(recipient_policy, now)?;
+    check_sol_recipient(token_mint, recipient, recipient_is_pda)
+}
 
-    #[account(
-        init_if_needed,
-        payer = sender,
-        associated_token::mint = token_mint,
-        associated_token::authority = transfer_account
-    )]
-    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+/// Move lamports out of a SOL escrow without dipping into its rent-exempt reserve
+fn debit_sol_escrow<'info>(
+    escrow: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    rent_reserve: u64,
+    amount: u64,
+) -> Result<()> {
+    let available = escrow
+        .lamports()
+        .checked_sub(rent_reserve)
+        .ok_or(TransferError::EscrowBelowRentReserve)?;
+    require!(amount <= available, TransferError::EscrowBelowRentReserve);
 
-    pub token_program: Option<Program<'info, Token>>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+    **escrow.try_borrow_mut_lamports()? -= amount;
+    **destination.try_borrow_mut_lamports()? += amount;
+
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct ExecuteScheduledTransfer<'info> {
-    #[account(
-        mut,
-        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
-        bump = transfer_account.bump
-    )]
-    pub transfer_account: Account<'info, ScheduledTransfer>,
+/// CPI into a transfer's condition program; it approves by returning data `[1]`
+fn custom_condition_approved<'info>(
+    transfer_account: &AccountInfo<'info>,
+    condition_program_account: &Option<AccountInfo<'info>>,
+    condition_program: Pubkey,
+) -> Result<bool> {
+    let program = condition_program_account
+        .as_ref()
+        .ok_or(TransferError::InvalidConditionProgram)?;
+    require_keys_eq!(
+        program.key(),
+        condition_program,
+        TransferError::InvalidConditionProgram
+    );
 
-    #[account(mut)]
-    pub recipient: Signer<'info>,
+    let check_instruction = Instruction {
+        program_id: condition_program,
+        accounts: vec![AccountMeta::new_readonly(transfer_account.key(), false)],
+        data: CHECK_CONDITION_DISCRIMINATOR.to_vec(),
+    };
+    invoke(
+        &check_instruction,
+        &[transfer_account.clone(), program.clone()],
+    )?;
 
-    #[account(
-        mut,
-        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount
-    )]
-    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+    Ok(matches!(
+        get_return_data(),
+        Some((program_id, data)) if program_id == condition_program && data == [1]
+    ))
+}
 
+/// CPI into a transfer's notify program to tell it `amount` has reached `payee`.
+/// Best-effort unless `require_callback_success`: a missing program account or a
+/// missing `[1]` acknowledgment is only logged. A handler that errors still
+/// aborts the execution, since a failed CPI cannot be caught.
+fn notify_funds_received<'info>(
+    transfer_account: &AccountInfo<'info>,
+    payee: &AccountInfo<'info>,
+    notify_program_account: &Option<AccountInfo<'info>>,
+    notify_program: Pubkey,
+    require_callback_success: bool,
+    amount: u64,
+) -> Result<()> {
+    let program = match notify_program_account {
+        Some(program) if program.key() == notify_program => program,
+        _ => {
+            msg!("Notify program not supplied: expected={}", notify_program);
+            require!(!require_callback_success, TransferError::CallbackFailed);
+            return Ok(());
+        }
+    };
+
+    let mut data = ON_FUNDS_RECEIVED_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(transfer_account.key().as_ref());
+    data.extend_from_slice(&amount.to_le_bytes());
+    let callback_instruction = Instruction {
+        program_id: notify_program,
+        accounts: vec![
+            AccountMeta::new_readonly(transfer_account.key(), false),
+            AccountMeta::new_readonly(payee.key(), false),
+        ],
+        data,
+    };
+    invoke(
+        &callback_instruction,
+        &[transfer_account.clone(), payee.clone(), program.clone()],
+    )?;
+
+    let acknowledged = matches!(
+        get_return_data(),
+        Some((program_id, data)) if program_id == notify_program && data == [1]
+    );
+    if !acknowledged {
+        msg!("Notify program did not acknowledge: program={}", notify_program);
+        require!(!require_callback_success, TransferError::CallbackFailed);
+    }
+
+    Ok(())
+}
+
+/// CPI into the swap program to convert escrowed tokens, signed by the transfer PDA
+#[allow(clippy::too_many_arguments)]
+fn swap_via_program<'info>(
+    swap_program: &AccountInfo<'info>,
+    transfer_account: AccountInfo<'info>,
+    source: AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    route_accounts: &[AccountInfo<'info>],
+    amount_in: u64,
+    min_out: u64,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(transfer_account.key(), true),
+        AccountMeta::new(source.key(), false),
+        AccountMeta::new(destination.key(), false),
+        AccountMeta::new_readonly(token_program.key(), false),
+    ];
+    accounts.extend(route_accounts.iter().map(|account| AccountMeta {
+        pubkey: account.key(),
+        is_signer: account.is_signer,
+        is_writable: account.is_writable,
+    }));
+
+    let mut data = SWAP_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_out.to_le_bytes());
+
+    let mut account_infos = vec![transfer_account, source, destination, token_program];
+    account_infos.extend_from_slice(route_accounts);
+    account_infos.push(swap_program.clone());
+
+    invoke_signed(
+        &Instruction {
+            program_id: swap_program.key(),
+            accounts,
+            data,
+        },
+        &account_infos,
+        signer,
+    )?;
+
+    Ok(())
+}
+
+/// Accounts needed to mint a receipt; every one is optional on
+/// `ExecuteScheduledTransfer` and only required when the transfer opted in
+struct ReceiptAccounts<'info> {
+    payer: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    token_account: AccountInfo<'info>,
+    metadata: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+    metadata_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+}
+
+fn receipt_account<'info, T: ToAccountInfo<'info>>(
+    account: &Option<T>,
+) -> Result<AccountInfo<'info>> {
+    account
+        .as_ref()
+        .map(|account| account.to_account_info())
+        .ok_or_else(|| TransferError::ReceiptMintFailed.into())
+}
+
+/// Mint a single frozen (soulbound) receipt token to the payee, recording the
+/// transfer id, amount and execution time in its metadata
+fn mint_transfer_receipt<'info>(
+    transfer_account: &Account<'info, ScheduledTransfer>,
+    receipt: ReceiptAccounts<'info>,
+    amount: u64,
+    executed_at: i64,
+) -> Result<()> {
+    let authority = transfer_account.to_account_info();
+    let seeds = &[
+        b"transfer",
+        transfer_account.sender.as_ref(),
+        transfer_account.nonce.as_ref(),
+        &[transfer_account.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    anchor_lang::system_program::create_account(
+        CpiContext::new(
+            receipt.system_program.clone(),
+            anchor_lang::system_program::CreateAccount {
+                from: receipt.payer.clone(),
+                to: receipt.mint.clone(),
+            },
+        ),
+        Rent::get()?.minimum_balance(Mint::LEN),
+        Mint::LEN as u64,
+        &token::ID,
+    )?;
+    token::initialize_mint2(
+        CpiContext::new(
+            receipt.token_program.clone(),
+            token::InitializeMint2 { mint: receipt.mint.clone() },
+        ),
+        0,
+        &authority.key(),
+        Some(&authority.key()),
+    )?;
+
+    associated_token::create(CpiContext::new(
+        receipt.associated_token_program.clone(),
+        associated_token::Create {
+            payer: receipt.payer.clone(),
+            associated_token: receipt.token_account.clone(),
+            authority: receipt.recipient.clone(),
+            mint: receipt.mint.clone(),
+            system_program: receipt.system_program.clone(),
+            token_program: receipt.token_program.clone(),
+        },
+    ))?;
+    token::mint_to(
+        CpiContext::new_with_signer(
+            receipt.token_program.clone(),
+            token::MintTo {
+                mint: receipt.mint.clone(),
+                to: receipt.token_account.clone(),
+                authority: authority.clone(),
+            },
+            signer,
+        ),
+        1,
+    )?;
+
+    metadata::create_metadata_accounts_v3(
+        CpiContext::new_with_signer(
+            receipt.metadata_program.clone(),
+            metadata::CreateMetadataAccountsV3 {
+                metadata: receipt.metadata.clone(),
+                mint: receipt.mint.clone(),
+                mint_authority: authority.clone(),
+                payer: receipt.payer.clone(),
+                update_authority: authority.clone(),
+                system_program: receipt.system_program.clone(),
+                rent: receipt.rent.clone(),
+            },
+            signer,
+        ),
+        DataV2 {
+            name: "Transfer Receipt".to_string(),
+            symbol: "RCPT".to_string(),
+            uri: format!(
+                "receipt:{}?amount={}&executed_at={}",
+                transfer_account.key(),
+                amount,
+                executed_at
+            ),
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        },
+        false,
+        true,
+        None,
+    )?;
+
+    // Soulbound: the holder can never move it, and no second copy can be minted
+    token::freeze_account(CpiContext::new_with_signer(
+        receipt.token_program.clone(),
+        token::FreezeAccount {
+            account: receipt.token_account.clone(),
+            mint: receipt.mint.clone(),
+            authority: authority.clone(),
+        },
+        signer,
+    ))?;
+    token::set_authority(
+        CpiContext::new_with_signer(
+            receipt.token_program,
+            token::SetAuthority {
+                current_authority: authority,
+                account_or_mint: receipt.mint,
+            },
+            signer,
+        ),
+        token::spl_token::instruction::AuthorityType::MintTokens,
+        None,
+    )?;
+
+    Ok(())
+}
+
+/// Accounts needed to create a payee's associated token account at execution
+struct SponsorAccounts<'info> {
+    payer: AccountInfo<'info>,
+    token_account: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    associated_token_program: AccountInfo<'info>,
+}
+
+fn sponsor_account<'info, T: ToAccountInfo<'info>>(
+    account: &Option<T>,
+) -> Result<AccountInfo<'info>> {
+    account
+        .as_ref()
+        .map(|account| account.to_account_info())
+        .ok_or_else(|| TransferError::InvalidTokenAccount.into())
+}
+
+/// Create the payee's associated token account with the executor paying, then
+/// reimburse the rent from the treasury (the config account's lamports above its
+/// own rent reserve, which also fund rebates)
+fn sponsor_recipient_token_account<'info>(
+    config: Option<&Account<'info, ProgramConfig>>,
+    sponsor: SponsorAccounts<'info>,
+) -> Result<AccountInfo<'info>> {
+    let config = config
+        .filter(|config| config.sponsor_recipient_ata)
+        .ok_or(TransferError::InvalidTokenAccount)?;
+
+    let treasury = config.to_account_info();
+    let treasury_reserve = Rent::get()?.minimum_balance(treasury.data_len());
+    let available = treasury.lamports().saturating_sub(treasury_reserve);
+    let account_rent = Rent::get()?.minimum_balance(TokenAccount::LEN);
+    if available < account_rent {
+        msg!("Treasury insufficient: available={} needed={}", available, account_rent);
+        return err!(TransferError::TreasuryInsufficient);
+    }
+
+    associated_token::create(CpiContext::new(
+        sponsor.associated_token_program,
+        associated_token::Create {
+            payer: sponsor.payer.clone(),
+            associated_token: sponsor.token_account.clone(),
+            authority: sponsor.recipient,
+            mint: sponsor.mint,
+            system_program: sponsor.system_program,
+            token_program: sponsor.token_program,
+        },
+    ))?;
+    debit_sol_escrow(&treasury, &sponsor.payer, treasury_reserve, account_rent)?;
+
+    Ok(sponsor.token_account)
+}
+
+/// Splice a settled transfer out of its sender's registry list
+fn unlink_transfer<'info>(
+    transfer_account: &mut Account<'info, ScheduledTransfer>,
+    registry: &mut Option<Account<'info, SenderRegistry>>,
+    previous_transfer: &mut Option<Account<'info, ScheduledTransfer>>,
+) -> Result<()> {
+    if !transfer_account.in_registry {
+        return Ok(());
+    }
+
+    let registry = registry.as_mut().ok_or(TransferError::RegistryRequired)?;
+    let transfer_key = transfer_account.key();
+
+    if registry.head == Some(transfer_key) {
+        registry.head = transfer_account.next;
+    } else {
+        let previous = previous_transfer
+            .as_mut()
+            .ok_or(TransferError::InvalidListLink)?;
+        require!(
+            previous.next == Some(transfer_key),
+            TransferError::InvalidListLink
+        );
+        previous.next = transfer_account.next;
+        previous.last_modified_at = Clock::get()?.unix_timestamp;
+    }
+
+    registry.count = registry
+        .count
+        .checked_sub(1)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+    transfer_account.next = None;
+    transfer_account.in_registry = false;
+
+    Ok(())
+}
+
+/// Mark a transfer cancelled and refund its remaining escrow and keeper fees to
+/// the signer of `accounts`; callers have already checked who may do so
+fn refund_cancelled_transfer<'info>(
+    accounts: &mut CancelScheduledTransfer<'info>,
+    clock: &Clock,
+) -> Result<()> {
+    let verbose = verbose_events(accounts.config.as_deref());
+    let transfer_account = &mut accounts.transfer_account;
+    let sender = &accounts.sender;
+
+    // The payee has until the deadline to acknowledge an executed transfer
+    if transfer_account.ack_deadline > 0 && clock.unix_timestamp <= transfer_account.ack_deadline {
+        msg!("Acknowledgment pending: ack_deadline={}", transfer_account.ack_deadline);
+        return err!(TransferError::AckPending);
+    }
+
+    // Only the unexecuted occurrences are still held in escrow
+    let refund_amount = transfer_account.remaining_escrow()?;
+    let refund_keeper_fees = transfer_account.remaining_keeper_fees()?;
+
+    // Mark as cancelled
+    transfer_account.cancelled = true;
+    transfer_account.cancelled_at = clock.unix_timestamp;
+    transfer_account.last_modified_at = clock.unix_timestamp;
+    release_outstanding(transfer_account, &mut accounts.mint_stats, refund_amount)?;
+    release_global_pending(transfer_account, accounts.config.as_deref_mut())?;
+    bump_event_seq(accounts.config.as_deref_mut())?;
+    unlink_transfer(
+        transfer_account,
+        &mut accounts.registry,
+        &mut accounts.previous_transfer,
+    )?;
+
+    // An unconfirmed transfer was never funded, so there is nothing to refund
+    if transfer_account.pending_confirmation {
+        if verbose {
+            emit!(TransferCancelled {
+                transfer_id: transfer_account.key(),
+                sender: transfer_account.sender,
+                amount: 0,
+                cancelled_at: transfer_account.cancelled_at,
+                slot: clock.slot,
+            });
+        } else {
+            emit!(TransferStatusChanged {
+                transfer_id: transfer_account.key(),
+                status: TransferStatus::Cancelled,
+            });
+        }
+        return Ok(());
+    }
+
+    // Refund tokens to sender
+    if transfer_account.token_mint == System::id() {
+        // SOL refund
+        debit_sol_escrow(
+            &transfer_account.to_account_info(),
+            &sender.to_account_info(),
+            transfer_account.rent_reserve,
+            refund_amount,
+        )?;
+
+    } else {
+        // SPL Token refund
+        let seeds = &[
+            b"transfer",
+            transfer_account.sender.as_ref(),
+            transfer_account.nonce.as_ref(),
+            &[transfer_account.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let escrow_token_account = accounts
+            .escrow_token_account
+            .as_ref()
+            .ok_or(TransferError::InvalidEscrowAccount)?;
+        let sender_token_account = accounts
+            .sender_token_account
+            .as_ref()
+            .ok_or(TransferError::InvalidTokenAccount)?;
+        let token_program = accounts
+            .token_program
+            .as_ref()
+            .ok_or(TransferError::InvalidTokenAccount)?;
+        let transfer_instruction = Transfer {
+            from: escrow_token_account.to_account_info(),
+            to: sender_token_account.to_account_info(),
+            authority: transfer_account.to_account_info(),
+        };
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                transfer_instruction,
+                signer,
+            ),
+            refund_amount,
+        )?;
+    }
+
+    // Unearned keeper fees go back to the sender
+    if refund_keeper_fees > 0 {
+        debit_sol_escrow(
+            &transfer_account.to_account_info(),
+            &sender.to_account_info(),
+            transfer_account.rent_reserve,
+            refund_keeper_fees,
+        )?;
+    }
+
+    if verbose {
+        emit!(TransferCancelled {
+            transfer_id: transfer_account.key(),
+            sender: transfer_account.sender,
+            amount: refund_amount,
+            cancelled_at: transfer_account.cancelled_at,
+            slot: clock.slot,
+        });
+    } else {
+        emit!(TransferStatusChanged {
+            transfer_id: transfer_account.key(),
+            status: TransferStatus::Cancelled,
+        });
+    }
+
+    Ok(())
+}
+
+/// Whether lifecycle events carry their full payload; a config can switch them
+/// to `TransferStatusChanged` to save compute
+fn verbose_events(config: Option<&ProgramConfig>) -> bool {
+    config.map_or(true, |config| config.verbose_events)
+}
+
+/// Count a new transfer towards the config's global pending total, enforcing
+/// `max_global_pending`; returns whether it was counted (only while a cap is set)
+fn count_global_pending(config: Option<&mut ProgramConfig>) -> Result<bool> {
+    let Some(config) = config.filter(|config| config.max_global_pending > 0) else {
+        return Ok(false);
+    };
+
+    if config.global_pending >= config.max_global_pending {
+        msg!(
+            "Global pending limit reached: pending={} max={}",
+            config.global_pending,
+            config.max_global_pending
+        );
+        return err!(TransferError::GlobalPendingLimitReached);
+    }
+    config.global_pending = config
+        .global_pending
+        .checked_add(1)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+
+    Ok(true)
+}
+
+/// Advance the config's event sequence for an instruction that moved funds;
+/// the config can only be left out while none has been created
+fn bump_event_seq(config: Option<&mut ProgramConfig>) -> Result<()> {
+    if let Some(config) = config {
+        config.event_seq = config
+            .event_seq
+            .checked_add(1)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+    }
+
+    Ok(())
+}
+
+/// Take a settled transfer off the global pending total it was counted in
+fn release_global_pending(
+    transfer_account: &mut ScheduledTransfer,
+    config: Option<&mut ProgramConfig>,
+) -> Result<()> {
+    if !transfer_account.in_global_pending {
+        return Ok(());
+    }
+
+    let config = config.ok_or(TransferError::ConfigRequired)?;
+    config.global_pending = config
+        .global_pending
+        .checked_sub(1)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+    transfer_account.in_global_pending = false;
+
+    Ok(())
+}
+
+/// Take `amount` of a tracked transfer's escrow off its mint's outstanding total
+fn release_outstanding<'info>(
+    transfer_account: &ScheduledTransfer,
+    mint_stats: &mut Option<Account<'info, MintStats>>,
+    amount: u64,
+) -> Result<()> {
+    if !transfer_account.in_mint_stats {
+        return Ok(());
+    }
+
+    let mint_stats = mint_stats.as_mut().ok_or(TransferError::MintStatsRequired)?;
+    mint_stats.total_outstanding = mint_stats
+        .total_outstanding
+        .checked_sub(amount)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+
+    Ok(())
+}
+
+/// Pay out (approve) or refund (reject) one milestone of an arbitrated transfer
+fn settle_milestone(ctx: Context<SettleMilestone>, index: u8, approve: bool) -> Result<()> {
+    let transfer_account = &mut ctx.accounts.transfer_account;
+    let destination = &ctx.accounts.destination;
+    let clock = Clock::get()?;
+
+    // Security validations
+    require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+    require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+    require!(
+        transfer_account.arbiter == Some(ctx.accounts.arbiter.key()),
+        TransferError::UnauthorizedArbiter
+    );
+    let expected_destination = if approve {
+        transfer_account.recipient
+    } else {
+        transfer_account.authority
+    };
+    require_keys_eq!(destination.key(), expected_destination, TransferError::InvalidRecipient);
+
+    let milestone = transfer_account
+        .milestones
+        .get_mut(index as usize)
+        .ok_or(TransferError::InvalidMilestones)?;
+    require!(
+        !milestone.approved && !milestone.rejected,
+        TransferError::MilestoneAlreadySettled
+    );
+    let amount = milestone.amount;
+    if approve {
+        milestone.approved = true;
+    } else {
+        milestone.rejected = true;
+    }
+
+    if transfer_account.token_mint == System::id() {
+        debit_sol_escrow(
+            &transfer_account.to_account_info(),
+            &destination.to_account_info(),
+            transfer_account.rent_reserve,
+            amount,
+        )?;
+    } else {
+        let escrow_token_account = ctx
+            .accounts
+            .escrow_token_account
+            .as_ref()
+            .ok_or(TransferError::InvalidEscrowAccount)?;
+        let destination_token_account = ctx
+            .accounts
+            .destination_token_account
+            .as_ref()
+            .ok_or(TransferError::InvalidTokenAccount)?;
+        let token_program = ctx
+            .accounts
+            .token_program
+            .as_ref()
+            .ok_or(TransferError::InvalidTokenAccount)?;
+
+        let seeds = &[
+            b"transfer",
+            transfer_account.sender.as_ref(),
+            transfer_account.nonce.as_ref(),
+            &[transfer_account.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: escrow_token_account.to_account_info(),
+            to: destination_token_account.to_account_info(),
+            authority: transfer_account.to_account_info(),
+        };
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                transfer_instruction,
+                signer,
+            ),
+            amount,
+        )?;
+    }
+
+    // `amount` tracks what is still held for the unsettled milestones
+    transfer_account.amount = transfer_account
+        .amount
+        .checked_sub(amount)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+    transfer_account.last_modified_at = clock.unix_timestamp;
+    release_outstanding(transfer_account, &mut ctx.accounts.mint_stats, amount)?;
+    bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+
+    // Once every milestone is settled the transfer counts as executed if
+    // anything was released, otherwise as cancelled
+    let settled = transfer_account
+        .milestones
+        .iter()
+        .all(|milestone| milestone.approved || milestone.rejected);
+    if settled {
+        if transfer_account.milestones.iter().any(|milestone| milestone.approved) {
+            transfer_account.executed = true;
+            transfer_account.executed_at = clock.unix_timestamp;
+            transfer_account.executed_occurrences = transfer_account.total_occurrences;
+        } else {
+            transfer_account.cancelled = true;
+            transfer_account.cancelled_at = clock.unix_timestamp;
+        }
+        release_global_pending(transfer_account, ctx.accounts.config.as_deref_mut())?;
+        unlink_transfer(
+            transfer_account,
+            &mut ctx.accounts.registry,
+            &mut ctx.accounts.previous_transfer,
+        )?;
+    }
+
+    emit!(MilestoneSettled {
+        transfer_id: transfer_account.key(),
+        arbiter: ctx.accounts.arbiter.key(),
+        index,
+        amount,
+        approved: approve,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+/// Accounts shared by the `schedule_transfer` variants
+struct ScheduleAccounts<'a, 'info> {
+    transfer_account: &'a mut Account<'info, ScheduledTransfer>,
+    sender: &'a Signer<'info>,
+    recipient: &'a AccountInfo<'info>,
+    token_mint: &'a AccountInfo<'info>,
+    config: Option<&'a mut ProgramConfig>,
+    denylist: &'a AccountInfo<'info>,
+    recipient_policy: &'a AccountInfo<'info>,
+    registry: Option<&'a mut Account<'info, SenderRegistry>>,
+    mint_stats: Option<&'a mut Account<'info, MintStats>>,
+    campaign_stats: Option<&'a mut Account<'info, CampaignStats>>,
+    refund_of_transfer: Option<&'a Account<'info, ScheduledTransfer>>,
+    sender_token_account: Option<AccountInfo<'info>>,
+    escrow_token_account: Option<AccountInfo<'info>>,
+    token_program: Option<AccountInfo<'info>>,
+    system_program: &'a Program<'info, System>,
+    memo_program: Option<AccountInfo<'info>>,
+}
+
+/// Accounts that move a new transfer's escrow out of the sender's wallet
+struct EscrowFunding<'info> {
+    sender: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    sender_token_account: Option<AccountInfo<'info>>,
+    escrow_token_account: Option<AccountInfo<'info>>,
+    token_program: Option<AccountInfo<'info>>,
+}
+
+/// Lamports a `sweep_all` transfer escrows: the sender's balance less its own
+/// rent-exempt minimum and the keeper fee
+fn sweep_amount(sender: &Signer, keeper_fee_lamports: u64) -> Result<u64> {
+    let balance = sender.lamports();
+    let keep = Rent::get()?
+        .minimum_balance(0)
+        .checked_add(keeper_fee_lamports)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+    match balance.checked_sub(keep) {
+        Some(amount) if amount > 0 => Ok(amount),
+        _ => {
+            msg!("Nothing to sweep: balance={} kept={}", balance, keep);
+            err!(TransferError::InsufficientFunds)
+        }
+    }
+}
+
+/// The amount a transfer escrows for `spec`; basis points apply to the
+/// sender's balance now, which for SOL is what a `sweep_all` would take
+fn resolve_amount(
+    spec: AmountSpec,
+    sender: &Signer,
+    sender_token_account: Option<&AccountInfo>,
+    token_mint: &Pubkey,
+    keeper_fee_lamports: u64,
+) -> Result<u64> {
+    let bps = match spec {
+        AmountSpec::Absolute(amount) => return Ok(amount),
+        AmountSpec::Bps(bps) => bps,
+    };
+    if bps > 10_000 {
+        msg!("Invalid amount bps: bps={} max=10000", bps);
+        return err!(TransferError::InvalidAmountBps);
+    }
+    let balance = if *token_mint == System::id() {
+        sweep_amount(sender, keeper_fee_lamports)?
+    } else {
+        let sender_token_account =
+            sender_token_account.ok_or(TransferError::InvalidTokenAccount)?;
+        TokenAccount::try_deserialize(&mut &sender_token_account.try_borrow_data()?[..])?.amount
+    };
+    let scaled = (balance as u128) * (bps as u128) / 10_000;
+    u64::try_from(scaled).map_err(|_| TransferError::ArithmeticOverflow.into())
+}
+
+/// Check balances up front so an under-funded sender gets a clear error
+/// instead of whatever the system or token program reports mid-CPI
+fn check_sender_funds(
+    sender: &AccountInfo,
+    sender_token_account: Option<&AccountInfo>,
+    is_sol: bool,
+    escrow_amount: u64,
+    keeper_fee_escrow: u64,
+) -> Result<()> {
+    let lamports_needed = if is_sol {
+        escrow_amount
+            .checked_add(keeper_fee_escrow)
+            .ok_or(TransferError::ArithmeticOverflow)?
+    } else {
+        keeper_fee_escrow
+    };
+    if sender.lamports() < lamports_needed {
+        msg!("Insufficient lamports: balance={} needed={}", sender.lamports(), lamports_needed);
+        return err!(TransferError::InsufficientFunds);
+    }
+    if !is_sol {
+        if let Some(sender_token_account) = sender_token_account {
+            let balance =
+                TokenAccount::try_deserialize(&mut &sender_token_account.try_borrow_data()?[..])?
+                    .amount;
+            if balance < escrow_amount {
+                msg!("Insufficient tokens: balance={} needed={}", balance, escrow_amount);
+                return err!(TransferError::InsufficientFunds);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Move `escrow_amount` (plus keeper fees, always in lamports) into escrow
+fn fund_escrow<'info>(
+    transfer_account: &Account<'info, ScheduledTransfer>,
+    funding: EscrowFunding<'info>,
+    escrow_amount: u64,
+    keeper_fee_escrow: u64,
+) -> Result<()> {
+    let is_sol = transfer_account.token_mint == System::id();
+    check_sender_funds(
+        &funding.sender,
+        funding.sender_token_account.as_ref(),
+        is_sol,
+        escrow_amount,
+        keeper_fee_escrow,
+    )?;
+
+    // Keeper fees are always paid in lamports held by the transfer account
+    if keeper_fee_escrow > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                funding.system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: funding.sender.clone(),
+                    to: transfer_account.to_account_info(),
+                },
+            ),
+            keeper_fee_escrow,
+        )?;
+    }
+
+    // Transfer tokens to escrow
+    if is_sol {
+        // SOL transfer to escrow
+        let transfer_instruction = anchor_lang::system_program::Transfer {
+            from: funding.sender,
+            to: transfer_account.to_account_info(),
+        };
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(funding.system_program, transfer_instruction),
+            escrow_amount,
+        )?;
+
+        let required_lamports = transfer_account
+            .rent_reserve
+            .checked_add(escrow_amount)
+            .and_then(|lamports| lamports.checked_add(keeper_fee_escrow))
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        require!(
+            transfer_account.to_account_info().lamports() >= required_lamports,
+            TransferError::EscrowBelowRentReserve
+        );
+    } else {
+        // SPL Token transfer to escrow
+        let sender_token_account = funding
+            .sender_token_account
+            .ok_or(TransferError::InvalidTokenAccount)?;
+        let escrow_token_account = funding
+            .escrow_token_account
+            .ok_or(TransferError::InvalidEscrowAccount)?;
+        let token_program = funding
+            .token_program
+            .ok_or(TransferError::InvalidTokenAccount)?;
+        let transfer_instruction = Transfer {
+            from: sender_token_account,
+            to: escrow_token_account,
+            authority: funding.sender,
+        };
+
+        token::transfer(CpiContext::new(token_program, transfer_instruction), escrow_amount)?;
+    }
+
+    Ok(())
+}
+
+/// Link a newly scheduled transfer into whichever trackers were passed, then
+/// announce it
+fn record_scheduled<'info>(
+    transfer_account: &mut Account<'info, ScheduledTransfer>,
+    registry: Option<&mut Account<'info, SenderRegistry>>,
+    mint_stats: Option<&mut Account<'info, MintStats>>,
+    campaign_stats: Option<&mut Account<'info, CampaignStats>>,
+    config: Option<&ProgramConfig>,
+    escrow_amount: u64,
+    slot: u64,
+) -> Result<()> {
+    // Push onto the head of the sender's transfer list
+    if let Some(registry) = registry {
+        transfer_account.next = registry.head;
+        transfer_account.in_registry = true;
+        registry.head = Some(transfer_account.key());
+        registry.count = registry
+            .count
+            .checked_add(1)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+    }
+
+    // Unconfirmed transfers are counted once `confirm_schedule` funds them
+    if !transfer_account.pending_confirmation {
+        count_escrow(transfer_account, mint_stats, campaign_stats, escrow_amount)?;
+    }
+
+    if verbose_events(config) {
+        emit!(TransferScheduled {
+            transfer_id: transfer_account.key(),
+            sender: transfer_account.sender,
+            recipient: transfer_account.recipient,
+            amount: transfer_account.amount,
+            token_mint: transfer_account.token_mint,
+            execute_after: transfer_account.execute_after,
+            nonce: transfer_account.nonce,
+            campaign_id: transfer_account.campaign_id,
+            refund_of: transfer_account.refund_of,
+            slot,
+        });
+    } else {
+        emit!(TransferStatusChanged {
+            transfer_id: transfer_account.key(),
+            status: TransferStatus::Scheduled,
+        });
+    }
+    if config.map_or(false, |config| is_large_transfer(config, transfer_account.amount)) {
+        emit!(LargeTransferScheduled {
+            transfer_id: transfer_account.key(),
+            sender: transfer_account.sender,
+            amount: transfer_account.amount,
+            token_mint: transfer_account.token_mint,
+            execute_after: transfer_account.execute_after,
+            slot,
+        });
+    }
+
+    Ok(())
+}
+
+/// Count a funded escrow towards its mint's reserves and attribute the
+/// transfer to its campaign
+fn count_escrow<'info>(
+    transfer_account: &mut Account<'info, ScheduledTransfer>,
+    mint_stats: Option<&mut Account<'info, MintStats>>,
+    campaign_stats: Option<&mut Account<'info, CampaignStats>>,
+    escrow_amount: u64,
+) -> Result<()> {
+    if let Some(mint_stats) = mint_stats {
+        transfer_account.in_mint_stats = true;
+        mint_stats.total_outstanding = mint_stats
+            .total_outstanding
+            .checked_add(escrow_amount)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+    }
+
+    if let Some(campaign_stats) = campaign_stats {
+        require!(
+            campaign_stats.campaign_id == transfer_account.campaign_id,
+            TransferError::CampaignMismatch
+        );
+        campaign_stats.scheduled_count = campaign_stats
+            .scheduled_count
+            .checked_add(1)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        campaign_stats.scheduled_volume = campaign_stats
+            .scheduled_volume
+            .checked_add(escrow_amount)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+    }
+
+    Ok(())
+}
+
+/// Validate, initialize and fund a new scheduled transfer
+fn process_schedule(
+    mut accounts: ScheduleAccounts,
+    bump: u8,
+    params: ScheduleTransferParams,
+    memo_capacity: usize,
+) -> Result<()> {
+    let ScheduleTransferParams {
+        amount,
+        execute_after,
+        nonce,
+        memo,
+        allowed_executors,
+        occurrences,
+        interval_seconds,
+        allow_recipient_reassignment,
+        fallback_recipient,
+        fallback_after,
+        emit_spl_memo,
+        condition_program,
+        keeper_fee_lamports,
+        refund_if_recipient_frozen,
+        mint_receipt,
+        irrevocable,
+        max_execution_attempts,
+        output_mint,
+        min_out,
+        escalation_bps,
+        recovery_key,
+        recovery_available_after,
+        require_confirmation,
+        campaign_id,
+        monthly,
+        refund_of,
+        sweep_all,
+        recipient_is_pda,
+        require_ack,
+        keeper_fee_bps,
+        keeper_fee_ceiling_lamports,
+    } = params;
+    let transfer_account = accounts.transfer_account;
+    let sender = accounts.sender;
+    let clock = Clock::get()?;
+
+    // An SPL transfer must name a real mint; anything else would be stored and
+    // only fail, cryptically, at execution
+    if accounts.token_mint.key() != System::id() {
+        let is_mint = *accounts.token_mint.owner == token::ID
+            && Mint::try_deserialize(&mut &accounts.token_mint.try_borrow_data()?[..]).is_ok();
+        if !is_mint {
+            msg!(
+                "Token mint is not an SPL mint: mint={} owner={}",
+                accounts.token_mint.key(),
+                accounts.token_mint.owner
+            );
+            return err!(TransferError::InvalidTokenMint);
+        }
+    }
+
+    // A sweep escrows everything the sender can spare, fixed at schedule time
+    let amount = if sweep_all {
+        require!(amount == AmountSpec::Absolute(0), TransferError::SweepAmountConflict);
+        require!(
+            accounts.token_mint.key() == System::id(),
+            TransferError::InvalidTokenMint
+        );
+        require!(occurrences == 1, TransferError::InvalidOccurrences);
+        sweep_amount(sender, keeper_fee_lamports)?
+    } else {
+        resolve_amount(
+            amount,
+            sender,
+            accounts.sender_token_account.as_ref(),
+            &accounts.token_mint.key(),
+            keeper_fee_lamports,
+        )?
+    };
+
+    // A percentage keeper fee is `keeper_fee_bps` of the amount, clamped between
+    // the flat fee and the ceiling. It is paid in lamports, so SOL only.
+    let keeper_fee_lamports = if keeper_fee_bps > 0 {
+        require!(
+            keeper_fee_bps <= 10_000
+                && keeper_fee_ceiling_lamports >= keeper_fee_lamports
+                && accounts.token_mint.key() == System::id()
+                && !sweep_all,
+            TransferError::InvalidKeeperFee
+        );
+        let scaled = (amount as u128 * keeper_fee_bps as u128 / 10_000) as u64;
+        scaled.min(keeper_fee_ceiling_lamports).max(keeper_fee_lamports)
+    } else {
+        keeper_fee_lamports
+    };
+
+    // Security validations
+    validate_schedule(
+        &sender.key(),
+        &accounts.recipient.key(),
+        amount,
+        execute_after,
+        &memo,
+        memo_capacity,
+        keeper_fee_lamports,
+        accounts.config.as_deref(),
+        clock.unix_timestamp,
+    )?;
+    check_derived_nonce(
+        accounts.config.as_deref(),
+        &sender.key(),
+        &accounts.recipient.key(),
+        amount,
+        execute_after,
+        &nonce,
+    )?;
+    validate_recipient(
+        accounts.denylist,
+        accounts.recipient_policy,
+        accounts.recipient,
+        &accounts.token_mint.key(),
+        recipient_is_pda,
+        clock.unix_timestamp,
+    )?;
+    // Acknowledged transfers pay out once, directly, with nothing owed to keepers
+    require!(
+        !require_ack
+            || (occurrences == 1
+                && output_mint.is_none()
+                && !mint_receipt
+                && keeper_fee_lamports == 0),
+        TransferError::AckIncompatible
+    );
+    // A refund must point back at a transfer in the opposite direction
+    if let Some(original) = accounts.refund_of_transfer {
+        require!(
+            refund_of == Some(original.key())
+                && original.sender == accounts.recipient.key()
+                && original.recipient == sender.key(),
+            TransferError::InvalidRefundReference
+        );
+    }
+    require!(
+        allowed_executors.len() <= MAX_ALLOWED_EXECUTORS,
+        TransferError::TooManyExecutors
+    );
+
+    // Recurring transfers escrow every occurrence up front
+    require!(occurrences > 0, TransferError::InvalidOccurrences);
+    // Monthly recurrences follow the calendar instead of a fixed interval
+    require!(
+        occurrences == 1 || interval_seconds > 0 || monthly,
+        TransferError::InvalidInterval
+    );
+    require!(!monthly || interval_seconds == 0, TransferError::InvalidInterval);
+    let escrow_amount =
+        ScheduledTransfer::escalated_total(amount, escalation_bps, occurrences as u64)?;
+    let keeper_fee_escrow = keeper_fee_lamports
+        .checked_mul(occurrences as u64)
+        .ok_or(TransferError::ArithmeticOverflow)?;
+
+    // A fallback recipient only takes over after the primary has had a chance to execute
+    if fallback_recipient.is_some() {
+        require!(
+            fallback_after > execute_after,
+            TransferError::InvalidFallbackTime
+        );
+    }
+
+    // Swapped payouts need an SPL escrow and an explicit slippage floor
+    if output_mint.is_some() {
+        require!(
+            accounts.token_mint.key() != System::id(),
+            TransferError::InvalidTokenMint
+        );
+        require!(min_out > 0, TransferError::InvalidAmount);
+    }
+
+    // Initialize the scheduled transfer
+    transfer_account.sender = sender.key();
+    transfer_account.recipient = accounts.recipient.key();
+    transfer_account.amount = amount;
+    transfer_account.token_mint = accounts.token_mint.key();
+    transfer_account.execute_after = execute_after;
+    transfer_account.created_at = clock.unix_timestamp;
+    transfer_account.created_slot = clock.slot;
+    transfer_account.program_version = PROGRAM_VERSION;
+    transfer_account.last_modified_at = clock.unix_timestamp;
+    transfer_account.executed = false;
+    transfer_account.cancelled = false;
+    transfer_account.unexecutable = false;
+    transfer_account.migrated = false;
+    transfer_account.require_ack = require_ack;
+    transfer_account.ack_deadline = 0;
+    transfer_account.ack_payee = Pubkey::default();
+    transfer_account.nonce = nonce;
+    transfer_account.memo = memo;
+    transfer_account.allowed_executors = allowed_executors;
+    transfer_account.total_occurrences = occurrences;
+    transfer_account.executed_occurrences = 0;
+    transfer_account.interval_seconds = interval_seconds;
+    transfer_account.monthly_anchor = if monthly { execute_after } else { 0 };
+    transfer_account.allow_recipient_reassignment = allow_recipient_reassignment;
+    transfer_account.fallback_recipient = fallback_recipient;
+    transfer_account.fallback_after = fallback_after;
+    transfer_account.emit_spl_memo = emit_spl_memo;
+    transfer_account.condition_program = condition_program;
+    transfer_account.keeper_fee_lamports = keeper_fee_lamports;
+    transfer_account.refund_if_recipient_frozen = refund_if_recipient_frozen;
+    transfer_account.mint_receipt = mint_receipt;
+    transfer_account.irrevocable = irrevocable;
+    transfer_account.max_execution_attempts = max_execution_attempts;
+    transfer_account.execution_attempts = 0;
+    transfer_account.output_mint = output_mint;
+    transfer_account.min_out = min_out;
+    transfer_account.escalation_bps = escalation_bps;
+    transfer_account.recovery_key = recovery_key;
+    transfer_account.recovery_available_after = recovery_available_after;
+    transfer_account.campaign_id = campaign_id;
+    transfer_account.refund_of = refund_of;
+    transfer_account.authority = sender.key();
+    transfer_account.next = None;
+    transfer_account.in_registry = false;
+    transfer_account.in_mint_stats = false;
+    transfer_account.in_global_pending = !require_confirmation
+        && count_global_pending(accounts.config.as_deref_mut())?;
+    bump_event_seq(accounts.config.as_deref_mut())?;
+    transfer_account.bump = bump;
+
+    // The SOL escrow shares the transfer account, so remember the reserve
+    // that must stay behind when lamports are paid out
+    let rent_reserve =
+        Rent::get()?.minimum_balance(transfer_account.to_account_info().data_len());
+    transfer_account.rent_reserve = rent_reserve;
+
+    // Opted-in transfers only record the intent; `confirm_schedule` funds them
+    transfer_account.pending_confirmation = require_confirmation;
+    transfer_account.execution_authorized_until = 0;
+    if !require_confirmation {
+        fund_escrow(
+            transfer_account,
+            EscrowFunding {
+                sender: sender.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+                sender_token_account: accounts.sender_token_account,
+                escrow_token_account: accounts.escrow_token_account,
+                token_program: accounts.token_program,
+            },
+            escrow_amount,
+            keeper_fee_escrow,
+        )?;
+    }
+
+    if transfer_account.emit_spl_memo && !transfer_account.memo.is_empty() {
+        log_spl_memo(&accounts.memo_program, &transfer_account.memo)?;
+    }
+
+    record_scheduled(
+        transfer_account,
+        accounts.registry,
+        accounts.mint_stats,
+        accounts.campaign_stats,
+        accounts.config.as_deref(),
+        escrow_amount,
+        clock.slot,
+    )
+}
+
+#[program]
+pub mod scheduled_transfer {
+    use super::*;
+
+    /// Schedule a transfer to be executed after a specific timestamp
+    pub fn schedule_transfer(
+        ctx: Context<ScheduleTransfer>,
+        params: ScheduleTransferParams,
+    ) -> Result<()> {
+        let bump = *ctx.bumps.get("transfer_account").unwrap();
+        let accounts = ctx.accounts;
+        process_schedule(
+            ScheduleAccounts {
+                transfer_account: &mut accounts.transfer_account,
+                sender: &accounts.sender,
+                recipient: &accounts.recipient,
+                token_mint: &accounts.token_mint,
+                config: accounts.config.as_deref_mut(),
+                denylist: &accounts.denylist,
+                recipient_policy: &accounts.recipient_policy,
+                registry: accounts.registry.as_mut(),
+                mint_stats: accounts.mint_stats.as_mut(),
+                campaign_stats: accounts.campaign_stats.as_mut(),
+                refund_of_transfer: accounts.refund_of_transfer.as_ref(),
+                sender_token_account: accounts
+                    .sender_token_account
+                    .as_ref()
+                    .map(|account| account.to_account_info()),
+                escrow_token_account: accounts
+                    .escrow_token_account
+                    .as_ref()
+                    .map(|account| account.to_account_info()),
+                token_program: accounts
+                    .token_program
+                    .as_ref()
+                    .map(|program| program.to_account_info()),
+                system_program: &accounts.system_program,
+                memo_program: accounts.memo_program.clone(),
+            },
+            bump,
+            params,
+            DEFAULT_MEMO_CAPACITY,
+        )
+    }
+
+    /// Schedule an SPL transfer into an escrow ATA the caller already created,
+    /// skipping the `init_if_needed` path
+    pub fn schedule_transfer_with_escrow(
+        ctx: Context<ScheduleTransferWithEscrow>,
+        params: ScheduleTransferParams,
+    ) -> Result<()> {
+        let bump = *ctx.bumps.get("transfer_account").unwrap();
+        let accounts = ctx.accounts;
+        process_schedule(
+            ScheduleAccounts {
+                transfer_account: &mut accounts.transfer_account,
+                sender: &accounts.sender,
+                recipient: &accounts.recipient,
+                token_mint: &accounts.token_mint,
+                config: accounts.config.as_deref_mut(),
+                denylist: &accounts.denylist,
+                recipient_policy: &accounts.recipient_policy,
+                registry: accounts.registry.as_mut(),
+                mint_stats: accounts.mint_stats.as_mut(),
+                campaign_stats: accounts.campaign_stats.as_mut(),
+                refund_of_transfer: accounts.refund_of_transfer.as_ref(),
+                sender_token_account: Some(accounts.sender_token_account.to_account_info()),
+                escrow_token_account: Some(accounts.escrow_token_account.to_account_info()),
+                token_program: Some(accounts.token_program.to_account_info()),
+                system_program: &accounts.system_program,
+                memo_program: accounts.memo_program.clone(),
+            },
+            bump,
+            params,
+            DEFAULT_MEMO_CAPACITY,
+        )
+    }
+
+    /// Schedule several SOL transfers at once. For each entry in `params`, in the
+    /// same order, `remaining_accounts` holds the writable transfer PDA, the
+    /// recipient and the recipient's writable policy PDA. Batched recipients
+    /// must be wallets, not program-owned accounts.
+    pub fn batch_schedule<'info>(
+        ctx: Context<'_, '_, '_, 'info, BatchSchedule<'info>>,
+        params: Vec<ScheduleParams>,
+    ) -> Result<()> {
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+        let rent = Rent::get()?;
+
+        require!(
+            !params.is_empty() && params.len() <= MAX_BATCH_SIZE,
+            TransferError::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() == params.len() * 3,
+            TransferError::InvalidBatchSize
+        );
+
+        for (entry, entry_accounts) in params.into_iter().zip(ctx.remaining_accounts.chunks(3)) {
+            let [transfer_info, recipient_info, recipient_policy] = entry_accounts else {
+                return err!(TransferError::InvalidBatchSize);
+            };
+            validate_schedule(
+                &sender.key(),
+                &entry.recipient,
+                entry.amount,
+                entry.execute_after,
+                &entry.memo,
+                DEFAULT_MEMO_CAPACITY,
+                0,
+                ctx.accounts.config.as_deref(),
+                clock.unix_timestamp,
+            )?;
+            check_derived_nonce(
+                ctx.accounts.config.as_deref(),
+                &sender.key(),
+                &entry.recipient,
+                entry.amount,
+                entry.execute_after,
+                &entry.nonce,
+            )?;
+            require_keys_eq!(
+                recipient_info.key(),
+                entry.recipient,
+                TransferError::InvalidRecipient
+            );
+            let (expected_policy, _) = Pubkey::find_program_address(
+                &[b"recipient_policy", entry.recipient.as_ref()],
+                ctx.program_id,
+            );
+            require_keys_eq!(
+                recipient_policy.key(),
+                expected_policy,
+                TransferError::InvalidRecipient
+            );
+            validate_recipient(
+                &ctx.accounts.denylist,
+                recipient_policy,
+                recipient_info,
+                &System::id(),
+                false,
+                clock.unix_timestamp,
+            )?;
+
+            let (expected_address, bump) = Pubkey::find_program_address(
+                &[b"transfer", sender.key().as_ref(), entry.nonce.as_ref()],
+                ctx.program_id,
+            );
+            require_keys_eq!(
+                transfer_info.key(),
+                expected_address,
+                TransferError::InvalidTransferAccount
+            );
+
+            // Creating the PDA fails if the nonce was already used, as with `init`
+            let space = compute_space(&entry);
+            let rent_reserve = rent.minimum_balance(space);
+            let lamports = rent_reserve
+                .checked_add(entry.amount)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+            check_sender_funds(&sender.to_account_info(), None, true, lamports, 0)?;
+            anchor_lang::system_program::create_account(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::CreateAccount {
+                        from: sender.to_account_info(),
+                        to: transfer_info.clone(),
+                    },
+                    &[&[b"transfer", sender.key().as_ref(), entry.nonce.as_ref(), &[bump]]],
+                ),
+                lamports,
+                space as u64,
+                ctx.program_id,
+            )?;
+
+            let transfer = ScheduledTransfer {
+                sender: sender.key(),
+                recipient: entry.recipient,
+                amount: entry.amount,
+                token_mint: System::id(),
+                execute_after: entry.execute_after,
+                created_at: clock.unix_timestamp,
+                created_slot: clock.slot,
+                program_version: PROGRAM_VERSION,
+                last_modified_at: clock.unix_timestamp,
+                nonce: entry.nonce,
+                memo: entry.memo,
+                total_occurrences: 1,
+                rent_reserve,
+                in_mint_stats: ctx.accounts.mint_stats.is_some(),
+                in_global_pending: count_global_pending(ctx.accounts.config.as_deref_mut())?,
+                authority: sender.key(),
+                bump,
+                ..ScheduledTransfer::default()
+            };
+            bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+            if let Some(mint_stats) = ctx.accounts.mint_stats.as_mut() {
+                mint_stats.total_outstanding = mint_stats
+                    .total_outstanding
+                    .checked_add(entry.amount)
+                    .ok_or(TransferError::ArithmeticOverflow)?;
+            }
+            transfer.try_serialize(&mut &mut transfer_info.try_borrow_mut_data()?[..])?;
+
+            if verbose_events(ctx.accounts.config.as_deref()) {
+                emit!(TransferScheduled {
+                    transfer_id: transfer_info.key(),
+                    sender: sender.key(),
+                    recipient: entry.recipient,
+                    amount: entry.amount,
+                    token_mint: System::id(),
+                    execute_after: entry.execute_after,
+                    nonce: entry.nonce,
+                    campaign_id: 0,
+                    refund_of: None,
+                    slot: clock.slot,
+                });
+            } else {
+                emit!(TransferStatusChanged {
+                    transfer_id: transfer_info.key(),
+                    status: TransferStatus::Scheduled,
+                });
+            }
+            let large = ctx
+                .accounts
+                .config
+                .as_ref()
+                .map_or(false, |config| is_large_transfer(config, entry.amount));
+            if large {
+                emit!(LargeTransferScheduled {
+                    transfer_id: transfer_info.key(),
+                    sender: sender.key(),
+                    amount: entry.amount,
+                    token_mint: System::id(),
+                    execute_after: entry.execute_after,
+                    slot: clock.slot,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Let `delegate` schedule up to `budget` of the owner's tokens. Approves the
+    /// delegation PDA on `owner_token_account`, replacing any earlier SPL approval.
+    /// SPL tokens only: lamports cannot leave the owner's account without their
+    /// signature.
+    pub fn create_delegation(ctx: Context<CreateDelegation>, budget: u64) -> Result<()> {
+        require!(budget > 0, TransferError::InvalidAmount);
+        let delegation = &mut ctx.accounts.delegation;
+
+        delegation.owner = ctx.accounts.owner.key();
+        delegation.delegate = ctx.accounts.delegate.key();
+        delegation.mint = ctx.accounts.owner_token_account.mint;
+        delegation.remaining_budget = budget;
+        delegation.bump = *ctx.bumps.get("delegation").unwrap();
+
+        token::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Approve {
+                    to: ctx.accounts.owner_token_account.to_account_info(),
+                    delegate: delegation.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            budget,
+        )?;
+
+        Ok(())
+    }
+
+    /// Schedule a one-off transfer whose account holds up to `memo_capacity`
+    /// memo bytes (at most `MAX_MEMO_CAPACITY`) instead of the default 200, e.g.
+    /// for invoice JSON; the sender pays the rent for the larger account
+    pub fn schedule_transfer_large_memo(
+        ctx: Context<ScheduleTransferLargeMemo>,
+        amount: u64,
+        execute_after: i64,
+        nonce: [u8; 32],
+        memo: String,
+        memo_capacity: u16,
+    ) -> Result<()> {
+        let memo_capacity = memo_capacity as usize;
+        if memo_capacity > MAX_MEMO_CAPACITY {
+            msg!("Memo capacity too large: capacity={} max={}", memo_capacity, MAX_MEMO_CAPACITY);
+            return err!(TransferError::MemoTooLong);
+        }
+
+        let bump = *ctx.bumps.get("transfer_account").unwrap();
+        let accounts = ctx.accounts;
+        process_schedule(
+            ScheduleAccounts {
+                transfer_account: &mut accounts.transfer_account,
+                sender: &accounts.sender,
+                recipient: &accounts.recipient,
+                token_mint: &accounts.token_mint,
+                config: accounts.config.as_deref_mut(),
+                denylist: &accounts.denylist,
+                recipient_policy: &accounts.recipient_policy,
+                registry: accounts.registry.as_mut(),
+                mint_stats: accounts.mint_stats.as_mut(),
+                campaign_stats: None,
+                refund_of_transfer: None,
+                sender_token_account: accounts
+                    .sender_token_account
+                    .as_ref()
+                    .map(|account| account.to_account_info()),
+                escrow_token_account: accounts
+                    .escrow_token_account
+                    .as_ref()
+                    .map(|account| account.to_account_info()),
+                token_program: accounts
+                    .token_program
+                    .as_ref()
+                    .map(|program| program.to_account_info()),
+                system_program: &accounts.system_program,
+                memo_program: None,
+            },
+            bump,
+            ScheduleTransferParams {
+                amount: AmountSpec::Absolute(amount),
+                execute_after,
+                nonce,
+                memo,
+                ..Default::default()
+            },
+            memo_capacity,
+        )
+    }
+
+    /// Schedule a one-off SPL transfer on the owner's behalf (delegate only). The
+    /// tokens escrow from the owner's account and the transfer belongs to the
+    /// owner, who alone can cancel it.
+    pub fn schedule_transfer_for(
+        ctx: Context<ScheduleTransferFor>,
+        amount: u64,
+        execute_after: i64,
+        nonce: [u8; 32],
+        memo: String,
+        campaign_id: u64,
+    ) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let recipient = ctx.accounts.recipient.key();
+        let clock = Clock::get()?;
+
+        validate_schedule(
+            &owner,
+            &recipient,
+            amount,
+            execute_after,
+            &memo,
+            DEFAULT_MEMO_CAPACITY,
+            0,
+            ctx.accounts.config.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        check_derived_nonce(
+            ctx.accounts.config.as_deref(),
+            &owner,
+            &recipient,
+            amount,
+            execute_after,
+            &nonce,
+        )?;
+        validate_recipient(
+            &ctx.accounts.denylist,
+            &ctx.accounts.recipient_policy,
+            &ctx.accounts.recipient,
+            &ctx.accounts.token_mint.key(),
+            false,
+            clock.unix_timestamp,
+        )?;
+
+        let delegation = &mut ctx.accounts.delegation;
+        if amount > delegation.remaining_budget {
+            msg!(
+                "Delegation budget exceeded: amount={} remaining={}",
+                amount,
+                delegation.remaining_budget
+            );
+            return err!(TransferError::DelegationBudgetExceeded);
+        }
+        delegation.remaining_budget -= amount;
+        bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let rent_reserve =
+            Rent::get()?.minimum_balance(transfer_account.to_account_info().data_len());
+        transfer_account.set_inner(ScheduledTransfer {
+            sender: owner,
+            recipient,
+            amount,
+            token_mint: ctx.accounts.token_mint.key(),
+            execute_after,
+            created_at: clock.unix_timestamp,
+            created_slot: clock.slot,
+            program_version: PROGRAM_VERSION,
+            last_modified_at: clock.unix_timestamp,
+            nonce,
+            memo,
+            total_occurrences: 1,
+            rent_reserve,
+            campaign_id,
+            in_global_pending: count_global_pending(ctx.accounts.config.as_deref_mut())?,
+            authority: owner,
+            bump: *ctx.bumps.get("transfer_account").unwrap(),
+            ..ScheduledTransfer::default()
+        });
+
+        let delegate = ctx.accounts.delegate.key();
+        let seeds = &[
+            b"delegation",
+            owner.as_ref(),
+            delegate.as_ref(),
+            &[delegation.bump],
+        ];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.owner_token_account.to_account_info(),
+                    to: ctx.accounts.escrow_token_account.to_account_info(),
+                    authority: delegation.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+        )?;
+
+        record_scheduled(
+            transfer_account,
+            ctx.accounts.registry.as_mut(),
+            ctx.accounts.mint_stats.as_mut(),
+            ctx.accounts.campaign_stats.as_mut(),
+            ctx.accounts.config.as_deref(),
+            amount,
+            clock.slot,
+        )
+    }
+
+    /// Execute a scheduled transfer after the execution time has passed
+    pub fn execute_scheduled_transfer<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteScheduledTransfer<'info>>,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let clock = Clock::get()?;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(!transfer_account.pending_confirmation, TransferError::NotConfirmed);
+        require!(transfer_account.arbiter.is_none(), TransferError::MilestoneGated);
+        require!(transfer_account.ack_deadline == 0, TransferError::AckPending);
+        if clock.unix_timestamp < transfer_account.execute_after {
+            msg!(
+                "Execution time not reached: now={} execute_after={}",
+                clock.unix_timestamp,
+                transfer_account.execute_after
+            );
+            return err!(TransferError::ExecutionTimeNotReached);
+        }
+
+        // The sender's registry must come along whenever it exists, so its
+        // freeze also covers transfers scheduled without it
+        if let Some(registry) = ctx.accounts.registry.as_ref() {
+            if clock.unix_timestamp < registry.frozen_until {
+                msg!("Sender frozen: frozen_at={}", registry.frozen_at);
+                return err!(TransferError::SenderFrozen);
+            }
+        }
+        transfer_account.last_modified_at = clock.unix_timestamp;
+
+        // Funds must have actually sat in escrow for the configured floor,
+        // however early `execute_after` was set
+        if let Some(config) = ctx.accounts.config.as_ref() {
+            let escrowed_until = transfer_account
+                .created_at
+                .checked_add(config.min_escrow_seconds)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+            if clock.unix_timestamp < escrowed_until {
+                msg!(
+                    "Minimum escrow not elapsed: now={} escrowed_until={}",
+                    clock.unix_timestamp,
+                    escrowed_until
+                );
+                return err!(TransferError::MinEscrowNotElapsed);
+            }
+        }
+
+        // Recurring transfers advance `execute_after` below; rebates use this due time
+        let due_at = transfer_account.execute_after;
+
+        // Past the fallback deadline the fallback recipient is paid instead
+        let payee = transfer_account.payee(clock.unix_timestamp);
+
+        // Verify recipient matches
+        if payee != ctx.accounts.recipient.key() {
+            msg!(
+                "Recipient mismatch: expected={} provided={}",
+                payee,
+                ctx.accounts.recipient.key()
+            );
+            return err!(TransferError::InvalidRecipient);
+        }
+
+        // The payee may have been denied after the transfer was scheduled
+        check_recipient_allowed(&ctx.accounts.denylist, &payee)?;
+
+        // A transfer that whitelists keepers may only be executed by them, whatever
+        // the config's execution mode. Otherwise the payee must sign (or has
+        // authorized any keeper until `execution_authorized_until`) unless the
+        // config opens execution to anyone or restricts it to whitelists.
+        let executor = ctx.accounts.executor.key();
+        let authorized_until = transfer_account.execution_authorized_until;
+        let now = clock.unix_timestamp;
+        let execution_mode = ctx
+            .accounts
+            .config
+            .as_ref()
+            .map_or(ExecutionMode::RecipientOnly, |config| config.execution_mode);
+        let recipient_only = execution_mode == ExecutionMode::RecipientOnly;
+        let executor_allowed = if !transfer_account.allowed_executors.is_empty() {
+            transfer_account.allowed_executors.contains(&executor)
+        } else {
+            match execution_mode {
+                ExecutionMode::Permissionless => true,
+                ExecutionMode::AllowlistOnly => false,
+                ExecutionMode::RecipientOnly => {
+                    executor == payee
+                        || (payee == transfer_account.recipient && now <= authorized_until)
+                }
+            }
+        };
+        if !executor_allowed {
+            if recipient_only
+                && transfer_account.allowed_executors.is_empty()
+                && authorized_until > 0
+                && now > authorized_until
+            {
+                msg!("Execution authorization expired at {}", authorized_until);
+                return err!(TransferError::AuthorizationExpired);
+            }
+            msg!("Executor not allowed: executor={}", executor);
+            return err!(TransferError::ExecutorNotAllowed);
+        }
+
+        // Custom conditions are evaluated by the integrator's own program. With an
+        // attempt limit, a failed check is recorded (not reverted) so the limit
+        // can be reached; "not due yet" above never counts as an attempt.
+        let mut attempts_exhausted = false;
+        if let Some(condition_program) = transfer_account.condition_program {
+            // Keepers wait out the backoff after a failed check instead of piling on
+            if clock.unix_timestamp < transfer_account.next_attempt_allowed_after {
+                msg!(
+                    "Retry too soon: now={} next_attempt_allowed_after={}",
+                    clock.unix_timestamp,
+                    transfer_account.next_attempt_allowed_after
+                );
+                return err!(TransferError::RetryTooSoon);
+            }
+            let approved = custom_condition_approved(
+                &transfer_account.to_account_info(),
+                &ctx.accounts.condition_program,
+                condition_program,
+            )?;
+            if !approved {
+                require!(
+                    transfer_account.max_execution_attempts > 0,
+                    TransferError::CustomConditionFailed
+                );
+                transfer_account.execution_attempts = transfer_account
+                    .execution_attempts
+                    .checked_add(1)
+                    .ok_or(TransferError::ArithmeticOverflow)?;
+                msg!(
+                    "Condition failed: attempt {} of {}",
+                    transfer_account.execution_attempts,
+                    transfer_account.max_execution_attempts
+                );
+                if transfer_account.execution_attempts < transfer_account.max_execution_attempts {
+                    let backoff = ctx
+                        .accounts
+                        .config
+                        .as_ref()
+                        .map_or(0, |config| config.condition_retry_backoff_seconds);
+                    transfer_account.next_attempt_allowed_after =
+                        clock.unix_timestamp.saturating_add(backoff);
+                    return Ok(());
+                }
+                attempts_exhausted = true;
+            }
+        }
+
+        // A frozen recipient token account would fail the SPL transfer opaquely;
+        // abort clearly or, if the sender opted in, cancel and refund instead
+        let recipient_frozen = transfer_account.token_mint != System::id()
+            && ctx
+                .accounts
+                .recipient_token_account
+                .as_ref()
+                .map_or(false, |account| account.is_frozen());
+        if recipient_frozen && !attempts_exhausted {
+            require!(
+                transfer_account.refund_if_recipient_frozen,
+                TransferError::RecipientAccountFrozen
+            );
+        }
+
+        if attempts_exhausted || recipient_frozen {
+            let refund_amount = transfer_account.remaining_escrow()?;
+            let refund_keeper_fees = transfer_account.remaining_keeper_fees()?;
+
+            release_outstanding(transfer_account, &mut ctx.accounts.mint_stats, refund_amount)?;
+            release_global_pending(transfer_account, ctx.accounts.config.as_deref_mut())?;
+            bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+            transfer_account.cancelled = true;
+            transfer_account.cancelled_at = clock.unix_timestamp;
+            unlink_transfer(
+                transfer_account,
+                &mut ctx.accounts.registry,
+                &mut ctx.accounts.previous_transfer,
+            )?;
+
+            // SOL escrow and keeper fees share the transfer account
+            let mut refund_lamports = refund_keeper_fees;
+            if transfer_account.token_mint == System::id() {
+                refund_lamports = refund_lamports
+                    .checked_add(refund_amount)
+                    .ok_or(TransferError::ArithmeticOverflow)?;
+            } else {
+                let seeds = &[
+                    b"transfer",
+                    transfer_account.sender.as_ref(),
+                    transfer_account.nonce.as_ref(),
+                    &[transfer_account.bump],
+                ];
+                let signer = &[&seeds[..]];
+
+                let escrow_token_account = ctx
+                    .accounts
+                    .escrow_token_account
+                    .as_ref()
+                    .ok_or(TransferError::InvalidEscrowAccount)?;
+                let sender_token_account = ctx
+                    .accounts
+                    .sender_token_account
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                let transfer_instruction = Transfer {
+                    from: escrow_token_account.to_account_info(),
+                    to: sender_token_account.to_account_info(),
+                    authority: transfer_account.to_account_info(),
+                };
+
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        transfer_instruction,
+                        signer,
+                    ),
+                    refund_amount,
+                )?;
+            }
+
+            if refund_lamports > 0 {
+                let sender = ctx
+                    .accounts
+                    .sender
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                debit_sol_escrow(
+                    &transfer_account.to_account_info(),
+                    sender,
+                    transfer_account.rent_reserve,
+                    refund_lamports,
+                )?;
+            }
+
+            if attempts_exhausted {
+                emit!(MaxAttemptsExceeded {
+                    transfer_id: transfer_account.key(),
+                    attempts: transfer_account.execution_attempts,
+                    refunded: refund_amount,
+                    slot: clock.slot,
+                });
+            }
+
+            if verbose_events(ctx.accounts.config.as_deref()) {
+                emit!(TransferCancelled {
+                    transfer_id: transfer_account.key(),
+                    sender: transfer_account.sender,
+                    amount: refund_amount,
+                    cancelled_at: transfer_account.cancelled_at,
+                    slot: clock.slot,
+                });
+            } else {
+                emit!(TransferStatusChanged {
+                    transfer_id: transfer_account.key(),
+                    status: TransferStatus::Cancelled,
+                });
+            }
+
+            return Ok(());
+        }
+
+        // Hold the funds in this transfer's escrow; the payee cannot move them
+        // until they acknowledge receipt
+        if transfer_account.require_ack {
+            transfer_account.ack_payee = payee;
+            transfer_account.ack_deadline = clock
+                .unix_timestamp
+                .checked_add(ACK_WINDOW_SECONDS)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+
+            emit!(AcknowledgmentRequested {
+                transfer_id: transfer_account.key(),
+                payee,
+                held_amount: transfer_account.amount,
+                ack_deadline: transfer_account.ack_deadline,
+                slot: clock.slot,
+            });
+
+            return Ok(());
+        }
+
+        // Record the occurrence before transfer to prevent reentrancy
+        let paid_amount = transfer_account.amount;
+        release_outstanding(transfer_account, &mut ctx.accounts.mint_stats, paid_amount)?;
+        bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+        if let Some(campaign_stats) = ctx.accounts.campaign_stats.as_mut() {
+            campaign_stats.executed_count = campaign_stats
+                .executed_count
+                .checked_add(1)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+            campaign_stats.executed_volume = campaign_stats
+                .executed_volume
+                .checked_add(paid_amount)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+        }
+        transfer_account.executed_occurrences = transfer_account
+            .executed_occurrences
+            .checked_add(1)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        if transfer_account.executed_occurrences == transfer_account.total_occurrences {
+            transfer_account.executed = true;
+            transfer_account.executed_at = clock.unix_timestamp;
+            release_global_pending(transfer_account, ctx.accounts.config.as_deref_mut())?;
+            unlink_transfer(
+                transfer_account,
+                &mut ctx.accounts.registry,
+                &mut ctx.accounts.previous_transfer,
+            )?;
+        } else {
+            transfer_account.execute_after = transfer_account.next_execute_after()?;
+            transfer_account.amount =
+                ScheduledTransfer::escalate(paid_amount, transfer_account.escalation_bps)?;
+        }
+
+        // Execute the transfer
+        if transfer_account.token_mint == System::id() {
+            // SOL transfer from escrow
+            debit_sol_escrow(
+                &transfer_account.to_account_info(),
+                &ctx.accounts.recipient.to_account_info(),
+                transfer_account.rent_reserve,
+                paid_amount,
+            )?;
+
+        } else {
+            // SPL Token transfer from escrow
+            let seeds = &[
+                b"transfer",
+                transfer_account.sender.as_ref(),
+                transfer_account.nonce.as_ref(),
+                &[transfer_account.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            if let Some(output_mint) = transfer_account.output_mint {
+                // Convert through the admin-configured swap program, then check
+                // what actually arrived against the slippage floor
+                let swap_program = ctx
+                    .accounts
+                    .swap_program
+                    .as_ref()
+                    .ok_or(TransferError::InvalidSwapProgram)?;
+                let configured = ctx
+                    .accounts
+                    .config
+                    .as_ref()
+                    .and_then(|config| config.swap_program);
+                require!(
+                    configured == Some(swap_program.key()),
+                    TransferError::InvalidSwapProgram
+                );
+                let destination = ctx
+                    .accounts
+                    .recipient_output_token_account
+                    .as_mut()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                require_keys_eq!(destination.mint, output_mint, TransferError::InvalidTokenMint);
+                let balance_before = destination.amount;
+
+                let escrow_token_account = ctx
+                    .accounts
+                    .escrow_token_account
+                    .as_ref()
+                    .ok_or(TransferError::InvalidEscrowAccount)?;
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                swap_via_program(
+                    swap_program,
+                    transfer_account.to_account_info(),
+                    escrow_token_account.to_account_info(),
+                    destination.to_account_info(),
+                    token_program.to_account_info(),
+                    ctx.remaining_accounts,
+                    paid_amount,
+                    transfer_account.min_out,
+                    signer,
+                )?;
+
+                destination.reload()?;
+                let received = destination.amount.saturating_sub(balance_before);
+                if received < transfer_account.min_out {
+                    msg!(
+                        "Slippage exceeded: received={} min_out={}",
+                        received,
+                        transfer_account.min_out
+                    );
+                    return err!(TransferError::SlippageExceeded);
+                }
+            } else {
+                // Without a token account the payee can still be paid when the
+                // treasury sponsors creating one
+                let destination = match ctx.accounts.recipient_token_account.as_ref() {
+                    Some(recipient_token_account) => {
+                        // Tokens may only land in an account the stored payee owns
+                        require_keys_eq!(
+                            recipient_token_account.owner,
+                            payee,
+                            TransferError::InvalidRecipient
+                        );
+                        recipient_token_account.to_account_info()
+                    }
+                    None => {
+                        let sponsor = SponsorAccounts {
+                            payer: ctx.accounts.executor.to_account_info(),
+                            token_account: sponsor_account(
+                                &ctx.accounts.new_recipient_token_account,
+                            )?,
+                            recipient: ctx.accounts.recipient.to_account_info(),
+                            mint: sponsor_account(&ctx.accounts.token_mint)?,
+                            system_program: ctx.accounts.system_program.to_account_info(),
+                            token_program: sponsor_account(&ctx.accounts.token_program)?,
+                            associated_token_program: sponsor_account(
+                                &ctx.accounts.associated_token_program,
+                            )?,
+                        };
+                        sponsor_recipient_token_account(ctx.accounts.config.as_ref(), sponsor)?
+                    }
+                };
+                let escrow_token_account = ctx
+                    .accounts
+                    .escrow_token_account
+                    .as_ref()
+                    .ok_or(TransferError::InvalidEscrowAccount)?;
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                let transfer_instruction = Transfer {
+                    from: escrow_token_account.to_account_info(),
+                    to: destination,
+                    authority: transfer_account.to_account_info(),
+                };
+
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        transfer_instruction,
+                        signer,
+                    ),
+                    paid_amount,
+                )?;
+            }
+        }
+
+        // Reward whoever executed this occurrence
+        if transfer_account.keeper_fee_lamports > 0 {
+            debit_sol_escrow(
+                &transfer_account.to_account_info(),
+                &ctx.accounts.executor.to_account_info(),
+                transfer_account.rent_reserve,
+                transfer_account.keeper_fee_lamports,
+            )?;
+        }
+
+        // Anything above what the remaining occurrences still need (e.g. tokens
+        // sent straight to the escrow ATA) goes back to the sender. The sweep only
+        // runs when the refund accounts are supplied so a donation can never
+        // block execution. A config may credit stray SOL to the payee instead.
+        let expected = transfer_account.remaining_escrow()?;
+        let credit_surplus_to_recipient = ctx
+            .accounts
+            .config
+            .as_ref()
+            .map_or(false, |config| config.credit_surplus_to_recipient);
+        if transfer_account.token_mint == System::id() {
+            let surplus_destination = if credit_surplus_to_recipient {
+                Some(&ctx.accounts.recipient)
+            } else {
+                ctx.accounts.sender.as_ref()
+            };
+            if let Some(destination) = surplus_destination {
+                let reserved = transfer_account
+                    .rent_reserve
+                    .checked_add(transfer_account.remaining_keeper_fees()?)
+                    .and_then(|reserved| reserved.checked_add(expected))
+                    .ok_or(TransferError::ArithmeticOverflow)?;
+                let surplus =
+                    transfer_account.to_account_info().lamports().saturating_sub(reserved);
+                if surplus > 0 {
+                    debit_sol_escrow(
+                        &transfer_account.to_account_info(),
+                        destination,
+                        transfer_account.rent_reserve,
+                        surplus,
+                    )?;
+                    if credit_surplus_to_recipient {
+                        emit!(SurplusCredited {
+                            transfer_id: transfer_account.key(),
+                            recipient: destination.key(),
+                            amount: surplus,
+                            slot: clock.slot,
+                        });
+                    } else {
+                        emit!(SurplusReturned {
+                            transfer_id: transfer_account.key(),
+                            sender: destination.key(),
+                            amount: surplus,
+                            slot: clock.slot,
+                        });
+                    }
+                }
+            }
+        } else if let (Some(escrow), Some(sender_token_account)) = (
+            ctx.accounts.escrow_token_account.as_mut(),
+            ctx.accounts.sender_token_account.as_ref(),
+        ) {
+            // The cached balance predates this occurrence's payout
+            escrow.reload()?;
+            let surplus = escrow.amount.saturating_sub(expected);
+            if surplus > 0 {
+                let seeds = &[
+                    b"transfer",
+                    transfer_account.sender.as_ref(),
+                    transfer_account.nonce.as_ref(),
+                    &[transfer_account.bump],
+                ];
+                let signer = &[&seeds[..]];
+
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                let transfer_instruction = Transfer {
+                    from: escrow.to_account_info(),
+                    to: sender_token_account.to_account_info(),
+                    authority: transfer_account.to_account_info(),
+                };
+
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        transfer_instruction,
+                        signer,
+                    ),
+                    surplus,
+                )?;
+                emit!(SurplusReturned {
+                    transfer_id: transfer_account.key(),
+                    sender: sender_token_account.owner,
+                    amount: surplus,
+                    slot: clock.slot,
+                });
+            }
+        }
+
+        if transfer_account.emit_spl_memo && !transfer_account.memo.is_empty() {
+            log_spl_memo(&ctx.accounts.memo_program, &transfer_account.memo)?;
+        }
+
+        if transfer_account.mint_receipt {
+            let receipt = ReceiptAccounts {
+                payer: ctx.accounts.executor.to_account_info(),
+                recipient: ctx.accounts.recipient.to_account_info(),
+                mint: receipt_account(&ctx.accounts.receipt_mint)?,
+                token_account: receipt_account(&ctx.accounts.receipt_token_account)?,
+                metadata: receipt_account(&ctx.accounts.receipt_metadata)?,
+                token_program: receipt_account(&ctx.accounts.token_program)?,
+                associated_token_program: receipt_account(&ctx.accounts.associated_token_program)?,
+                metadata_program: receipt_account(&ctx.accounts.metadata_program)?,
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: receipt_account(&ctx.accounts.rent)?,
+            };
+            mint_transfer_receipt(transfer_account, receipt, paid_amount, clock.unix_timestamp)?;
+        }
+
+        // Recipients who claim promptly earn a rebate from the pool held by the
+        // config account; an underfunded pool simply pays nothing
+        if let Some(config) = ctx.accounts.config.as_ref() {
+            let window_end = due_at.saturating_add(config.rebate_window_seconds);
+            let on_time = clock.unix_timestamp <= window_end;
+            if executor == payee && config.rebate_lamports > 0 && on_time {
+                let pool = config.to_account_info();
+                let pool_reserve = Rent::get()?.minimum_balance(pool.data_len());
+                let available = pool.lamports().saturating_sub(pool_reserve);
+                if available >= config.rebate_lamports {
+                    debit_sol_escrow(
+                        &pool,
+                        &ctx.accounts.recipient.to_account_info(),
+                        pool_reserve,
+                        config.rebate_lamports,
+                    )?;
+                    emit!(RebatePaid {
+                        transfer_id: transfer_account.key(),
+                        recipient: payee,
+                        amount: config.rebate_lamports,
+                        slot: clock.slot,
+                    });
+                }
+            }
+        }
+
+        // Program recipients (e.g. lending protocols) can react to the payout
+        if let Some(notify_program) = transfer_account.notify_program {
+            notify_funds_received(
+                &transfer_account.to_account_info(),
+                &ctx.accounts.recipient.to_account_info(),
+                &ctx.accounts.notify_program,
+                notify_program,
+                transfer_account.require_callback_success,
+                paid_amount,
+            )?;
+        }
+
+        if verbose_events(ctx.accounts.config.as_deref()) {
+            emit!(TransferExecuted {
+                transfer_id: transfer_account.key(),
+                sender: transfer_account.sender,
+                recipient: payee,
+                amount: paid_amount,
+                token_mint: transfer_account.token_mint,
+                executed_at: clock.unix_timestamp,
+                refund_of: transfer_account.refund_of,
+                slot: clock.slot,
+            });
+        } else {
+            emit!(TransferStatusChanged {
+                transfer_id: transfer_account.key(),
+                status: TransferStatus::Executed,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Fund a transfer scheduled with `require_confirmation` (authority only, in a
+    /// later transaction at least `MIN_CONFIRMATION_DELAY_SECONDS` after scheduling)
+    pub fn confirm_schedule(ctx: Context<ConfirmSchedule>) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(transfer_account.pending_confirmation, TransferError::AlreadyConfirmed);
+        require_keys_eq!(
+            transfer_account.authority,
+            sender.key(),
+            TransferError::UnauthorizedAuthority
+        );
+        let confirmable_at = transfer_account
+            .created_at
+            .saturating_add(MIN_CONFIRMATION_DELAY_SECONDS);
+        if clock.unix_timestamp < confirmable_at {
+            msg!(
+                "Confirmation too early: now={} confirmable_at={}",
+                clock.unix_timestamp,
+                confirmable_at
+            );
+            return err!(TransferError::ConfirmationTooEarly);
+        }
+
+        let escrow_amount = transfer_account.remaining_escrow()?;
+        fund_escrow(
+            transfer_account,
+            EscrowFunding {
+                sender: sender.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                sender_token_account: ctx
+                    .accounts
+                    .sender_token_account
+                    .as_ref()
+                    .map(|account| account.to_account_info()),
+                escrow_token_account: ctx
+                    .accounts
+                    .escrow_token_account
+                    .as_ref()
+                    .map(|account| account.to_account_info()),
+                token_program: ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .map(|program| program.to_account_info()),
+            },
+            escrow_amount,
+            transfer_account.remaining_keeper_fees()?,
+        )?;
+        transfer_account.pending_confirmation = false;
+        transfer_account.last_modified_at = clock.unix_timestamp;
+        transfer_account.in_global_pending =
+            count_global_pending(ctx.accounts.config.as_deref_mut())?;
+        count_escrow(
+            transfer_account,
+            ctx.accounts.mint_stats.as_mut(),
+            ctx.accounts.campaign_stats.as_mut(),
+            escrow_amount,
+        )?;
+        bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+
+        emit!(TransferConfirmed {
+            transfer_id: transfer_account.key(),
+            sender: sender.key(),
+            amount: escrow_amount,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a scheduled transfer (only by sender before execution)
+    pub fn cancel_scheduled_transfer(
+        ctx: Context<CancelScheduledTransfer>,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::AlreadyCancelled);
+        if transfer_account.authority != sender.key() {
+            // The recovery key stands in for a lost sender key, but only once unlocked
+            require!(
+                transfer_account.recovery_key == Some(sender.key()),
+                TransferError::UnauthorizedCancellation
+            );
+            if clock.unix_timestamp < transfer_account.recovery_available_after {
+                msg!(
+                    "Recovery locked: now={} available_after={}",
+                    clock.unix_timestamp,
+                    transfer_account.recovery_available_after
+                );
+                return err!(TransferError::UnauthorizedRecovery);
+            }
+        }
+        require!(!transfer_account.irrevocable, TransferError::TransferIrrevocable);
+        require!(transfer_account.arbiter.is_none(), TransferError::MilestoneGated);
+
+        refund_cancelled_transfer(ctx.accounts, &clock)
+    }
+
+    /// Give up on a transfer that has stayed unexecuted for
+    /// `UNEXECUTABLE_AFTER_SECONDS` past its due time (e.g. the payee's token
+    /// account keeps failing), refunding the sender. Authority only; unlike
+    /// cancellation this also recovers irrevocable transfers.
+    pub fn mark_unexecutable(ctx: Context<CancelScheduledTransfer>) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let clock = Clock::get()?;
+
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::AlreadyCancelled);
+        require!(
+            transfer_account.authority == ctx.accounts.sender.key(),
+            TransferError::UnauthorizedCancellation
+        );
+        require!(transfer_account.arbiter.is_none(), TransferError::MilestoneGated);
+        let unexecutable_at = transfer_account
+            .execute_after
+            .checked_add(UNEXECUTABLE_AFTER_SECONDS)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        if clock.unix_timestamp < unexecutable_at {
+            msg!(
+                "Transfer may still execute: now={} unexecutable_at={}",
+                clock.unix_timestamp,
+                unexecutable_at
+            );
+            return err!(TransferError::NotYetUnexecutable);
+        }
+
+        transfer_account.unexecutable = true;
+        refund_cancelled_transfer(ctx.accounts, &clock)
+    }
+
+    /// Refund an SPL transfer whose payee's associated token account still does
+    /// not exist `RECLAIM_GRACE_SECONDS` after it is due, since execution would
+    /// keep failing. Authority only, and not for irrevocable transfers.
+    pub fn reclaim_undeliverable(ctx: Context<CancelScheduledTransfer>) -> Result<()> {
+        let transfer_account = &ctx.accounts.transfer_account;
+        let clock = Clock::get()?;
+
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::AlreadyCancelled);
+        require!(
+            transfer_account.authority == ctx.accounts.sender.key(),
+            TransferError::UnauthorizedCancellation
+        );
+        require!(!transfer_account.irrevocable, TransferError::TransferIrrevocable);
+        require!(transfer_account.arbiter.is_none(), TransferError::MilestoneGated);
+        require!(
+            transfer_account.token_mint != System::id(),
+            TransferError::InvalidTokenMint
+        );
+        // Execution would create the missing account from the treasury
+        if let Some(config) = ctx.accounts.config.as_ref() {
+            require!(!config.sponsor_recipient_ata, TransferError::RecipientAtaSponsored);
+        }
+        let reclaimable_at = transfer_account
+            .execute_after
+            .checked_add(RECLAIM_GRACE_SECONDS)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        if clock.unix_timestamp < reclaimable_at {
+            msg!(
+                "Reclaim grace period not over: now={} reclaimable_at={}",
+                clock.unix_timestamp,
+                reclaimable_at
+            );
+            return err!(TransferError::ReclaimGracePending);
+        }
+
+        let recipient_token_account = ctx
+            .accounts
+            .recipient_token_account
+            .as_ref()
+            .ok_or(TransferError::InvalidTokenAccount)?;
+        let payee = transfer_account.payee(clock.unix_timestamp);
+        require_keys_eq!(
+            recipient_token_account.key(),
+            get_associated_token_address(&payee, &transfer_account.token_mint),
+            TransferError::InvalidTokenAccount
+        );
+        // A closed account is handed back to the system program with no data
+        if recipient_token_account.owner == &token::ID && !recipient_token_account.data_is_empty()
+        {
+            msg!("Payee token account still exists: {}", recipient_token_account.key());
+            return err!(TransferError::UndeliverableRecipient);
+        }
+
+        refund_cancelled_transfer(ctx.accounts, &clock)
+    }
+
+    /// Finalize an executed `require_ack` transfer (its payee only, before the
+    /// acknowledgment deadline), releasing the held escrow to the payee
+    pub fn acknowledge_receipt(ctx: Context<AcknowledgeReceipt>) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let payee = &ctx.accounts.payee;
+        let clock = Clock::get()?;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(transfer_account.ack_deadline > 0, TransferError::NotAwaitingAck);
+        require_keys_eq!(payee.key(), transfer_account.ack_payee, TransferError::InvalidRecipient);
+        if clock.unix_timestamp > transfer_account.ack_deadline {
+            msg!(
+                "Acknowledgment window closed: now={} ack_deadline={}",
+                clock.unix_timestamp,
+                transfer_account.ack_deadline
+            );
+            return err!(TransferError::AckWindowExpired);
+        }
+
+        let paid_amount = transfer_account.amount;
+        release_outstanding(transfer_account, &mut ctx.accounts.mint_stats, paid_amount)?;
+        transfer_account.executed_occurrences = transfer_account.total_occurrences;
+        transfer_account.executed = true;
+        transfer_account.executed_at = clock.unix_timestamp;
+        transfer_account.last_modified_at = clock.unix_timestamp;
+        release_global_pending(transfer_account, ctx.accounts.config.as_deref_mut())?;
+        bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+        unlink_transfer(
+            transfer_account,
+            &mut ctx.accounts.registry,
+            &mut ctx.accounts.previous_transfer,
+        )?;
+
+        if transfer_account.token_mint == System::id() {
+            debit_sol_escrow(
+                &transfer_account.to_account_info(),
+                &payee.to_account_info(),
+                transfer_account.rent_reserve,
+                paid_amount,
+            )?;
+        } else {
+            let escrow_token_account = ctx
+                .accounts
+                .escrow_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidEscrowAccount)?;
+            let payee_token_account = ctx
+                .accounts
+                .payee_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidTokenAccount)?;
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(TransferError::InvalidTokenAccount)?;
+
+            let seeds = &[
+                b"transfer",
+                transfer_account.sender.as_ref(),
+                transfer_account.nonce.as_ref(),
+                &[transfer_account.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let transfer_instruction = Transfer {
+                from: escrow_token_account.to_account_info(),
+                to: payee_token_account.to_account_info(),
+                authority: transfer_account.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    transfer_instruction,
+                    signer,
+                ),
+                paid_amount,
+            )?;
+        }
+
+        if verbose_events(ctx.accounts.config.as_deref()) {
+            emit!(TransferExecuted {
+                transfer_id: transfer_account.key(),
+                sender: transfer_account.sender,
+                recipient: payee.key(),
+                amount: paid_amount,
+                token_mint: transfer_account.token_mint,
+                executed_at: clock.unix_timestamp,
+                refund_of: transfer_account.refund_of,
+                slot: clock.slot,
+            });
+        } else {
+            emit!(TransferStatusChanged {
+                transfer_id: transfer_account.key(),
+                status: TransferStatus::Executed,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Refund the held escrow of an executed `require_ack` transfer its payee
+    /// never acknowledged, once the deadline has passed. Authority only; this
+    /// also recovers irrevocable transfers.
+    pub fn reclaim_unacknowledged(ctx: Context<CancelScheduledTransfer>) -> Result<()> {
+        let transfer_account = &ctx.accounts.transfer_account;
+        let clock = Clock::get()?;
+
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::AlreadyCancelled);
+        require!(
+            transfer_account.authority == ctx.accounts.sender.key(),
+            TransferError::UnauthorizedCancellation
+        );
+        require!(transfer_account.ack_deadline > 0, TransferError::NotAwaitingAck);
+
+        // Refunding checks the deadline has passed
+        refund_cancelled_transfer(ctx.accounts, &clock)
+    }
+
+    /// Close a settled transfer once the config's `auto_close_after_seconds`
+    /// have passed since it was executed or cancelled (permissionless). Rent
+    /// from the transfer and its escrow ATA goes back to the sender, along with
+    /// any tokens left in the escrow, except what a `ClosedTransfer` tombstone
+    /// needs: it stays at the transfer's address so the nonce is never reused.
+    pub fn close_stale(ctx: Context<CloseStale>) -> Result<()> {
+        let transfer_info = ctx.accounts.transfer_account.to_account_info();
+        let transfer_account = Account::<ScheduledTransfer>::try_from(&transfer_info)?;
+        let clock = Clock::get()?;
+
+        let transfer_key = Pubkey::create_program_address(
+            &[
+                b"transfer",
+                transfer_account.sender.as_ref(),
+                transfer_account.nonce.as_ref(),
+                &[transfer_account.bump],
+            ],
+            ctx.program_id,
+        )
+        .map_err(|_| TransferError::InvalidTransferAccount)?;
+        require_keys_eq!(
+            transfer_info.key(),
+            transfer_key,
+            TransferError::InvalidTransferAccount
+        );
+        require_keys_eq!(
+            ctx.accounts.sender.key(),
+            transfer_account.authority,
+            TransferError::UnauthorizedAuthority
+        );
+
+        let finalized_at = if transfer_account.executed {
+            transfer_account.executed_at
+        } else if transfer_account.cancelled {
+            transfer_account.cancelled_at
+        } else {
+            msg!("Transfer has not settled yet");
+            return err!(TransferError::NotYetCloseable);
+        };
+        let retention = ctx.accounts.config.auto_close_after_seconds;
+        let closeable_at = finalized_at
+            .checked_add(retention)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        if retention == 0 || clock.unix_timestamp < closeable_at {
+            msg!(
+                "Transfer not yet closeable: now={} closeable_at={} retention={}",
+                clock.unix_timestamp,
+                closeable_at,
+                retention
+            );
+            return err!(TransferError::NotYetCloseable);
+        }
+
+        // The escrow ATA is owned by the transfer PDA, so close it while we can sign
+        if transfer_account.token_mint != System::id() {
+            let escrow_token_account = ctx
+                .accounts
+                .escrow_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidEscrowAccount)?;
+            require_keys_eq!(
+                escrow_token_account.key(),
+                escrow_ata(&transfer_key, &transfer_account.token_mint),
+                TransferError::InvalidEscrowAccount
+            );
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(TransferError::InvalidTokenAccount)?;
+
+            let seeds = &[
+                b"transfer",
+                transfer_account.sender.as_ref(),
+                transfer_account.nonce.as_ref(),
+                &[transfer_account.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            // Anyone can send tokens to the escrow ATA, and a non-empty
+            // account cannot be closed, so sweep leftovers to the authority
+            if escrow_token_account.amount > 0 {
+                let sender_token_account = ctx
+                    .accounts
+                    .sender_token_account
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+                require_keys_eq!(
+                    sender_token_account.owner,
+                    transfer_account.authority,
+                    TransferError::InvalidTokenAccount
+                );
+                require_keys_eq!(
+                    sender_token_account.mint,
+                    transfer_account.token_mint,
+                    TransferError::InvalidTokenMint
+                );
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        Transfer {
+                            from: escrow_token_account.to_account_info(),
+                            to: sender_token_account.to_account_info(),
+                            authority: transfer_account.to_account_info(),
+                        },
+                        signer,
+                    ),
+                    escrow_token_account.amount,
+                )?;
+            }
+
+            token::close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                CloseAccount {
+                    account: escrow_token_account.to_account_info(),
+                    destination: ctx.accounts.sender.to_account_info(),
+                    authority: transfer_account.to_account_info(),
+                },
+                signer,
+            ))?;
+        }
+        bump_event_seq(Some(&mut *ctx.accounts.config))?;
+
+        // A closed PDA could be initialized again under the same nonce and
+        // replay the transfer, so shrink it to a tombstone instead
+        let tombstone_space = 8 + ClosedTransfer::INIT_SPACE;
+        let tombstone_reserve = Rent::get()?.minimum_balance(tombstone_space);
+        let refund = transfer_info
+            .lamports()
+            .checked_sub(tombstone_reserve)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        debit_sol_escrow(&transfer_info, &ctx.accounts.sender, tombstone_reserve, refund)?;
+        transfer_info.realloc(tombstone_space, false)?;
+        ClosedTransfer {}.try_serialize(&mut &mut transfer_info.try_borrow_mut_data()?[..])?;
+
+        emit!(TransferClosed {
+            transfer_id: transfer_key,
+            sender: ctx.accounts.sender.key(),
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Reduce a pending transfer, refunding the difference to the sender
+    pub fn reduce_transfer(
+        ctx: Context<ReduceTransfer>,
+        reduce_by: u64,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let sender = &ctx.accounts.sender;
+        let clock = Clock::get()?;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(
+            transfer_account.authority == sender.key(),
+            TransferError::UnauthorizedCancellation
+        );
+        require!(!transfer_account.irrevocable, TransferError::TransferIrrevocable);
+        require!(!transfer_account.pending_confirmation, TransferError::NotConfirmed);
+        require!(transfer_account.arbiter.is_none(), TransferError::MilestoneGated);
+        require!(transfer_account.ack_deadline == 0, TransferError::AckPending);
+        // Reducing to zero is a full cancellation
+        require!(
+            reduce_by > 0 && reduce_by < transfer_account.amount,
+            TransferError::InvalidAmount
+        );
+        transfer_account.last_modified_at = clock.unix_timestamp;
+
+        // The next payment shrinks by `reduce_by` (later escalations follow it);
+        // refund whatever the smaller schedule no longer needs
+        let escrow_before = transfer_account.remaining_escrow()?;
+        transfer_account.amount = transfer_account
+            .amount
+            .checked_sub(reduce_by)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        let refund_amount = escrow_before
+            .checked_sub(transfer_account.remaining_escrow()?)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        release_outstanding(transfer_account, &mut ctx.accounts.mint_stats, refund_amount)?;
+        bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+
+        // Refund tokens to sender
+        if transfer_account.token_mint == System::id() {
+            // SOL refund
+            debit_sol_escrow(
+                &transfer_account.to_account_info(),
+                &sender.to_account_info(),
+                transfer_account.rent_reserve,
+                refund_amount,
+            )?;
+
+        } else {
+            // SPL Token refund
+            let seeds = &[
+                b"transfer",
+                transfer_account.sender.as_ref(),
+                transfer_account.nonce.as_ref(),
+                &[transfer_account.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let escrow_token_account = ctx
+                .accounts
+                .escrow_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidEscrowAccount)?;
+            let sender_token_account = ctx
+                .accounts
+                .sender_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidTokenAccount)?;
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(TransferError::InvalidTokenAccount)?;
+            let transfer_instruction = Transfer {
+                from: escrow_token_account.to_account_info(),
+                to: sender_token_account.to_account_info(),
+                authority: transfer_account.to_account_info(),
+            };
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    transfer_instruction,
+                    signer,
+                ),
+                refund_amount,
+            )?;
+        }
+
+        emit!(TransferReduced {
+            transfer_id: transfer_account.key(),
+            sender: transfer_account.sender,
+            reduced_by: reduce_by,
+            new_amount: transfer_account.amount,
+            refunded: refund_amount,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Merge pending single-payment transfers into `transfer_account`: each one in
+    /// `remaining_accounts` (followed by its escrow ATA for SPL) must share the
+    /// sender, recipient and mint. Its escrow moves into the target, whose amount
+    /// grows to match, and it is cancelled; unearned keeper fees go back to the
+    /// authority. Transfers linked into a sender registry are not merged.
+    pub fn consolidate_transfers<'info>(
+        ctx: Context<'_, '_, '_, 'info, ConsolidateTransfers<'info>>,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let authority = &ctx.accounts.authority;
+        let clock = Clock::get()?;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(!transfer_account.pending_confirmation, TransferError::NotConfirmed);
+        require!(
+            transfer_account.total_occurrences == 1
+                && transfer_account.arbiter.is_none()
+                && !transfer_account.require_ack,
+            TransferError::ConsolidationMismatch
+        );
+
+        let is_sol = transfer_account.token_mint == System::id();
+        let stride = if is_sol { 1 } else { 2 };
+        require!(
+            !ctx.remaining_accounts.is_empty() && ctx.remaining_accounts.len() % stride == 0,
+            TransferError::InvalidBatchSize
+        );
+
+        let target_key = transfer_account.key();
+        let mut merged_transfers = Vec::with_capacity(ctx.remaining_accounts.len() / stride);
+        for accounts in ctx.remaining_accounts.chunks(stride) {
+            let source_info = &accounts[0];
+            require_keys_eq!(
+                *source_info.owner,
+                *ctx.program_id,
+                TransferError::InvalidTransferAccount
+            );
+            let mut source =
+                ScheduledTransfer::try_deserialize(&mut &source_info.try_borrow_data()?[..])?;
+
+            let compatible = source_info.key() != target_key
+                && source.sender == transfer_account.sender
+                && source.recipient == transfer_account.recipient
+                && source.token_mint == transfer_account.token_mint
+                && source.in_mint_stats == transfer_account.in_mint_stats
+                && source.total_occurrences == 1
+                && !source.executed
+                && !source.cancelled
+                && !source.pending_confirmation
+                && !source.in_registry
+                && source.arbiter.is_none()
+                && !source.require_ack;
+            if !compatible {
+                msg!("Cannot consolidate {} into {}", source_info.key(), target_key);
+                return err!(TransferError::ConsolidationMismatch);
+            }
+            require_keys_eq!(
+                source.authority,
+                authority.key(),
+                TransferError::UnauthorizedCancellation
+            );
+            require!(!source.irrevocable, TransferError::TransferIrrevocable);
+
+            // Move the escrow over; mint reserves are unchanged since both are tracked alike
+            let amount = source.remaining_escrow()?;
+            let keeper_fees = source.remaining_keeper_fees()?;
+            if is_sol {
+                debit_sol_escrow(
+                    source_info,
+                    &transfer_account.to_account_info(),
+                    source.rent_reserve,
+                    amount,
+                )?;
+            } else {
+                let source_escrow = &accounts[1];
+                require_keys_eq!(
+                    source_escrow.key(),
+                    escrow_ata(&source_info.key(), &source.token_mint),
+                    TransferError::InvalidEscrowAccount
+                );
+                let escrow_token_account = ctx
+                    .accounts
+                    .escrow_token_account
+                    .as_ref()
+                    .ok_or(TransferError::InvalidEscrowAccount)?;
+                let token_program = ctx
+                    .accounts
+                    .token_program
+                    .as_ref()
+                    .ok_or(TransferError::InvalidTokenAccount)?;
+
+                let seeds = &[
+                    b"transfer",
+                    source.sender.as_ref(),
+                    source.nonce.as_ref(),
+                    &[source.bump],
+                ];
+                let signer = &[&seeds[..]];
+
+                let transfer_instruction = Transfer {
+                    from: source_escrow.clone(),
+                    to: escrow_token_account.to_account_info(),
+                    authority: source_info.clone(),
+                };
+
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        transfer_instruction,
+                        signer,
+                    ),
+                    amount,
+                )?;
+            }
+            if keeper_fees > 0 {
+                debit_sol_escrow(
+                    source_info,
+                    &authority.to_account_info(),
+                    source.rent_reserve,
+                    keeper_fees,
+                )?;
+            }
+
+            release_global_pending(&mut source, ctx.accounts.config.as_deref_mut())?;
+            bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+            source.cancelled = true;
+            source.cancelled_at = clock.unix_timestamp;
+            source.last_modified_at = clock.unix_timestamp;
+            source.try_serialize(&mut &mut source_info.try_borrow_mut_data()?[..])?;
+
+            transfer_account.amount = transfer_account
+                .amount
+                .checked_add(amount)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+
+            // Nothing is refunded: the escrow now backs the target transfer
+            emit!(TransferCancelled {
+                transfer_id: source_info.key(),
+                sender: source.sender,
+                amount: 0,
+                cancelled_at: clock.unix_timestamp,
+                slot: clock.slot,
+            });
+            merged_transfers.push(source_info.key());
+        }
+        transfer_account.last_modified_at = clock.unix_timestamp;
+
+        emit!(TransfersConsolidated {
+            transfer_id: target_key,
+            merged_transfers,
+            amount: transfer_account.amount,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Redirect a pending transfer to a new recipient (only by the current recipient)
+    pub fn reassign_recipient(
+        ctx: Context<ReassignRecipient>,
+        new_recipient: Pubkey,
+        recipient_is_pda: bool,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let recipient = &ctx.accounts.recipient;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(transfer_account.ack_deadline == 0, TransferError::AckPending);
+        require!(
+            transfer_account.recipient == recipient.key(),
+            TransferError::InvalidRecipient
+        );
+        require!(
+            transfer_account.allow_recipient_reassignment,
+            TransferError::ReassignmentNotPermitted
+        );
+        // The new recipient must pass the same checks as one named at schedule time
+        check_recipient_allowed(&ctx.accounts.denylist, &new_recipient)?;
+        check_self_transfer(
+            ctx.accounts.config.as_deref(),
+            &transfer_account.sender,
+            &new_recipient,
+        )?;
+        check_sol_recipient(
+            &transfer_account.token_mint,
+            &ctx.accounts.new_recipient_account,
+            recipient_is_pda,
+        )?;
+
+        let previous_recipient = transfer_account.recipient;
+        transfer_account.recipient = new_recipient;
+        // The new recipient has not authorized anyone
+        transfer_account.execution_authorized_until = 0;
+        transfer_account.last_modified_at = Clock::get()?.unix_timestamp;
+
+        emit!(RecipientReassigned {
+            transfer_id: transfer_account.key(),
+            previous_recipient,
+            new_recipient,
+        });
+
+        Ok(())
+    }
+
+    /// Let any keeper execute on the recipient's behalf until `until`; pass 0 to
+    /// revoke. Only applies to transfers without an executor whitelist.
+    pub fn authorize_execution(ctx: Context<AuthorizeExecution>, until: i64) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let recipient = &ctx.accounts.recipient;
+
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(
+            transfer_account.recipient == recipient.key(),
+            TransferError::InvalidRecipient
+        );
+        require!(until >= 0, TransferError::InvalidExecutionTime);
+
+        transfer_account.execution_authorized_until = until;
+        transfer_account.last_modified_at = Clock::get()?.unix_timestamp;
+
+        emit!(ExecutionAuthorized {
+            transfer_id: transfer_account.key(),
+            recipient: recipient.key(),
+            until,
+        });
+
+        Ok(())
+    }
+
+    /// Have execution CPI `on_funds_received` on `notify_program` after each
+    /// payout, for recipients that are programs; `None` turns it off. With
+    /// `require_callback_success` the payout fails unless the program acknowledges.
+    /// Authority only, while the transfer is pending.
+    pub fn set_notify_program(
+        ctx: Context<SetNotifyProgram>,
+        notify_program: Option<Pubkey>,
+        require_callback_success: bool,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(
+            transfer_account.authority == ctx.accounts.authority.key(),
+            TransferError::UnauthorizedAuthority
+        );
+
+        transfer_account.notify_program = notify_program;
+        transfer_account.require_callback_success =
+            notify_program.is_some() && require_callback_success;
+        transfer_account.last_modified_at = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+
+    /// Split a funded single-occurrence transfer into milestones that only
+    /// `arbiter` can release to the recipient or refund to the sender. The
+    /// amounts must add up to the escrow. Sender only, before anything is paid.
+    pub fn attach_milestones(
+        ctx: Context<AttachMilestones>,
+        arbiter: Pubkey,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(!transfer_account.pending_confirmation, TransferError::NotConfirmed);
+        require!(
+            transfer_account.authority == ctx.accounts.sender.key(),
+            TransferError::UnauthorizedAuthority
+        );
+        require!(
+            transfer_account.arbiter.is_none()
+                && !transfer_account.require_ack
+                && transfer_account.total_occurrences == 1
+                && transfer_account.keeper_fee_lamports == 0,
+            TransferError::InvalidMilestones
+        );
+        require!(
+            !amounts.is_empty() && amounts.len() <= MAX_MILESTONES,
+            TransferError::InvalidMilestones
+        );
+        let mut total: u64 = 0;
+        for amount in amounts.iter() {
+            require!(*amount > 0, TransferError::InvalidAmount);
+            total = total
+                .checked_add(*amount)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+        }
+        if total != transfer_account.amount {
+            msg!(
+                "Milestones must cover the escrow: total={} escrow={}",
+                total,
+                transfer_account.amount
+            );
+            return err!(TransferError::InvalidMilestones);
+        }
+
+        transfer_account.arbiter = Some(arbiter);
+        transfer_account.milestones = amounts
+            .into_iter()
+            .map(|amount| Milestone {
+                amount,
+                approved: false,
+                rejected: false,
+            })
+            .collect();
+        transfer_account.last_modified_at = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+
+    /// Pay milestone `index` to the recipient (arbiter only)
+    pub fn release_milestone(ctx: Context<SettleMilestone>, index: u8) -> Result<()> {
+        settle_milestone(ctx, index, true)
+    }
+
+    /// Refund milestone `index` to the sender (arbiter only)
+    pub fn reject_milestone(ctx: Context<SettleMilestone>, index: u8) -> Result<()> {
+        settle_milestone(ctx, index, false)
+    }
+
+    /// Hand a pending transfer to a new authority, e.g. when rotating keys.
+    /// The PDA seeds and registry keep using the original `sender`.
+    pub fn transfer_ownership(
+        ctx: Context<TransferOwnership>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let clock = Clock::get()?;
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(
+            transfer_account.authority == ctx.accounts.authority.key(),
+            TransferError::UnauthorizedAuthority
+        );
+        // A stolen key must not be able to take transfers out from under a freeze
+        if let Some(registry) = ctx.accounts.registry.as_ref() {
+            if clock.unix_timestamp < registry.frozen_until {
+                msg!("Sender frozen: frozen_at={}", registry.frozen_at);
+                return err!(TransferError::SenderFrozen);
+            }
+        }
+
+        let previous_authority = transfer_account.authority;
+        transfer_account.authority = new_authority;
+        transfer_account.last_modified_at = clock.unix_timestamp;
+
+        emit!(OwnershipTransferred {
+            transfer_id: transfer_account.key(),
+            previous_authority,
+            new_authority,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Create the sender's registry that links their transfers for pagination
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+
+        registry.sender = ctx.accounts.sender.key();
+        registry.head = None;
+        registry.count = 0;
+        registry.frozen_at = 0;
+        registry.frozen_until = 0;
+        registry.bump = *ctx.bumps.get("registry").unwrap();
+
+        Ok(())
+    }
+
+    /// Opt in to (or adjust) a minimum number of seconds between transfers
+    /// scheduled to the signing recipient; 0 turns the cooldown off
+    pub fn set_recipient_cooldown(
+        ctx: Context<SetRecipientCooldown>,
+        cooldown_seconds: i64,
+    ) -> Result<()> {
+        require!(cooldown_seconds >= 0, TransferError::InvalidInterval);
+        let policy = &mut ctx.accounts.recipient_policy;
+
+        policy.recipient = ctx.accounts.recipient.key();
+        policy.cooldown_seconds = cooldown_seconds;
+        policy.bump = *ctx.bumps.get("recipient_policy").unwrap();
+
+        Ok(())
+    }
+
+    /// Freeze every transfer of the sender until `unfreeze`, for when the sender
+    /// suspects their key is compromised. Once the registry exists it must be
+    /// passed to execute, reschedule or hand over any of their transfers.
+    pub fn emergency_freeze(ctx: Context<FreezeSender>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let clock = Clock::get()?;
+
+        registry.frozen_at = clock.unix_timestamp;
+        registry.frozen_until = i64::MAX;
+
+        emit!(SenderFreezeChanged {
+            sender: registry.sender,
+            frozen_until: registry.frozen_until,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Lift an `emergency_freeze` once it has stood for `UNFREEZE_DELAY_SECONDS`,
+    /// so a stolen key cannot undo the freeze straight away
+    pub fn unfreeze(ctx: Context<FreezeSender>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let clock = Clock::get()?;
+
+        let unfreeze_at = registry
+            .frozen_at
+            .checked_add(UNFREEZE_DELAY_SECONDS)
+            .ok_or(TransferError::ArithmeticOverflow)?;
+        if clock.unix_timestamp < unfreeze_at {
+            msg!(
+                "Unfreeze locked: now={} unfreeze_at={}",
+                clock.unix_timestamp,
+                unfreeze_at
+            );
+            return err!(TransferError::UnfreezeLocked);
+        }
+        registry.frozen_until = 0;
+
+        emit!(SenderFreezeChanged {
+            sender: registry.sender,
+            frozen_until: registry.frozen_until,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Create the reserves tracker for `token_mint` (`System` program id for SOL);
+    /// transfers scheduled with it count towards its outstanding total
+    pub fn initialize_mint_stats(ctx: Context<InitializeMintStats>) -> Result<()> {
+        let mint_stats = &mut ctx.accounts.mint_stats;
+
+        mint_stats.mint = ctx.accounts.token_mint.key();
+        mint_stats.total_outstanding = 0;
+        mint_stats.bump = *ctx.bumps.get("mint_stats").unwrap();
+
+        Ok(())
+    }
+
+    /// Create the counters for `campaign_id`; transfers scheduled or executed
+    /// with it passed in are tallied there
+    pub fn initialize_campaign_stats(
+        ctx: Context<InitializeCampaignStats>,
+        campaign_id: u64,
+    ) -> Result<()> {
+        let campaign_stats = &mut ctx.accounts.campaign_stats;
+
+        campaign_stats.campaign_id = campaign_id;
+        campaign_stats.scheduled_count = 0;
+        campaign_stats.scheduled_volume = 0;
+        campaign_stats.executed_count = 0;
+        campaign_stats.executed_volume = 0;
+        campaign_stats.bump = *ctx.bumps.get("campaign_stats").unwrap();
+
+        Ok(())
+    }
+
+    /// Initialize the global program configuration (once per deployment)
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        config.admin = ctx.accounts.admin.key();
+        config.allow_self_transfer = false;
+        config.require_memo = false;
+        config.min_keeper_fee_lamports = 0;
+        config.rebate_window_seconds = 0;
+        config.rebate_lamports = 0;
+        config.min_escrow_seconds = 0;
+        config.swap_program = None;
+        config.max_memo_chars = 0;
+        config.sponsor_recipient_ata = false;
+        config.large_transfer_threshold = 0;
+        config.large_transfer_extra_delay = 0;
+        config.execution_mode = ExecutionMode::RecipientOnly;
+        config.max_global_pending = 0;
+        config.global_pending = 0;
+        config.event_seq = 0;
+        config.auto_close_after_seconds = 0;
+        config.require_derived_nonce = false;
+        config.condition_retry_backoff_seconds = 0;
+        config.verbose_events = true;
+        config.credit_surplus_to_recipient = false;
+        config.sunset = false;
+        config.successor_program = None;
+        config.bump = *ctx.bumps.get("config").unwrap();
+
+        Ok(())
+    }
+
+    /// Close the program configuration, returning rent to the admin. Refused
+    /// once it has sequenced events or while transfers count against the
+    /// global cap, which a fresh config would otherwise reset.
+    pub fn close_config(_ctx: Context<CloseConfig>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Create the program-wide recipient denylist (admin only)
+    pub fn initialize_denylist(ctx: Context<InitializeDenylist>) -> Result<()> {
+        let denylist = &mut ctx.accounts.denylist;
+
+        denylist.denied = Vec::new();
+        denylist.bump = *ctx.bumps.get("denylist").unwrap();
+
+        Ok(())
+    }
+
+    /// Block an address from receiving transfers (admin only)
+    pub fn add_denied(ctx: Context<ManageDenylist>, address: Pubkey) -> Result<()> {
+        let denylist = &mut ctx.accounts.denylist;
+
+        if !denylist.denied.contains(&address) {
+            require!(
+                denylist.denied.len() < MAX_DENIED_RECIPIENTS,
+                TransferError::DenylistFull
+            );
+            denylist.denied.push(address);
+        }
+
+        Ok(())
+    }
+
+    /// Unblock a previously denied address (admin only)
+    pub fn remove_denied(ctx: Context<ManageDenylist>, address: Pubkey) -> Result<()> {
+        ctx.accounts.denylist.denied.retain(|denied| denied != &address);
+
+        Ok(())
+    }
+
+    /// Move a pending SPL transfer's escrow to `destination_token_account` and
+    /// retire the transfer (admin only, once the config is sunset). The
+    /// destination must be owned by the PDA of the config's successor program
+    /// derived from `owner_seeds`, bump included. Unspent keeper fees go back
+    /// to the sender.
+    pub fn migrate_to_program(
+        ctx: Context<MigrateToProgram>,
+        owner_seeds: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        let transfer_account = &mut ctx.accounts.transfer_account;
+        let destination = &ctx.accounts.destination_token_account;
+        let clock = Clock::get()?;
+
+        require!(ctx.accounts.config.sunset, TransferError::ProgramNotSunset);
+        let new_program = ctx
+            .accounts
+            .config
+            .successor_program
+            .ok_or(TransferError::SuccessorProgramRequired)?;
+        // Escrow may only leave for an account the successor program controls
+        let seeds: Vec<&[u8]> = owner_seeds.iter().map(Vec::as_slice).collect();
+        let expected_owner = Pubkey::create_program_address(&seeds, &new_program)
+            .map_err(|_| error!(TransferError::InvalidTokenAccount))?;
+        if destination.owner != expected_owner {
+            msg!(
+                "Destination owner is not the successor PDA: owner={}, expected={}",
+                destination.owner,
+                expected_owner
+            );
+            return err!(TransferError::InvalidTokenAccount);
+        }
+
+        // Security validations
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::TransferCancelled);
+        require!(!transfer_account.pending_confirmation, TransferError::NotConfirmed);
+        require!(transfer_account.ack_deadline == 0, TransferError::AckPending);
+        require!(
+            transfer_account.token_mint != System::id(),
+            TransferError::InvalidTokenMint
+        );
+        let amount = transfer_account.remaining_escrow()?;
+        let refund_keeper_fees = transfer_account.remaining_keeper_fees()?;
+        release_outstanding(transfer_account, &mut ctx.accounts.mint_stats, amount)?;
+        release_global_pending(transfer_account, Some(&mut *ctx.accounts.config))?;
+        bump_event_seq(Some(&mut *ctx.accounts.config))?;
+        unlink_transfer(
+            transfer_account,
+            &mut ctx.accounts.registry,
+            &mut ctx.accounts.previous_transfer,
+        )?;
+        transfer_account.migrated = true;
+        transfer_account.cancelled = true;
+        transfer_account.cancelled_at = clock.unix_timestamp;
+        transfer_account.last_modified_at = clock.unix_timestamp;
+
+        let seeds = &[
+            b"transfer",
+            transfer_account.sender.as_ref(),
+            transfer_account.nonce.as_ref(),
+            &[transfer_account.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let transfer_instruction = Transfer {
+            from: ctx.accounts.escrow_token_account.to_account_info(),
+            to: destination.to_account_info(),
+            authority: transfer_account.to_account_info(),
+        };
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                transfer_instruction,
+                signer,
+            ),
+            amount,
+        )?;
+
+        if refund_keeper_fees > 0 {
+            debit_sol_escrow(
+                &transfer_account.to_account_info(),
+                &ctx.accounts.sender,
+                transfer_account.rent_reserve,
+                refund_keeper_fees,
+            )?;
+        }
+
+        emit!(TransferMigrated {
+            transfer_id: transfer_account.key(),
+            new_program,
+            destination: destination.key(),
+            amount,
+            slot: clock.slot,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a pending one-off transfer and, in the same instruction, schedule
+    /// its escrow to `recipient` at `execute_after`, moving the funds straight
+    /// into the new transfer's escrow instead of refunding and re-escrowing them
+    /// (authority only). Unspent keeper fees go back to the sender.
+    /// `recipient_is_pda` vouches for a program-owned SOL recipient, as in
+    /// `schedule_transfer`.
+    pub fn cancel_and_reschedule(
+        ctx: Context<CancelAndReschedule>,
+        execute_after: i64,
+        nonce: [u8; 32],
+        memo: String,
+        recipient_is_pda: bool,
+    ) -> Result<()> {
+        let sender = ctx.accounts.sender.key();
+        let recipient = ctx.accounts.recipient.key();
+        let new_transfer_id = ctx.accounts.new_transfer_account.key();
+        let clock = Clock::get()?;
+        let transfer_account = &mut ctx.accounts.transfer_account;
+
+        // Security validations: the old transfer must be cancellable
+        require!(!transfer_account.executed, TransferError::AlreadyExecuted);
+        require!(!transfer_account.cancelled, TransferError::AlreadyCancelled);
+        require!(!transfer_account.irrevocable, TransferError::TransferIrrevocable);
+        require!(transfer_account.arbiter.is_none(), TransferError::MilestoneGated);
+        require!(!transfer_account.pending_confirmation, TransferError::NotConfirmed);
+        require!(transfer_account.ack_deadline == 0, TransferError::AckPending);
+        // The replacement is a one-off, so it can only take over a one-off's escrow
+        require!(transfer_account.total_occurrences == 1, TransferError::InvalidOccurrences);
+        // Redirecting funds is exactly what a freeze guards against
+        if let Some(registry) = ctx.accounts.registry.as_ref() {
+            if clock.unix_timestamp < registry.frozen_until {
+                msg!("Sender frozen: frozen_at={}", registry.frozen_at);
+                return err!(TransferError::SenderFrozen);
+            }
+        }
+
+        // ...and the new parameters must pass the usual schedule checks
+        let amount = transfer_account.remaining_escrow()?;
+        validate_schedule(
+            &sender,
+            &recipient,
+            amount,
+            execute_after,
+            &memo,
+            DEFAULT_MEMO_CAPACITY,
+            0,
+            ctx.accounts.config.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        check_derived_nonce(
+            ctx.accounts.config.as_deref(),
+            &sender,
+            &recipient,
+            amount,
+            execute_after,
+            &nonce,
+        )?;
+        validate_recipient(
+            &ctx.accounts.denylist,
+            &ctx.accounts.recipient_policy,
+            &ctx.accounts.recipient,
+            &transfer_account.token_mint,
+            recipient_is_pda,
+            clock.unix_timestamp,
+        )?;
+
+        let refund_keeper_fees = transfer_account.remaining_keeper_fees()?;
+        unlink_transfer(
+            transfer_account,
+            &mut ctx.accounts.registry,
+            &mut ctx.accounts.previous_transfer,
+        )?;
+        transfer_account.cancelled = true;
+        transfer_account.cancelled_at = clock.unix_timestamp;
+        transfer_account.last_modified_at = clock.unix_timestamp;
+
+        // The escrow stays counted in the mint and global totals, now under the
+        // new transfer
+        let new_transfer = &mut ctx.accounts.new_transfer_account;
+        let rent_reserve =
+            Rent::get()?.minimum_balance(new_transfer.to_account_info().data_len());
+        new_transfer.set_inner(ScheduledTransfer {
+            sender,
+            recipient,
+            amount,
+            token_mint: transfer_account.token_mint,
+            execute_after,
+            created_at: clock.unix_timestamp,
+            created_slot: clock.slot,
+            program_version: PROGRAM_VERSION,
+            last_modified_at: clock.unix_timestamp,
+            nonce,
+            memo,
+            total_occurrences: 1,
+            rent_reserve,
+            in_mint_stats: transfer_account.in_mint_stats,
+            in_global_pending: transfer_account.in_global_pending,
+            authority: sender,
+            bump: *ctx.bumps.get("new_transfer_account").unwrap(),
+            ..ScheduledTransfer::default()
+        });
+        transfer_account.in_mint_stats = false;
+        transfer_account.in_global_pending = false;
+        bump_event_seq(ctx.accounts.config.as_deref_mut())?;
+
+        if transfer_account.token_mint == System::id() {
+            debit_sol_escrow(
+                &transfer_account.to_account_info(),
+                &new_transfer.to_account_info(),
+                transfer_account.rent_reserve,
+                amount,
+            )?;
+        } else {
+            let seeds = &[
+                b"transfer",
+                transfer_account.sender.as_ref(),
+                transfer_account.nonce.as_ref(),
+                &[transfer_account.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let escrow_token_account = ctx
+                .accounts
+                .escrow_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidEscrowAccount)?;
+            let new_escrow_token_account = ctx
+                .accounts
+                .new_escrow_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidEscrowAccount)?;
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(TransferError::InvalidTokenAccount)?;
+            let transfer_instruction = Transfer {
+                from: escrow_token_account.to_account_info(),
+                to: new_escrow_token_account.to_account_info(),
+                authority: transfer_account.to_account_info(),
+            };
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    transfer_instruction,
+                    signer,
+                ),
+                amount,
+            )?;
+        }
+
+        if refund_keeper_fees > 0 {
+            debit_sol_escrow(
+                &transfer_account.to_account_info(),
+                &ctx.accounts.sender.to_account_info(),
+                transfer_account.rent_reserve,
+                refund_keeper_fees,
+            )?;
+        }
+
+        let verbose = verbose_events(ctx.accounts.config.as_deref());
+        if verbose {
+            // The escrow moved to the new transfer, so the sender got none of it back
+            emit!(TransferCancelled {
+                transfer_id: transfer_account.key(),
+                sender: transfer_account.sender,
+                amount: 0,
+                cancelled_at: transfer_account.cancelled_at,
+                slot: clock.slot,
+            });
+        } else {
+            emit!(TransferStatusChanged {
+                transfer_id: transfer_account.key(),
+                status: TransferStatus::Cancelled,
+            });
+        }
+        emit!(TransferRescheduled {
+            transfer_id: transfer_account.key(),
+            new_transfer_id,
+            amount,
+            slot: clock.slot,
+        });
+        if verbose {
+            emit!(TransferScheduled {
+                transfer_id: new_transfer_id,
+                sender,
+                recipient,
+                amount,
+                token_mint: new_transfer.token_mint,
+                execute_after,
+                nonce,
+                campaign_id: 0,
+                refund_of: None,
+                slot: clock.slot,
+            });
+        } else {
+            emit!(TransferStatusChanged {
+                transfer_id: new_transfer_id,
+                status: TransferStatus::Scheduled,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Update program configuration (admin only); `None` fields are left unchanged
+    pub fn update_config(ctx: Context<UpdateConfig>, update: ConfigUpdate) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let slot = Clock::get()?.slot;
+
+        // Each group of settings emits its own event with before/after values
+        if update.allow_self_transfer.is_some()
+            || update.require_memo.is_some()
+            || update.sponsor_recipient_ata.is_some()
+            || update.require_derived_nonce.is_some()
+            || update.verbose_events.is_some()
+            || update.credit_surplus_to_recipient.is_some()
+        {
+            let old_allow_self_transfer = config.allow_self_transfer;
+            let old_require_memo = config.require_memo;
+            let old_sponsor_recipient_ata = config.sponsor_recipient_ata;
+            let old_require_derived_nonce = config.require_derived_nonce;
+            let old_verbose_events = config.verbose_events;
+            let old_credit_surplus_to_recipient = config.credit_surplus_to_recipient;
+            if let Some(allow_self_transfer) = update.allow_self_transfer {
+                config.allow_self_transfer = allow_self_transfer;
+            }
+            if let Some(require_memo) = update.require_memo {
+                config.require_memo = require_memo;
+            }
+            if let Some(sponsor_recipient_ata) = update.sponsor_recipient_ata {
+                config.sponsor_recipient_ata = sponsor_recipient_ata;
+            }
+            if let Some(require_derived_nonce) = update.require_derived_nonce {
+                config.require_derived_nonce = require_derived_nonce;
+            }
+            if let Some(verbose_events) = update.verbose_events {
+                config.verbose_events = verbose_events;
+            }
+            if let Some(credit_surplus_to_recipient) = update.credit_surplus_to_recipient {
+                config.credit_surplus_to_recipient = credit_surplus_to_recipient;
+            }
+
+            emit!(PolicyChanged {
+                old_allow_self_transfer,
+                new_allow_self_transfer: config.allow_self_transfer,
+                old_require_memo,
+                new_require_memo: config.require_memo,
+                old_sponsor_recipient_ata,
+                new_sponsor_recipient_ata: config.sponsor_recipient_ata,
+                old_require_derived_nonce,
+                new_require_derived_nonce: config.require_derived_nonce,
+                old_verbose_events,
+                new_verbose_events: config.verbose_events,
+                old_credit_surplus_to_recipient,
+                new_credit_surplus_to_recipient: config.credit_surplus_to_recipient,
+                slot,
+            });
+        }
+
+        if let Some(min_keeper_fee_lamports) = update.min_keeper_fee_lamports {
+            let old_min_keeper_fee_lamports = config.min_keeper_fee_lamports;
+            config.min_keeper_fee_lamports = min_keeper_fee_lamports;
+
+            emit!(FeeChanged {
+                old_min_keeper_fee_lamports,
+                new_min_keeper_fee_lamports: min_keeper_fee_lamports,
+                slot,
+            });
+        }
+
+        if update.rebate_window_seconds.is_some() || update.rebate_lamports.is_some() {
+            let old_rebate_window_seconds = config.rebate_window_seconds;
+            let old_rebate_lamports = config.rebate_lamports;
+            if let Some(rebate_window_seconds) = update.rebate_window_seconds {
+                require!(rebate_window_seconds >= 0, TransferError::InvalidInterval);
+                config.rebate_window_seconds = rebate_window_seconds;
+            }
+            if let Some(rebate_lamports) = update.rebate_lamports {
+                config.rebate_lamports = rebate_lamports;
+            }
+
+            emit!(RebateChanged {
+                old_rebate_window_seconds,
+                new_rebate_window_seconds: config.rebate_window_seconds,
+                old_rebate_lamports,
+                new_rebate_lamports: config.rebate_lamports,
+                slot,
+            });
+        }
+
+        if update.min_escrow_seconds.is_some()
+            || update.max_memo_chars.is_some()
+            || update.large_transfer_threshold.is_some()
+            || update.large_transfer_extra_delay.is_some()
+            || update.max_global_pending.is_some()
+            || update.auto_close_after_seconds.is_some()
+            || update.condition_retry_backoff_seconds.is_some()
+        {
+            let old_min_escrow_seconds = config.min_escrow_seconds;
+            let old_max_memo_chars = config.max_memo_chars;
+            let old_large_transfer_threshold = config.large_transfer_threshold;
+            let old_large_transfer_extra_delay = config.large_transfer_extra_delay;
+            let old_max_global_pending = config.max_global_pending;
+            let old_auto_close_after_seconds = config.auto_close_after_seconds;
+            let old_condition_retry_backoff_seconds = config.condition_retry_backoff_seconds;
+            if let Some(min_escrow_seconds) = update.min_escrow_seconds {
+                require!(min_escrow_seconds >= 0, TransferError::InvalidInterval);
+                config.min_escrow_seconds = min_escrow_seconds;
+            }
+            if let Some(max_memo_chars) = update.max_memo_chars {
+                config.max_memo_chars = max_memo_chars;
+            }
+            if let Some(large_transfer_threshold) = update.large_transfer_threshold {
+                config.large_transfer_threshold = large_transfer_threshold;
+            }
+            if let Some(large_transfer_extra_delay) = update.large_transfer_extra_delay {
+                require!(large_transfer_extra_delay >= 0, TransferError::InvalidInterval);
+                config.large_transfer_extra_delay = large_transfer_extra_delay;
+            }
+            if let Some(max_global_pending) = update.max_global_pending {
+                config.max_global_pending = max_global_pending;
+            }
+            if let Some(auto_close_after_seconds) = update.auto_close_after_seconds {
+                require!(auto_close_after_seconds >= 0, TransferError::InvalidInterval);
+                config.auto_close_after_seconds = auto_close_after_seconds;
+            }
+            if let Some(backoff) = update.condition_retry_backoff_seconds {
+                require!(backoff >= 0, TransferError::InvalidInterval);
+                config.condition_retry_backoff_seconds = backoff;
+            }
+
+            emit!(LimitsChanged {
+                old_min_escrow_seconds,
+                new_min_escrow_seconds: config.min_escrow_seconds,
+                old_max_memo_chars,
+                new_max_memo_chars: config.max_memo_chars,
+                old_large_transfer_threshold,
+                new_large_transfer_threshold: config.large_transfer_threshold,
+                old_large_transfer_extra_delay,
+                new_large_transfer_extra_delay: config.large_transfer_extra_delay,
+                old_max_global_pending,
+                new_max_global_pending: config.max_global_pending,
+                old_auto_close_after_seconds,
+                new_auto_close_after_seconds: config.auto_close_after_seconds,
+                old_condition_retry_backoff_seconds,
+                new_condition_retry_backoff_seconds: config.condition_retry_backoff_seconds,
+                slot,
+            });
+        }
+
+        if let Some(swap_program) = update.swap_program {
+            let old_swap_program = config.swap_program;
+            config.swap_program = Some(swap_program);
+
+            emit!(SwapProgramChanged {
+                old_swap_program,
+                new_swap_program: swap_program,
+                slot,
+            });
+        }
+
+        if let Some(execution_mode) = update.execution_mode {
+            let old_execution_mode = config.execution_mode;
+            config.execution_mode = execution_mode;
+
+            emit!(ExecutionModeChanged {
+                old_execution_mode,
+                new_execution_mode: execution_mode,
+                slot,
+            });
+        }
+
+        if update.sunset.is_some() || update.successor_program.is_some() {
+            let old_sunset = config.sunset;
+            let old_successor_program = config.successor_program;
+            if let Some(successor_program) = update.successor_program {
+                config.successor_program = Some(successor_program);
+            }
+            if let Some(sunset) = update.sunset {
+                config.sunset = sunset;
+            }
+            // Migration needs somewhere to send escrows
+            require!(
+                !config.sunset || config.successor_program.is_some(),
+                TransferError::SuccessorProgramRequired
+            );
+
+            emit!(SunsetChanged {
+                old_sunset,
+                new_sunset: config.sunset,
+                old_successor_program,
+                new_successor_program: config.successor_program,
+                slot,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Get the full program configuration (view function)
+    pub fn get_program_config(ctx: Context<GetProgramConfig>) -> Result<ProgramConfig> {
+        Ok(ctx.accounts.config.clone().into_inner())
+    }
+
+    /// The config's event sequence, for indexers reconciling missed events.
+    /// Every schedule, confirmation, execution, reduction, cancellation,
+    /// acknowledgment, milestone settlement, consolidation, migration, reschedule
+    /// or stale close advances it by exactly one per transfer it moves funds for,
+    /// in the same transaction as that transfer's events, so the sequence only
+    /// grows and events within a transaction keep instruction order. Once the
+    /// config exists these instructions must be passed it, and it cannot be
+    /// closed and re-created to restart the count. An indexer that has seen `n`
+    /// such instructions since the config was created has missed
+    /// `get_last_seq() - n`.
+    pub fn get_last_seq(ctx: Context<GetProgramConfig>) -> Result<u64> {
+        Ok(ctx.accounts.config.event_seq)
+    }
+
+    /// Get one campaign's counters (view function)
+    pub fn get_campaign_stats(ctx: Context<GetCampaignStats>) -> Result<CampaignStats> {
+        Ok(ctx.accounts.campaign_stats.clone().into_inner())
+    }
+
+    /// Get the address holding a transfer's escrow (view function). SOL escrow
+    /// lives in the transfer account itself.
+    pub fn get_escrow_address(ctx: Context<GetEscrowAddress>) -> Result<Pubkey> {
+        let transfer_account = &ctx.accounts.transfer_account;
+        if transfer_account.token_mint == System::id() {
+            return Ok(transfer_account.key());
+        }
+
+        Ok(escrow_ata(&transfer_account.key(), &transfer_account.token_mint))
+    }
+
+    /// Get transfer information (view function)
+    pub fn get_transfer_info(
+        ctx: Context<GetTransferInfo>,
+    ) -> Result<TransferInfo> {
+        Ok(ctx.accounts.transfer_account.info(Clock::get()?.unix_timestamp))
+    }
+
+    /// `get_transfer_info` for raw account data: accounts in the current layout
+    /// decode in full, older ones fall back to the original pre-versioning
+    /// layout (view function)
+    pub fn parse_transfer_info(ctx: Context<ParseTransferInfo>) -> Result<TransferInfo> {
+        let now = Clock::get()?.unix_timestamp;
+        let data = ctx.accounts.transfer_account.try_borrow_data()?;
+        require!(
+            data.starts_with(&ScheduledTransfer::DISCRIMINATOR),
+            TransferError::InvalidTransferAccount
+        );
+
+        if let Ok(transfer) = ScheduledTransfer::try_deserialize(&mut &data[..]) {
+            return Ok(transfer.info(now));
+        }
+        let legacy = LegacyTransferLayout::deserialize(&mut &data[8..])?;
+        Ok(legacy.info(now))
+    }
+
+    /// Get information on every transfer passed in `remaining_accounts` in one
+    /// call (view function for dashboards)
+    pub fn get_transfers_batch(ctx: Context<GetTransfersBatch>) -> Result<Vec<TransferInfo>> {
+        require!(
+            ctx.remaining_accounts.len() <= MAX_TRANSFER_INFO_BATCH,
+            TransferError::InvalidBatchSize
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let mut infos = Vec::with_capacity(ctx.remaining_accounts.len());
+
+        for account_info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(
+                *account_info.owner,
+                *ctx.program_id,
+                TransferError::InvalidTransferAccount
+            );
+            let transfer =
+                ScheduledTransfer::try_deserialize(&mut &account_info.try_borrow_data()?[..])?;
+            infos.push(transfer.info(now));
+        }
+
+        // Fail clearly instead of having the runtime reject oversized return data
+        let size = infos.try_to_vec()?.len();
+        if size > MAX_RETURN_DATA {
+            msg!("Batch result too large: size={} max={}", size, MAX_RETURN_DATA);
+            return err!(TransferError::InvalidBatchSize);
+        }
+
+        Ok(infos)
+    }
+
+    /// Check that a transfer's escrow holds exactly what is still owed
+    pub fn verify_escrow_integrity(ctx: Context<VerifyEscrowIntegrity>) -> Result<IntegrityReport> {
+        let transfer_account = &ctx.accounts.transfer_account;
+        // Unconfirmed transfers hold nothing until `confirm_schedule` funds them
+        let funded = !transfer_account.pending_confirmation;
+        let expected = if funded { transfer_account.remaining_escrow()? } else { 0 };
+
+        let held = if transfer_account.token_mint == System::id() {
+            // SOL escrow shares the account with its rent reserve and keeper fees
+            let keeper_fees = if funded { transfer_account.remaining_keeper_fees()? } else { 0 };
+            let reserved = transfer_account
+                .rent_reserve
+                .checked_add(keeper_fees)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+            transfer_account
+                .to_account_info()
+                .lamports()
+                .saturating_sub(reserved)
+        } else {
+            ctx.accounts
+                .escrow_token_account
+                .as_ref()
+                .ok_or(TransferError::InvalidEscrowAccount)?
+                .amount
+        };
+
+        let delta = held as i128 - expected as i128;
+        let report = IntegrityReport {
+            balanced: delta == 0,
+            delta,
+        };
+
+        emit!(IntegrityChecked {
+            transfer_id: transfer_account.key(),
+            expected,
+            held,
+            delta,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(report)
+    }
+
+    /// Outstanding escrow of each mint whose `MintStats` is passed in
+    /// `remaining_accounts` (view function for proof-of-reserves reporting).
+    /// Return data caps a call at roughly 25 mints.
+    pub fn get_reserves(ctx: Context<GetReserves>) -> Result<Vec<MintReserve>> {
+        let mut reserves = Vec::with_capacity(ctx.remaining_accounts.len());
+
+        for account_info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(
+                *account_info.owner,
+                *ctx.program_id,
+                TransferError::InvalidTransferAccount
+            );
+            let mint_stats = MintStats::try_deserialize(&mut &account_info.try_borrow_data()?[..])?;
+
+            reserves.push(MintReserve {
+                mint: mint_stats.mint,
+                total_outstanding: mint_stats.total_outstanding,
+            });
+        }
+
+        Ok(reserves)
+    }
+
+    /// Emit `Pong` so monitoring can confirm the program is deployed and responding
+    pub fn ping(_ctx: Context<Ping>) -> Result<()> {
+        let clock = Clock::get()?;
+
+        emit!(Pong {
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Fail with `NonceAlreadyUsed` if `sender` already has a transfer under `nonce`
+    /// (deterministic pre-flight for scheduling). An executed or cancelled
+    /// transfer keeps its nonce used, even after `close_stale` closes it.
+    pub fn assert_nonce_available(
+        ctx: Context<AssertNonceAvailable>,
+        _nonce: [u8; 32],
+    ) -> Result<()> {
+        if !ctx.accounts.transfer_account.data_is_empty() {
+            msg!("Nonce already used by {}", ctx.accounts.transfer_account.key());
+            return err!(TransferError::NonceAlreadyUsed);
+        }
+
+        Ok(())
+    }
+
+    /// Count how many of the transfers passed in `remaining_accounts` are
+    /// executable right now (view function for keeper dashboards)
+    pub fn get_due_count(ctx: Context<GetDueCount>) -> Result<u32> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        let mut due_count: u32 = 0;
+
+        for account_info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(
+                *account_info.owner,
+                *ctx.program_id,
+                TransferError::InvalidTransferAccount
+            );
+            let transfer =
+                ScheduledTransfer::try_deserialize(&mut &account_info.try_borrow_data()?[..])?;
+
+            if transfer.is_due(now) {
+                due_count = due_count
+                    .checked_add(1)
+                    .ok_or(TransferError::ArithmeticOverflow)?;
+            }
+        }
+
+        Ok(due_count)
+    }
+
+    /// Sum, per mint, what `recipient` could execute right now among the
+    /// transfers passed in `remaining_accounts` (view function for recipient
+    /// dashboards). Custom condition programs are not evaluated.
+    pub fn get_claimable_total(
+        ctx: Context<GetClaimableTotal>,
+        recipient: Pubkey,
+    ) -> Result<Vec<ClaimableTotal>> {
+        let now = ctx.accounts.clock.unix_timestamp;
+        let mut totals: Vec<ClaimableTotal> = Vec::new();
+
+        for account_info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(
+                *account_info.owner,
+                *ctx.program_id,
+                TransferError::InvalidTransferAccount
+            );
+            let transfer =
+                ScheduledTransfer::try_deserialize(&mut &account_info.try_borrow_data()?[..])?;
+
+            if !transfer.is_due(now) || transfer.payee(now) != recipient {
+                continue;
+            }
+
+            match totals.iter_mut().find(|total| total.mint == transfer.token_mint) {
+                Some(total) => {
+                    total.amount = total
+                        .amount
+                        .checked_add(transfer.amount)
+                        .ok_or(TransferError::ArithmeticOverflow)?;
+                }
+                None => totals.push(ClaimableTotal {
+                    mint: transfer.token_mint,
+                    amount: transfer.amount,
+                }),
+            }
+        }
+
+        Ok(totals)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(params: ScheduleTransferParams)]
+pub struct ScheduleTransfer<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = ScheduledTransfer::space(params.memo.len(), params.allowed_executors.len()),
+        seeds = [b"transfer", sender.key().as_ref(), params.nonce.as_ref()],
+        bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: This is validated in the instruction
+    pub recipient: AccountInfo<'info>,
+
+    /// CHECK: Token mint account
+    pub token_mint: AccountInfo<'info>,
+
+    /// Counts the transfer towards the global pending cap
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+
+    /// CHECK: The recipient's cooldown policy PDA; may not be initialized yet
+    #[account(mut, seeds = [b"recipient_policy", recipient.key().as_ref()], bump)]
+    pub recipient_policy: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", sender.key().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", token_mint.key().as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign", campaign_stats.campaign_id.to_le_bytes().as_ref()],
+        bump = campaign_stats.bump
+    )]
+    pub campaign_stats: Option<Account<'info, CampaignStats>>,
+
+    /// When passed, `refund_of` is checked on-chain against this transfer
+    pub refund_of_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount,
+        constraint = sender_token_account.mint == token_mint.key() @ TransferError::InvalidTokenMint
+    )]
+    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        associated_token::mint = token_mint,
+        associated_token::authority = transfer_account
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: Only invoked, and must be the SPL Memo program
+    #[account(address = spl_memo::ID @ TransferError::MemoProgramRequired)]
+    pub memo_program: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: ScheduleTransferParams)]
+pub struct ScheduleTransferWithEscrow<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = ScheduledTransfer::space(params.memo.len(), params.allowed_executors.len()),
+        seeds = [b"transfer", sender.key().as_ref(), params.nonce.as_ref()],
+        bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: This is validated in the instruction
+    pub recipient: AccountInfo<'info>,
+
+    /// CHECK: Token mint account
+    pub token_mint: AccountInfo<'info>,
+
+    /// Counts the transfer towards the global pending cap
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+
+    /// CHECK: The recipient's cooldown policy PDA; may not be initialized yet
+    #[account(mut, seeds = [b"recipient_policy", recipient.key().as_ref()], bump)]
+    pub recipient_policy: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", sender.key().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", token_mint.key().as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign", campaign_stats.campaign_id.to_le_bytes().as_ref()],
+        bump = campaign_stats.bump
+    )]
+    pub campaign_stats: Option<Account<'info, CampaignStats>>,
+
+    /// When passed, `refund_of` is checked on-chain against this transfer
+    pub refund_of_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount,
+        constraint = sender_token_account.mint == token_mint.key() @ TransferError::InvalidTokenMint
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    /// Must already exist as the canonical ATA of the transfer PDA
+    #[account(
+        mut,
+        address = escrow_ata(&transfer_account.key(), &token_mint.key())
+            @ TransferError::InvalidEscrowAccount,
+        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount,
+        constraint = escrow_token_account.mint == token_mint.key() @ TransferError::InvalidTokenMint
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Only invoked, and must be the SPL Memo program
+    #[account(address = spl_memo::ID @ TransferError::MemoProgramRequired)]
+    pub memo_program: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct BatchSchedule<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// Counts the transfer towards the global pending cap
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+
+    /// Reserves tracker for SOL, which every batched transfer escrows
+    #[account(
+        mut,
+        seeds = [b"mint_stats", system_program.key().as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateDelegation<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Delegation::INIT_SPACE,
+        seeds = [b"delegation", owner.key().as_ref(), delegate.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: Key allowed to schedule on the owner's behalf
+    pub delegate: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == owner.key() @ TransferError::InvalidTokenAccount
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    amount: u64,
+    execute_after: i64,
+    nonce: [u8; 32],
+    memo: String,
+    memo_capacity: u16
+)]
+pub struct ScheduleTransferLargeMemo<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = ScheduledTransfer::space_with_memo_capacity(
+            (memo_capacity as usize).min(MAX_MEMO_CAPACITY),
+            0
+        ),
+        seeds = [b"transfer", sender.key().as_ref(), nonce.as_ref()],
+        bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: This is validated in the instruction
+    pub recipient: AccountInfo<'info>,
+
+    /// CHECK: Token mint account
+    pub token_mint: AccountInfo<'info>,
+
+    /// Counts the transfer towards the global pending cap
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+
+    /// CHECK: The recipient's cooldown policy PDA; may not be initialized yet
+    #[account(mut, seeds = [b"recipient_policy", recipient.key().as_ref()], bump)]
+    pub recipient_policy: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", sender.key().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", token_mint.key().as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount,
+        constraint = sender_token_account.mint == token_mint.key() @ TransferError::InvalidTokenMint
+    )]
+    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        associated_token::mint = token_mint,
+        associated_token::authority = transfer_account
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, execute_after: i64, nonce: [u8; 32], memo: String)]
+pub struct ScheduleTransferFor<'info> {
+    #[account(
+        init,
+        payer = delegate,
+        space = ScheduledTransfer::space(memo.len(), 0),
+        seeds = [b"transfer", owner.key().as_ref(), nonce.as_ref()],
+        bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(
+        mut,
+        seeds = [b"delegation", owner.key().as_ref(), delegate.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    /// CHECK: Owner of the delegated funds; seeds the delegation and transfer PDAs
+    pub owner: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub delegate: Signer<'info>,
+
+    /// CHECK: This is validated in the instruction
+    pub recipient: AccountInfo<'info>,
+
+    #[account(address = delegation.mint @ TransferError::InvalidTokenMint)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// Counts the transfer towards the global pending cap
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+
+    /// CHECK: The recipient's cooldown policy PDA; may not be initialized yet
+    #[account(mut, seeds = [b"recipient_policy", recipient.key().as_ref()], bump)]
+    pub recipient_policy: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", owner.key().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", token_mint.key().as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign", campaign_stats.campaign_id.to_le_bytes().as_ref()],
+        bump = campaign_stats.bump
+    )]
+    pub campaign_stats: Option<Account<'info, CampaignStats>>,
+
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == owner.key() @ TransferError::InvalidTokenAccount,
+        constraint = owner_token_account.mint == token_mint.key() @ TransferError::InvalidTokenMint
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = delegate,
+        associated_token::mint = token_mint,
+        associated_token::authority = transfer_account
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteScheduledTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    /// CHECK: Validated against the stored recipient in the instruction
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount,
+        constraint = escrow_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == recipient.key() @ TransferError::InvalidRecipient,
+        constraint = recipient_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub recipient_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Payee's account for `output_mint` when the transfer swaps on execution
+    #[account(
+        mut,
+        constraint = recipient_output_token_account.owner == recipient.key() @ TransferError::InvalidTokenAccount
+    )]
+    pub recipient_output_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Payee's associated token account, created when the config sponsors
+    /// it and `recipient_token_account` is omitted; the ATA program checks the address
+    #[account(mut)]
+    pub new_recipient_token_account: Option<AccountInfo<'info>>,
+
+    #[account(
+        constraint = token_mint.key() == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub token_mint: Option<Account<'info, Mint>>,
+
+    /// CHECK: Refund destination (the transfer's authority) when the transfer is
+    /// cancelled at execution or the escrow holds a surplus
+    #[account(
+        mut,
+        constraint = sender.key() == transfer_account.authority @ TransferError::InvalidTokenAccount
+    )]
+    pub sender: Option<AccountInfo<'info>>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == transfer_account.authority @ TransferError::InvalidTokenAccount
+    )]
+    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Holds the treasury (rebates, sponsored token accounts) and execution policy
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    /// CHECK: The sender's registry PDA itself, so its freeze cannot be dodged
+    /// by leaving the registry out
+    #[account(
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump,
+        constraint = registry.is_some() || registry_pda.data_is_empty()
+            @ TransferError::RegistryRequired
+    )]
+    pub registry_pda: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", transfer_account.token_mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign", transfer_account.campaign_id.to_le_bytes().as_ref()],
+        bump = campaign_stats.bump
+    )]
+    pub campaign_stats: Option<Account<'info, CampaignStats>>,
+
+    /// The transfer whose `next` points at this one, when it is not the list head
+    #[account(
+        mut,
+        constraint = previous_transfer.sender == transfer_account.sender @ TransferError::InvalidListLink
+    )]
+    pub previous_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    /// CHECK: Matched against the transfer's stored condition program
+    pub condition_program: Option<AccountInfo<'info>>,
+
+    /// CHECK: Matched against the configured swap program
+    pub swap_program: Option<AccountInfo<'info>>,
+
+    /// CHECK: Matched against the transfer's stored notify program
+    pub notify_program: Option<AccountInfo<'info>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: Only invoked, and must be the SPL Memo program
+    #[account(address = spl_memo::ID @ TransferError::MemoProgramRequired)]
+    pub memo_program: Option<AccountInfo<'info>>,
+
+    /// Fresh keypair for the receipt mint; only needed when `mint_receipt` is set
+    #[account(mut)]
+    pub receipt_mint: Option<Signer<'info>>,
+
+    /// CHECK: Created as the payee's associated token account for `receipt_mint`
+    #[account(mut)]
+    pub receipt_token_account: Option<AccountInfo<'info>>,
+
+    /// CHECK: Metadata PDA of `receipt_mint`, validated by the metadata program
+    #[account(mut)]
+    pub receipt_metadata: Option<AccountInfo<'info>>,
+
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub metadata_program: Option<Program<'info, Metadata>>,
+    pub rent: Option<Sysvar<'info, Rent>>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmSchedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount,
+        constraint = sender_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        address = escrow_ata(&transfer_account.key(), &transfer_account.token_mint)
+            @ TransferError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Counts the now funded escrow towards its mint's reserves
+    #[account(
+        mut,
+        seeds = [b"mint_stats", transfer_account.token_mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign", campaign_stats.campaign_id.to_le_bytes().as_ref()],
+        bump = campaign_stats.bump
+    )]
+    pub campaign_stats: Option<Account<'info, CampaignStats>>,
+
+    /// Counts the transfer against the global cap and advances `event_seq`
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelScheduledTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount
+    )]
+    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", transfer_account.token_mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    /// The transfer whose `next` points at this one, when it is not the list head
+    #[account(
+        mut,
+        constraint = previous_transfer.sender == transfer_account.sender @ TransferError::InvalidListLink
+    )]
+    pub previous_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    /// Required when the transfer counts towards the global pending total
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: The payee's associated token account, only read by `reclaim_undeliverable`
+    pub recipient_token_account: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateToProgram<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TransferError::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>,
+
+    /// CHECK: Receives unspent keeper fees; must be the transfer's authority
+    #[account(mut, address = transfer_account.authority @ TransferError::UnauthorizedAuthority)]
+    pub sender: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        address = escrow_ata(&transfer_account.key(), &transfer_account.token_mint)
+            @ TransferError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", transfer_account.token_mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    /// The transfer whose `next` points at this one, when it is not the list head
+    #[account(
+        mut,
+        constraint = previous_transfer.sender == transfer_account.sender @ TransferError::InvalidListLink
+    )]
+    pub previous_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(execute_after: i64, nonce: [u8; 32], memo: String)]
+pub struct CancelAndReschedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = ScheduledTransfer::space(memo.len(), 0),
+        seeds = [b"transfer", sender.key().as_ref(), nonce.as_ref()],
+        bump
+    )]
+    pub new_transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(
+        mut,
+        address = transfer_account.authority @ TransferError::UnauthorizedCancellation
+    )]
+    pub sender: Signer<'info>,
+
+    /// CHECK: This is validated in the instruction
+    pub recipient: AccountInfo<'info>,
+
+    /// CHECK: Mint of the escrow being moved
+    #[account(address = transfer_account.token_mint @ TransferError::InvalidTokenMint)]
+    pub token_mint: AccountInfo<'info>,
+
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+
+    /// CHECK: The recipient's cooldown policy PDA; may not be initialized yet
+    #[account(mut, seeds = [b"recipient_policy", recipient.key().as_ref()], bump)]
+    pub recipient_policy: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    /// CHECK: The sender's registry PDA itself, so its freeze cannot be dodged
+    /// by leaving the registry out
+    #[account(
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump,
+        constraint = registry.is_some() || registry_pda.data_is_empty()
+            @ TransferError::RegistryRequired
+    )]
+    pub registry_pda: UncheckedAccount<'info>,
+
+    /// The transfer whose `next` points at this one, when it is not the list head
+    #[account(
+        mut,
+        constraint = previous_transfer.sender == transfer_account.sender @ TransferError::InvalidListLink
+    )]
+    pub previous_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    #[account(
+        mut,
+        address = escrow_ata(&transfer_account.key(), &transfer_account.token_mint)
+            @ TransferError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        associated_token::mint = token_mint,
+        associated_token::authority = new_transfer_account
+    )]
+    pub new_escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStale<'info> {
+    /// CHECK: Loaded as a `ScheduledTransfer` and checked against its seeds by
+    /// `close_stale`, which leaves a `ClosedTransfer` in its place. Anchor
+    /// would write the full transfer back over the shrunk account on exit.
+    #[account(mut)]
+    pub transfer_account: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the rent; must be the transfer's authority
+    #[account(mut)]
+    pub sender: AccountInfo<'info>,
+
+    /// Must be the transfer's escrow ATA
+    #[account(mut)]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Receives tokens left in the escrow; only needed when it is not empty.
+    /// Must be the authority's account for the transfer's mint.
+    #[account(mut)]
+    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct ReduceTransfer<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", transfer_account.token_mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount
+    )]
+    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Advances the config's `event_seq`
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConsolidateTransfers<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump,
+        constraint = transfer_account.authority == authority.key() @ TransferError::UnauthorizedCancellation
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    /// Receives the merged transfers' unearned keeper fees
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount,
+        constraint = escrow_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Required when the merged transfers count towards the global pending total
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_recipient: Pubkey)]
+pub struct ReassignRecipient<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    pub recipient: Signer<'info>,
+
+    /// CHECK: Only its owner is read, for the SOL recipient check
+    #[account(address = new_recipient @ TransferError::InvalidRecipient)]
+    pub new_recipient_account: AccountInfo<'info>,
+
+    /// Supplies `allow_self_transfer`
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    /// CHECK: Program-wide recipient denylist PDA; may not be initialized yet
+    #[account(seeds = [b"denylist"], bump)]
+    pub denylist: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AuthorizeExecution<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetNotifyProgram<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AttachMilestones<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    pub arbiter: Signer<'info>,
+
+    /// CHECK: The recipient when releasing, the transfer's authority when
+    /// rejecting; validated in the instruction
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        address = escrow_ata(&transfer_account.key(), &transfer_account.token_mint)
+            @ TransferError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = destination_token_account.owner == destination.key() @ TransferError::InvalidTokenAccount,
+        constraint = destination_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub destination_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", transfer_account.token_mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    /// The transfer whose `next` points at this one, when it is not the list head
+    #[account(
+        mut,
+        constraint = previous_transfer.sender == transfer_account.sender @ TransferError::InvalidListLink
+    )]
+    pub previous_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    /// Required when the transfer counts towards the global pending total
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct AcknowledgeReceipt<'info> {
+    #[account(
+        mut,
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
+    )]
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(mut)]
+    pub payee: Signer<'info>,
+
+    #[account(
+        mut,
+        address = escrow_ata(&transfer_account.key(), &transfer_account.token_mint)
+            @ TransferError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = payee_token_account.owner == payee.key() @ TransferError::InvalidRecipient,
+        constraint = payee_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub payee_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_stats", transfer_account.token_mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Option<Account<'info, MintStats>>,
+
+    /// The transfer whose `next` points at this one, when it is not the list head
+    #[account(
+        mut,
+        constraint = previous_transfer.sender == transfer_account.sender @ TransferError::InvalidListLink
+    )]
+    pub previous_transfer: Option<Account<'info, ScheduledTransfer>>,
+
+    /// Required when the transfer counts towards the global pending total
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    /// CHECK: The config PDA itself, so an initialized config cannot be left out
+    #[account(
+        seeds = [b"config"],
+        bump,
+        constraint = config.is_some() || config_pda.data_is_empty() @ TransferError::ConfigRequired
+    )]
+    pub config_pda: UncheckedAccount<'info>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct TransferOwnership<'info> {
     #[account(
         mut,
-        constraint = recipient_token_account.owner == recipient.key() @ TransferError::InvalidTokenAccount
+        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
+        bump = transfer_account.bump
     )]
-    pub recipient_token_account: Option<Account<'info, TokenAccount>>,
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    pub authority: Signer<'info>,
+
+    /// The sender's registry, whose freeze blocks handing transfers over
+    #[account(
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Option<Account<'info, SenderRegistry>>,
+
+    /// CHECK: The sender's registry PDA itself, so its freeze cannot be dodged
+    /// by leaving the registry out
+    #[account(
+        seeds = [b"registry", transfer_account.sender.as_ref()],
+        bump,
+        constraint = registry.is_some() || registry_pda.data_is_empty()
+            @ TransferError::RegistryRequired
+    )]
+    pub registry_pda: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + SenderRegistry::INIT_SPACE,
+        seeds = [b"registry", sender.key().as_ref()],
+        bump
+    )]
+    pub registry: Account<'info, SenderRegistry>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRecipientCooldown<'info> {
+    #[account(
+        init_if_needed,
+        payer = recipient,
+        space = 8 + RecipientPolicy::INIT_SPACE,
+        seeds = [b"recipient_policy", recipient.key().as_ref()],
+        bump
+    )]
+    pub recipient_policy: Account<'info, RecipientPolicy>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeSender<'info> {
+    #[account(
+        mut,
+        seeds = [b"registry", sender.key().as_ref()],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, SenderRegistry>,
+
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMintStats<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + MintStats::INIT_SPACE,
+        seeds = [b"mint_stats", token_mint.key().as_ref()],
+        bump
+    )]
+    pub mint_stats: Account<'info, MintStats>,
+
+    /// CHECK: Token mint, or the System program id for SOL
+    pub token_mint: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u64)]
+pub struct InitializeCampaignStats<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CampaignStats::INIT_SPACE,
+        seeds = [b"campaign", campaign_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub campaign_stats: Account<'info, CampaignStats>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    // `init` (not `init_if_needed`) so a live config can never be overwritten
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TransferError::UnauthorizedAdmin,
+        constraint = config.global_pending == 0 @ TransferError::ConfigInUse,
+        constraint = config.event_seq == 0 @ TransferError::ConfigInUse,
+        close = admin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TransferError::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeDenylist<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + RecipientDenylist::INIT_SPACE,
+        seeds = [b"denylist"],
+        bump
+    )]
+    pub denylist: Account<'info, RecipientDenylist>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TransferError::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageDenylist<'info> {
+    #[account(mut, seeds = [b"denylist"], bump = denylist.bump)]
+    pub denylist: Account<'info, RecipientDenylist>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TransferError::UnauthorizedAdmin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetProgramConfig<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct GetCampaignStats<'info> {
+    #[account(
+        seeds = [b"campaign", campaign_stats.campaign_id.to_le_bytes().as_ref()],
+        bump = campaign_stats.bump
+    )]
+    pub campaign_stats: Account<'info, CampaignStats>,
+}
+
+#[derive(Accounts)]
+pub struct GetTransferInfo<'info> {
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+}
+
+#[derive(Accounts)]
+pub struct ParseTransferInfo<'info> {
+    /// CHECK: Decoded by hand so older layouts can be read too
+    #[account(owner = crate::ID @ TransferError::InvalidTransferAccount)]
+    pub transfer_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetEscrowAddress<'info> {
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyEscrowIntegrity<'info> {
+    pub transfer_account: Account<'info, ScheduledTransfer>,
+
+    #[account(
+        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount,
+        constraint = escrow_token_account.mint == transfer_account.token_mint @ TransferError::InvalidTokenMint
+    )]
+    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: [u8; 32])]
+pub struct AssertNonceAvailable<'info> {
+    /// CHECK: Only seeds the transfer PDA
+    pub sender: AccountInfo<'info>,
+
+    /// CHECK: Transfer PDA for `nonce`; may not exist
+    #[account(seeds = [b"transfer", sender.key().as_ref(), nonce.as_ref()], bump)]
+    pub transfer_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetReserves {}
+
+#[derive(Accounts)]
+pub struct Ping {}
+
+#[derive(Accounts)]
+pub struct GetTransfersBatch {}
+
+#[derive(Accounts)]
+pub struct GetDueCount<'info> {
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct GetClaimableTotal<'info> {
+    pub clock: Sysvar<'info, Clock>,
+}
+
+/// Unset timestamps are stored as 0, which views report as `None` so it is not
+/// mistaken for 1970
+fn timestamp_if_set(timestamp: i64) -> Option<i64> {
+    (timestamp != 0).then_some(timestamp)
+}
+
+/// `ScheduledTransfer` as written before `program_version` existed (reported as
+/// version 0), which had no `created_slot`
+#[derive(AnchorDeserialize)]
+struct LegacyTransferLayout {
+    sender: Pubkey,
+    recipient: Pubkey,
+    amount: u64,
+    token_mint: Pubkey,
+    execute_after: i64,
+    created_at: i64,
+    executed: bool,
+    executed_at: i64,
+    cancelled: bool,
+    cancelled_at: i64,
+    _nonce: [u8; 32],
+    memo: String,
+    _bump: u8,
+}
+
+impl LegacyTransferLayout {
+    fn info(self, now: i64) -> TransferInfo {
+        TransferInfo {
+            sender: self.sender,
+            recipient: self.recipient,
+            amount: self.amount,
+            token_mint: self.token_mint,
+            execute_after: self.execute_after,
+            created_at: self.created_at,
+            created_slot: 0,
+            last_modified_at: self.created_at,
+            executed: self.executed,
+            executed_at: timestamp_if_set(self.executed_at),
+            cancelled: self.cancelled,
+            cancelled_at: timestamp_if_set(self.cancelled_at),
+            memo: self.memo,
+            is_executable_now: !self.executed && !self.cancelled && self.execute_after <= now,
+            program_version: 0,
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace, Default)]
+pub struct ScheduledTransfer {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub token_mint: Pubkey,
+    pub execute_after: i64,
+    pub created_at: i64,
+    /// Orders transfers created within the same `created_at` second
+    pub created_slot: u64,
+    pub executed: bool,
+    /// 0 until executed (`None` in `TransferInfo`)
+    pub executed_at: i64,
+    pub cancelled: bool,
+    /// 0 until cancelled (`None` in `TransferInfo`)
+    pub cancelled_at: i64,
+    pub nonce: [u8; 32],
+    #[max_len(200)]
+    pub memo: String,
+    #[max_len(5)]
+    pub allowed_executors: Vec<Pubkey>,
+    pub total_occurrences: u16,
+    pub executed_occurrences: u16,
+    pub interval_seconds: i64,
+    /// First `execute_after` of a monthly recurrence, which later occurrences
+    /// count whole calendar months from (0 for fixed-interval transfers)
+    pub monthly_anchor: i64,
+    pub allow_recipient_reassignment: bool,
+    pub fallback_recipient: Option<Pubkey>,
+    pub fallback_after: i64,
+    pub emit_spl_memo: bool,
+    pub next: Option<Pubkey>,
+    pub in_registry: bool,
+    pub rent_reserve: u64,
+    pub condition_program: Option<Pubkey>,
+    pub keeper_fee_lamports: u64,
+    pub refund_if_recipient_frozen: bool,
+    pub mint_receipt: bool,
+    /// Funds are committed: only execution can move them out of escrow
+    pub irrevocable: bool,
+    /// Failed condition checks tolerated before auto-cancelling; 0 means unlimited
+    pub max_execution_attempts: u16,
+    pub execution_attempts: u16,
+    /// Set after a recorded failed condition check to now plus the config's
+    /// `condition_retry_backoff_seconds`; earlier attempts fail with `RetryTooSoon`
+    pub next_attempt_allowed_after: i64,
+    /// Pay out in this mint instead, via the configured swap program
+    pub output_mint: Option<Pubkey>,
+    /// Minimum `output_mint` amount the payee must receive per occurrence
+    pub min_out: u64,
+    /// Updated by every instruction that mutates this account
+    pub last_modified_at: i64,
+    /// Each recurrence pays this many basis points more than the previous one
+    pub escalation_bps: u16,
+    /// Escrow is counted in its mint's `MintStats`, which must then be passed
+    /// whenever the escrow shrinks
+    pub in_mint_stats: bool,
+    /// Counted in the config's `global_pending`, which must then be passed
+    /// whenever the transfer settles
+    pub in_global_pending: bool,
+    /// Backup key that may cancel (and receive the refund) once
+    /// `recovery_available_after` has passed, in case the sender key is lost
+    pub recovery_key: Option<Pubkey>,
+    pub recovery_available_after: i64,
+    /// Scheduled with `require_confirmation` and not yet funded by `confirm_schedule`
+    pub pending_confirmation: bool,
+    /// Until this time any keeper may execute without the recipient signing
+    /// (set by `authorize_execution`; 0 = never)
+    pub execution_authorized_until: i64,
+    /// Marketing campaign the transfer is attributed to
+    pub campaign_id: u64,
+    /// Earlier transfer this one refunds, for indexers linking the two
+    pub refund_of: Option<Pubkey>,
+    /// `PROGRAM_VERSION` of the program that created the account
+    pub program_version: u16,
+    /// Refunded by `mark_unexecutable` after going unexecuted too long (also `cancelled`)
+    pub unexecutable: bool,
+    /// Escrow moved to a successor program by `migrate_to_program` (also `cancelled`)
+    pub migrated: bool,
+    /// Execution holds the funds in this transfer's escrow, which only
+    /// `acknowledge_receipt` releases to the payee
+    pub require_ack: bool,
+    /// Set on execution of a `require_ack` transfer: the payee must acknowledge
+    /// by then, after which the authority may `reclaim_unacknowledged`
+    pub ack_deadline: i64,
+    /// Payee at execution, the only signer `acknowledge_receipt` accepts
+    pub ack_payee: Pubkey,
+    /// Settles the milestones instead of execution or cancellation
+    pub arbiter: Option<Pubkey>,
+    #[max_len(5)]
+    pub milestones: Vec<Milestone>,
+    /// Program CPI'd with `on_funds_received` after each payout (set by `set_notify_program`)
+    pub notify_program: Option<Pubkey>,
+    /// Fail execution with `CallbackFailed` unless the notify program acknowledges
+    pub require_callback_success: bool,
+    /// Key allowed to cancel or reduce; starts as `sender`, which stays fixed
+    /// because it seeds the PDA
+    pub authority: Pubkey,
+    pub bump: u8,
+}
+
+/// One slice of an arbitrated transfer; `amount` counts towards the escrow
+/// until the arbiter approves or rejects it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub struct Milestone {
+    pub amount: u64,
+    pub approved: bool,
+    pub rejected: bool,
+}
+
+impl ScheduledTransfer {
+    /// Account size for a transfer with a `memo_len`-byte memo and `executors`
+    /// allowed executors. Room for `MAX_MILESTONES` stays reserved since
+    /// `attach_milestones` can add them later; oversized inputs get the full
+    /// size and are rejected by validation.
+    pub fn space(memo_len: usize, executors: usize) -> usize {
+        Self::space_with_memo_capacity(memo_len.min(DEFAULT_MEMO_CAPACITY), executors)
+    }
+
+    /// `space` with `memo_capacity` bytes reserved for the memo, which
+    /// `schedule_transfer_large_memo` may set above `DEFAULT_MEMO_CAPACITY`
+    pub fn space_with_memo_capacity(memo_capacity: usize, executors: usize) -> usize {
+        let unused_executors = MAX_ALLOWED_EXECUTORS.saturating_sub(executors) * 32;
+        8 + Self::INIT_SPACE - DEFAULT_MEMO_CAPACITY + memo_capacity - unused_executors
+    }
+
+    /// Whether a keeper could execute this transfer at `now`; unconfirmed and
+    /// milestone-gated transfers never are
+    pub fn is_due(&self, now: i64) -> bool {
+        !self.executed
+            && !self.cancelled
+            && !self.pending_confirmation
+            && self.arbiter.is_none()
+            && self.ack_deadline == 0
+            && self.execute_after <= now
+    }
+
+    /// Who an execution at `now` pays: the fallback recipient once past its deadline
+    pub fn payee(&self, now: i64) -> Pubkey {
+        match self.fallback_recipient {
+            Some(fallback) if now > self.fallback_after => fallback,
+            _ => self.recipient,
+        }
+    }
+
+    /// Number of occurrences not yet executed
+    pub fn remaining_occurrences(&self) -> Result<u64> {
+        self.total_occurrences
+            .checked_sub(self.executed_occurrences)
+            .map(u64::from)
+            .ok_or_else(|| TransferError::ArithmeticOverflow.into())
+    }
+
+    /// Summary returned by the `get_transfer_info` views
+    pub fn info(&self, now: i64) -> TransferInfo {
+        TransferInfo {
+            sender: self.sender,
+            recipient: self.recipient,
+            amount: self.amount,
+            token_mint: self.token_mint,
+            execute_after: self.execute_after,
+            created_at: self.created_at,
+            created_slot: self.created_slot,
+            last_modified_at: self.last_modified_at,
+            executed: self.executed,
+            executed_at: timestamp_if_set(self.executed_at),
+            cancelled: self.cancelled,
+            cancelled_at: timestamp_if_set(self.cancelled_at),
+            memo: self.memo.clone(),
+            is_executable_now: self.is_due(now),
+            program_version: self.program_version,
+        }
+    }
+
+    /// Amount still held in escrow for the occurrences not yet executed
+    pub fn remaining_escrow(&self) -> Result<u64> {
+        Self::escalated_total(self.amount, self.escalation_bps, self.remaining_occurrences()?)
+    }
+
+    /// When the occurrence after the `executed_occurrences` already paid is due.
+    /// Monthly occurrences keep the anchor's day of month, clamped to the
+    /// month's last day, so a 31st anchor returns to the 31st after February.
+    pub fn next_execute_after(&self) -> Result<i64> {
+        if self.monthly_anchor == 0 {
+            return self
+                .execute_after
+                .checked_add(self.interval_seconds)
+                .ok_or(TransferError::ArithmeticOverflow.into());
+        }
+        add_calendar_months(self.monthly_anchor, self.executed_occurrences as i64)
+    }
+
+    /// The payment following one of `amount`, raised by `escalation_bps`
+    pub fn escalate(amount: u64, escalation_bps: u16) -> Result<u64> {
+        let scaled = (amount as u128)
+            .checked_mul(10_000 + escalation_bps as u128)
+            .ok_or(TransferError::ArithmeticOverflow)?
+            / 10_000;
+        u64::try_from(scaled).map_err(|_| TransferError::ArithmeticOverflow.into())
+    }
+
+    /// Sum of `occurrences` payments starting at `amount`, escalating after each
+    pub fn escalated_total(amount: u64, escalation_bps: u16, occurrences: u64) -> Result<u64> {
+        if escalation_bps == 0 {
+            return amount
+                .checked_mul(occurrences)
+                .ok_or_else(|| TransferError::ArithmeticOverflow.into());
+        }
+
+        let mut total: u64 = 0;
+        let mut payment = amount;
+        for occurrence in 0..occurrences {
+            total = total
+                .checked_add(payment)
+                .ok_or(TransferError::ArithmeticOverflow)?;
+            if occurrence + 1 < occurrences {
+                payment = Self::escalate(payment, escalation_bps)?;
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Keeper fee lamports still held for the occurrences not yet executed
+    pub fn remaining_keeper_fees(&self) -> Result<u64> {
+        self.keeper_fee_lamports
+            .checked_mul(self.remaining_occurrences()?)
+            .ok_or_else(|| TransferError::ArithmeticOverflow.into())
+    }
+}
+
+/// Escrow still owed by tracked transfers in one mint (SOL uses the System
+/// program id): scheduled minus executed, cancelled and reduced amounts
+/// Left by `close_stale` at a settled transfer's address, so its
+/// `[sender, nonce]` PDA can never be initialized again
+#[account]
+#[derive(InitSpace)]
+pub struct ClosedTransfer {}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MintStats {
+    pub mint: Pubkey,
+    pub total_outstanding: u64,
+    pub bump: u8,
+}
+
+/// Transfers attributed to one campaign id. Volumes add raw amounts across
+/// mints; executions count each paid occurrence
+#[account]
+#[derive(InitSpace)]
+pub struct CampaignStats {
+    pub campaign_id: u64,
+    pub scheduled_count: u64,
+    pub scheduled_volume: u64,
+    pub executed_count: u64,
+    pub executed_volume: u64,
+    pub bump: u8,
+}
+
+/// Lets `delegate` schedule transfers of `owner`'s `mint` tokens, up to a budget
+#[account]
+#[derive(InitSpace)]
+pub struct Delegation {
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub mint: Pubkey,
+    pub remaining_budget: u64,
+    pub bump: u8,
+}
+
+/// Head of a sender's linked list of pending transfers (newest first)
+#[account]
+#[derive(InitSpace)]
+pub struct SenderRegistry {
+    pub sender: Pubkey,
+    pub head: Option<Pubkey>,
+    pub count: u32,
+    /// When the latest `emergency_freeze` began
+    pub frozen_at: i64,
+    /// Tracked transfers cannot execute before this time
+    pub frozen_until: i64,
+    pub bump: u8,
+}
+
+/// A recipient's opt-in limits on transfers scheduled to them
+#[account]
+#[derive(InitSpace)]
+pub struct RecipientPolicy {
+    pub recipient: Pubkey,
+    /// Minimum seconds between transfers scheduled to the recipient; 0 disables it
+    pub cooldown_seconds: i64,
+    /// When the latest transfer to the recipient was scheduled while a cooldown was set
+    pub last_scheduled_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    pub admin: Pubkey,
+    pub allow_self_transfer: bool,
+    pub require_memo: bool,
+    pub min_keeper_fee_lamports: u64,
+    /// Recipients executing within this many seconds of `execute_after` earn a rebate
+    pub rebate_window_seconds: i64,
+    /// Paid from lamports deposited into this account above its rent reserve
+    pub rebate_lamports: u64,
+    /// Funds must stay escrowed this long after `created_at` before execution
+    pub min_escrow_seconds: i64,
+    /// Program trusted to convert escrow into a transfer's `output_mint`
+    pub swap_program: Option<Pubkey>,
+    /// Character limit on memos, on top of the byte cap; 0 disables it
+    pub max_memo_chars: u16,
+    /// Pay for payees' missing token accounts from the treasury at execution
+    pub sponsor_recipient_ata: bool,
+    /// Transfers of at least this amount (base units) need the extra delay; 0 disables
+    pub large_transfer_threshold: u64,
+    /// Added to `min_escrow_seconds` for the earliest `execute_after` of a large transfer
+    pub large_transfer_extra_delay: i64,
+    /// Who may execute transfers when this config is passed to execution
+    pub execution_mode: ExecutionMode,
+    /// Cap on `global_pending`; 0 means unlimited
+    pub max_global_pending: u64,
+    /// Seconds after settling that anyone may `close_stale` a transfer; 0 disables it
+    pub auto_close_after_seconds: i64,
+    /// Nonces must equal `derived_nonce` of the transfer's parameters
+    pub require_derived_nonce: bool,
+    /// Wait imposed after a recorded failed condition check; 0 disables it
+    pub condition_retry_backoff_seconds: i64,
+    /// Emit full lifecycle events; when off, schedule/execute/cancel emit only
+    /// `TransferStatusChanged`
+    pub verbose_events: bool,
+    /// Execution pays lamports sent straight to a SOL escrow to the payee rather
+    /// than returning them to the sender
+    pub credit_surplus_to_recipient: bool,
+    /// Unsettled transfers scheduled with this config passed while a cap was set
+    pub global_pending: u64,
+    /// Program-wide sequence advanced by each funds-moving instruction; read
+    /// through `get_last_seq`
+    pub event_seq: u64,
+    /// The program is being retired; `migrate_to_program` may move escrows out
+    pub sunset: bool,
+    /// Program whose PDAs `migrate_to_program` may move escrows to
+    pub successor_program: Option<Pubkey>,
+    pub bump: u8,
+}
 
-    pub token_program: Option<Program<'info, Token>>,
-    pub system_program: Program<'info, System>,
+/// Lifecycle stage reported by `TransferStatusChanged`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferStatus {
+    Scheduled,
+    Executed,
+    Cancelled,
 }
 
-#[derive(Accounts)]
-pub struct CancelScheduledTransfer<'info> {
-    #[account(
-        mut,
-        seeds = [b"transfer", transfer_account.sender.as_ref(), transfer_account.nonce.as_ref()],
-        bump = transfer_account.bump
-    )]
-    pub transfer_account: Account<'info, ScheduledTransfer>,
+/// How `schedule_transfer` sizes a transfer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmountSpec {
+    /// Exactly this many lamports or base units
+    Absolute(u64),
+    /// This share, in basis points, of the sender's balance at schedule time
+    Bps(u16),
+}
 
-    #[account(mut)]
-    pub sender: Signer<'info>,
+/// Program-wide rule for who may execute a transfer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ExecutionMode {
+    /// The payee, a keeper it authorized, or the transfer's whitelisted keepers
+    /// (the behaviour without a config)
+    RecipientOnly,
+    /// Anyone, unless the transfer whitelisted its keepers
+    Permissionless,
+    /// Only the transfer's whitelisted keepers; transfers without one can only be cancelled
+    AllowlistOnly,
+}
 
-    #[account(
-        mut,
-        constraint = sender_token_account.owner == sender.key() @ TransferError::InvalidTokenAccount
-    )]
-    pub sender_token_account: Option<Account<'info, TokenAccount>>,
+/// Addresses that may not be scheduled to or paid, managed by the config admin
+#[account]
+#[derive(InitSpace)]
+pub struct RecipientDenylist {
+    #[max_len(100)]
+    pub denied: Vec<Pubkey>,
+    pub bump: u8,
+}
 
-    #[account(
-        mut,
-        constraint = escrow_token_account.owner == transfer_account.key() @ TransferError::InvalidEscrowAccount
-    )]
-    pub escrow_token_account: Option<Account<'info, TokenAccount>>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ConfigUpdate {
+    pub allow_self_transfer: Option<bool>,
+    pub require_memo: Option<bool>,
+    pub min_keeper_fee_lamports: Option<u64>,
+    pub rebate_window_seconds: Option<i64>,
+    pub rebate_lamports: Option<u64>,
+    pub min_escrow_seconds: Option<i64>,
+    pub swap_program: Option<Pubkey>,
+    pub max_memo_chars: Option<u16>,
+    pub sponsor_recipient_ata: Option<bool>,
+    pub large_transfer_threshold: Option<u64>,
+    pub large_transfer_extra_delay: Option<i64>,
+    pub execution_mode: Option<ExecutionMode>,
+    pub max_global_pending: Option<u64>,
+    pub auto_close_after_seconds: Option<i64>,
+    pub require_derived_nonce: Option<bool>,
+    pub condition_retry_backoff_seconds: Option<i64>,
+    pub verbose_events: Option<bool>,
+    pub credit_surplus_to_recipient: Option<bool>,
+    pub sunset: Option<bool>,
+    pub successor_program: Option<Pubkey>,
+}
 
-    pub token_program: Option<Program<'info, Token>>,
-    pub system_program: Program<'info, System>,
+/// Arguments of `schedule_transfer` and `schedule_transfer_with_escrow`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ScheduleTransferParams {
+    pub amount: AmountSpec,
+    pub execute_after: i64,
+    pub nonce: [u8; 32],
+    pub memo: String,
+    pub allowed_executors: Vec<Pubkey>,
+    pub occurrences: u16,
+    pub interval_seconds: i64,
+    pub allow_recipient_reassignment: bool,
+    pub fallback_recipient: Option<Pubkey>,
+    pub fallback_after: i64,
+    pub emit_spl_memo: bool,
+    pub condition_program: Option<Pubkey>,
+    pub keeper_fee_lamports: u64,
+    pub refund_if_recipient_frozen: bool,
+    pub mint_receipt: bool,
+    pub irrevocable: bool,
+    pub max_execution_attempts: u16,
+    pub output_mint: Option<Pubkey>,
+    pub min_out: u64,
+    pub escalation_bps: u16,
+    pub recovery_key: Option<Pubkey>,
+    pub recovery_available_after: i64,
+    pub require_confirmation: bool,
+    pub campaign_id: u64,
+    pub monthly: bool,
+    pub refund_of: Option<Pubkey>,
+    pub sweep_all: bool,
+    pub recipient_is_pda: bool,
+    pub require_ack: bool,
+    /// Scale the keeper fee with the amount, with `keeper_fee_lamports` as the
+    /// floor and `keeper_fee_ceiling_lamports` as the cap; 0 keeps it flat
+    pub keeper_fee_bps: u16,
+    pub keeper_fee_ceiling_lamports: u64,
 }
 
-#[derive(Accounts)]
-pub struct GetTransferInfo<'info> {
-    pub transfer_account: Account<'info, ScheduledTransfer>,
+impl Default for ScheduleTransferParams {
+    fn default() -> Self {
+        Self {
+            amount: AmountSpec::Absolute(0),
+            execute_after: 0,
+            nonce: [0; 32],
+            memo: String::new(),
+            allowed_executors: Vec::new(),
+            occurrences: 1,
+            interval_seconds: 0,
+            allow_recipient_reassignment: false,
+            fallback_recipient: None,
+            fallback_after: 0,
+            emit_spl_memo: false,
+            condition_program: None,
+            keeper_fee_lamports: 0,
+            refund_if_recipient_frozen: false,
+            mint_receipt: false,
+            irrevocable: false,
+            max_execution_attempts: 0,
+            output_mint: None,
+            min_out: 0,
+            escalation_bps: 0,
+            recovery_key: None,
+            recovery_available_after: 0,
+            require_confirmation: false,
+            campaign_id: 0,
+            monthly: false,
+            refund_of: None,
+            sweep_all: false,
+            recipient_is_pda: false,
+            require_ack: false,
+            keeper_fee_bps: 0,
+            keeper_fee_ceiling_lamports: 0,
+        }
+    }
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct ScheduledTransfer {
-    pub sender: Pubkey,
+/// One SOL transfer within a `batch_schedule` call
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ScheduleParams {
     pub recipient: Pubkey,
     pub amount: u64,
-    pub token_mint: Pubkey,
     pub execute_after: i64,
-    pub created_at: i64,
-    pub executed: bool,
-    pub executed_at: i64,
-    pub cancelled: bool,
-    pub cancelled_at: i64,
     pub nonce: [u8; 32],
-    #[max_len(200)]
     pub memo: String,
-    pub bump: u8,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+/// One mint's entry in `get_reserves`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct MintReserve {
+    pub mint: Pubkey,
+    pub total_outstanding: u64,
+}
+
+/// One mint's entry in `get_claimable_total`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ClaimableTotal {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// Result of `verify_escrow_integrity`; `delta` is held minus owed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct IntegrityReport {
+    pub balanced: bool,
+    pub delta: i128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
 pub struct TransferInfo {
     pub sender: Pubkey,
     pub recipient: Pubkey,
@@ -379,11 +6689,18 @@ pub struct TransferInfo {
     pub token_mint: Pubkey,
     pub execute_after: i64,
     pub created_at: i64,
+    pub created_slot: u64,
+    pub last_modified_at: i64,
     pub executed: bool,
-    pub executed_at: i64,
+    /// `None` until executed, rather than the account's 0 placeholder
+    pub executed_at: Option<i64>,
     pub cancelled: bool,
-    pub cancelled_at: i64,
+    /// `None` until cancelled, rather than the account's 0 placeholder
+    pub cancelled_at: Option<i64>,
     pub memo: String,
+    /// Time reached and neither executed nor cancelled, as of the current clock
+    pub is_executable_now: bool,
+    pub program_version: u16,
 }
 
 #[event]
@@ -395,6 +6712,20 @@ pub struct TransferScheduled {
     pub token_mint: Pubkey,
     pub execute_after: i64,
     pub nonce: [u8; 32],
+    pub campaign_id: u64,
+    pub refund_of: Option<Pubkey>,
+    /// Also the transfer's `created_slot`
+    pub slot: u64,
+}
+
+#[event]
+pub struct LargeTransferScheduled {
+    pub transfer_id: Pubkey,
+    pub sender: Pubkey,
+    pub amount: u64,
+    pub token_mint: Pubkey,
+    pub execute_after: i64,
+    pub slot: u64,
 }
 
 #[event]
@@ -405,6 +6736,16 @@ pub struct TransferExecuted {
     pub amount: u64,
     pub token_mint: Pubkey,
     pub executed_at: i64,
+    pub refund_of: Option<Pubkey>,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TransferConfirmed {
+    pub transfer_id: Pubkey,
+    pub sender: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
 }
 
 #[event]
@@ -413,6 +6754,228 @@ pub struct TransferCancelled {
     pub sender: Pubkey,
     pub amount: u64,
     pub cancelled_at: i64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TransferReduced {
+    pub transfer_id: Pubkey,
+    pub sender: Pubkey,
+    pub reduced_by: u64,
+    pub new_amount: u64,
+    pub refunded: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RecipientReassigned {
+    pub transfer_id: Pubkey,
+    pub previous_recipient: Pubkey,
+    pub new_recipient: Pubkey,
+}
+
+#[event]
+pub struct ExecutionAuthorized {
+    pub transfer_id: Pubkey,
+    pub recipient: Pubkey,
+    pub until: i64,
+}
+
+#[event]
+pub struct MaxAttemptsExceeded {
+    pub transfer_id: Pubkey,
+    pub attempts: u16,
+    pub refunded: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RebatePaid {
+    pub transfer_id: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct SurplusReturned {
+    pub transfer_id: Pubkey,
+    pub sender: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct SurplusCredited {
+    pub transfer_id: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TransfersConsolidated {
+    pub transfer_id: Pubkey,
+    pub merged_transfers: Vec<Pubkey>,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct OwnershipTransferred {
+    pub transfer_id: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PolicyChanged {
+    pub old_allow_self_transfer: bool,
+    pub new_allow_self_transfer: bool,
+    pub old_require_memo: bool,
+    pub new_require_memo: bool,
+    pub old_sponsor_recipient_ata: bool,
+    pub new_sponsor_recipient_ata: bool,
+    pub old_require_derived_nonce: bool,
+    pub new_require_derived_nonce: bool,
+    pub old_verbose_events: bool,
+    pub new_verbose_events: bool,
+    pub old_credit_surplus_to_recipient: bool,
+    pub new_credit_surplus_to_recipient: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeeChanged {
+    pub old_min_keeper_fee_lamports: u64,
+    pub new_min_keeper_fee_lamports: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RebateChanged {
+    pub old_rebate_window_seconds: i64,
+    pub new_rebate_window_seconds: i64,
+    pub old_rebate_lamports: u64,
+    pub new_rebate_lamports: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LimitsChanged {
+    pub old_min_escrow_seconds: i64,
+    pub new_min_escrow_seconds: i64,
+    pub old_max_memo_chars: u16,
+    pub new_max_memo_chars: u16,
+    pub old_large_transfer_threshold: u64,
+    pub new_large_transfer_threshold: u64,
+    pub old_large_transfer_extra_delay: i64,
+    pub new_large_transfer_extra_delay: i64,
+    pub old_max_global_pending: u64,
+    pub new_max_global_pending: u64,
+    pub old_auto_close_after_seconds: i64,
+    pub new_auto_close_after_seconds: i64,
+    pub old_condition_retry_backoff_seconds: i64,
+    pub new_condition_retry_backoff_seconds: i64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ExecutionModeChanged {
+    pub old_execution_mode: ExecutionMode,
+    pub new_execution_mode: ExecutionMode,
+    pub slot: u64,
+}
+
+#[event]
+pub struct MilestoneSettled {
+    pub transfer_id: Pubkey,
+    pub arbiter: Pubkey,
+    pub index: u8,
+    pub amount: u64,
+    pub approved: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TransferRescheduled {
+    pub transfer_id: Pubkey,
+    pub new_transfer_id: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TransferMigrated {
+    pub transfer_id: Pubkey,
+    pub new_program: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+/// Execution of a `require_ack` transfer put its funds on hold in the
+/// transfer's own escrow until `payee` acknowledges by `ack_deadline`
+#[event]
+pub struct AcknowledgmentRequested {
+    pub transfer_id: Pubkey,
+    pub payee: Pubkey,
+    pub held_amount: u64,
+    pub ack_deadline: i64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct SenderFreezeChanged {
+    pub sender: Pubkey,
+    pub frozen_until: i64,
+    pub slot: u64,
+}
+
+/// Compact stand-in for the full lifecycle events when `verbose_events` is off
+#[event]
+pub struct TransferStatusChanged {
+    pub transfer_id: Pubkey,
+    pub status: TransferStatus,
+}
+
+#[event]
+pub struct TransferClosed {
+    pub transfer_id: Pubkey,
+    pub sender: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct Pong {
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SunsetChanged {
+    pub old_sunset: bool,
+    pub new_sunset: bool,
+    pub old_successor_program: Option<Pubkey>,
+    pub new_successor_program: Option<Pubkey>,
+    pub slot: u64,
+}
+
+#[event]
+pub struct SwapProgramChanged {
+    pub old_swap_program: Option<Pubkey>,
+    pub new_swap_program: Pubkey,
+    pub slot: u64,
+}
+
+#[event]
+pub struct IntegrityChecked {
+    pub transfer_id: Pubkey,
+    pub expected: u64,
+    pub held: u64,
+    pub delta: i128,
+    pub slot: u64,
 }
 
 #[error_code]
@@ -461,4 +7024,217 @@ pub enum TransferError {
 
     #[msg("Clock unavailable")]
     ClockUnavailable,
+
+    #[msg("Too many allowed executors")]
+    TooManyExecutors,
+
+    #[msg("Executor is not allowed to execute this transfer")]
+    ExecutorNotAllowed,
+
+    #[msg("Occurrence count must be at least one")]
+    InvalidOccurrences,
+
+    #[msg("Recurring transfers require a positive interval")]
+    InvalidInterval,
+
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+
+    #[msg("Sender has not permitted recipient reassignment")]
+    ReassignmentNotPermitted,
+
+    #[msg("Program config is already initialized")]
+    ConfigAlreadyInitialized,
+
+    #[msg("Signer is not the config admin")]
+    UnauthorizedAdmin,
+
+    #[msg("Sender and recipient must differ")]
+    SelfTransferNotAllowed,
+
+    #[msg("Fallback deadline must be after the execution time")]
+    InvalidFallbackTime,
+
+    #[msg("SPL Memo program account is required")]
+    MemoProgramRequired,
+
+    #[msg("Sender registry account is required")]
+    RegistryRequired,
+
+    #[msg("Previous transfer does not link to this transfer")]
+    InvalidListLink,
+
+    #[msg("Escrow cannot cover the amount while staying rent-exempt")]
+    EscrowBelowRentReserve,
+
+    #[msg("Condition program account is missing or does not match")]
+    InvalidConditionProgram,
+
+    #[msg("Custom condition program did not approve execution")]
+    CustomConditionFailed,
+
+    #[msg("Batch size is empty, too large, or does not match the accounts")]
+    InvalidBatchSize,
+
+    #[msg("Transfer account does not match the expected address")]
+    InvalidTransferAccount,
+
+    #[msg("A memo is required")]
+    MemoRequired,
+
+    #[msg("Keeper fee is below the configured minimum")]
+    KeeperFeeTooLow,
+
+    #[msg("Recipient token account is frozen")]
+    RecipientAccountFrozen,
+
+    #[msg("Receipt could not be minted; receipt accounts are missing")]
+    ReceiptMintFailed,
+
+    #[msg("Only the transfer's authority can do this")]
+    UnauthorizedAuthority,
+
+    #[msg("Recipient is on the denylist")]
+    RecipientDenied,
+
+    #[msg("Recipient denylist is full")]
+    DenylistFull,
+
+    #[msg("Transfer is irrevocable")]
+    TransferIrrevocable,
+
+    #[msg("Minimum escrow duration has not elapsed")]
+    MinEscrowNotElapsed,
+
+    #[msg("Swap program is missing or not the configured one")]
+    InvalidSwapProgram,
+
+    #[msg("Swap returned less than the minimum output")]
+    SlippageExceeded,
+
+    #[msg("Nonce has already been used by this sender")]
+    NonceAlreadyUsed,
+
+    #[msg("Memo has more characters than the configured maximum")]
+    MemoTooManyChars,
+
+    #[msg("Treasury cannot cover the recipient token account rent")]
+    TreasuryInsufficient,
+
+    #[msg("Mint stats account is required for this transfer")]
+    MintStatsRequired,
+
+    #[msg("Large transfers must be scheduled further in the future")]
+    LargeTransferDelayRequired,
+
+    #[msg("Recovery key cannot cancel before the recovery unlock time")]
+    UnauthorizedRecovery,
+
+    #[msg("Amount exceeds the delegation's remaining budget")]
+    DelegationBudgetExceeded,
+
+    #[msg("Transfer has not been confirmed")]
+    NotConfirmed,
+
+    #[msg("Transfer does not need confirmation")]
+    AlreadyConfirmed,
+
+    #[msg("Confirmation must wait until after the schedule transaction")]
+    ConfirmationTooEarly,
+
+    #[msg("The recipient's execution authorization has expired")]
+    AuthorizationExpired,
+
+    #[msg("Campaign stats account does not match the transfer's campaign id")]
+    CampaignMismatch,
+
+    #[msg("Transfers differ in sender, recipient, mint or shape and cannot be consolidated")]
+    ConsolidationMismatch,
+
+    #[msg("Transfer has not been overdue long enough to be marked unexecutable")]
+    NotYetUnexecutable,
+
+    #[msg("The program-wide limit on pending transfers has been reached")]
+    GlobalPendingLimitReached,
+
+    #[msg("Program config account is required for this transfer")]
+    ConfigRequired,
+
+    #[msg("Refund must reference a transfer from the recipient to the sender")]
+    InvalidRefundReference,
+
+    #[msg("sweep_all cannot be combined with an explicit amount")]
+    SweepAmountConflict,
+
+    #[msg("Transfer is not closeable until its retention period has passed")]
+    NotYetCloseable,
+
+    #[msg("Nonce must be derived from the transfer parameters")]
+    NonceNotDerived,
+
+    #[msg("Only the transfer's arbiter may settle milestones")]
+    UnauthorizedArbiter,
+
+    #[msg("Milestones must be 1-5 non-zero amounts adding up to a plain, funded escrow")]
+    InvalidMilestones,
+
+    #[msg("Transfer is released through its milestones")]
+    MilestoneGated,
+
+    #[msg("Milestone has already been approved or rejected")]
+    MilestoneAlreadySettled,
+
+    #[msg("Condition check failed recently; retry after the backoff")]
+    RetryTooSoon,
+
+    #[msg("Execution time must be a positive Unix timestamp")]
+    NonPositiveExecutionTime,
+
+    #[msg("Amount basis points cannot exceed 10000")]
+    InvalidAmountBps,
+
+    #[msg("Payee token account still exists; the transfer is not undeliverable")]
+    UndeliverableRecipient,
+
+    #[msg("Payee has until the reclaim grace period ends to reopen their token account")]
+    ReclaimGracePending,
+
+    #[msg("Sponsored payee token accounts are created at execution; nothing to reclaim")]
+    RecipientAtaSponsored,
+
+    #[msg("Sender has frozen their transfers")]
+    SenderFrozen,
+
+    #[msg("Freeze cannot be lifted yet")]
+    UnfreezeLocked,
+
+    #[msg("require_ack only supports one-off transfers without swaps, receipts or keeper fees")]
+    AckIncompatible,
+
+    #[msg("Transfer is waiting for the payee to acknowledge receipt")]
+    AckPending,
+
+    #[msg("Acknowledgment window has closed")]
+    AckWindowExpired,
+
+    #[msg("Transfer is not waiting for an acknowledgment")]
+    NotAwaitingAck,
+
+    #[msg("Recipient's cooldown between scheduled transfers has not elapsed")]
+    RecipientCooldownActive,
+
+    #[msg("Percentage keeper fees need bps up to 10000 and a ceiling at or above the flat fee")]
+    InvalidKeeperFee,
+
+    #[msg("Recipient program did not acknowledge the on_funds_received callback")]
+    CallbackFailed,
+
+    #[msg("Migration is only allowed once the program is sunset")]
+    ProgramNotSunset,
+
+    #[msg("A successor program must be configured")]
+    SuccessorProgramRequired,
+
+    #[msg("Config cannot be closed once it has sequenced events or counts pending transfers")]
+    ConfigInUse,
 }
\ No newline at end of file
@@ -3,15 +3,27 @@
  */
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{TokenAccount, Mint};
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{spl_token, TokenAccount, Mint};
+use solana_program::program_pack::Pack;
 use scheduled_transfer::{
-    program::ScheduledTransfer as ScheduledTransferProgram,
-    ScheduledTransfer, TransferError, TransferInfo,
+    compute_space, derived_nonce, escrow_ata,
+    program::ScheduledTransfer as ScheduledTransferProgram, spl_memo, AcknowledgmentRequested,
+    AmountSpec, CampaignStats, ClaimableTotal, ClosedTransfer, ConfigUpdate, Delegation,
+    ExecutionMode, FeeChanged, IntegrityReport, LargeTransferScheduled, MaxAttemptsExceeded,
+    Milestone, MintReserve, PolicyChanged, Pong, ProgramConfig, RebatePaid, ScheduleParams,
+    ScheduleTransferParams, ScheduledTransfer, SenderRegistry, SunsetChanged, SurplusCredited,
+    SurplusReturned, TransferCancelled, TransferError, TransferExecuted, TransferInfo,
+    TransferMigrated, TransferRescheduled, TransferScheduled, TransferStatus, TransferStatusChanged,
+    ACK_WINDOW_SECONDS, MAX_MEMO_CAPACITY, MAX_MILESTONES, RECLAIM_GRACE_SECONDS,
+    UNEXECUTABLE_AFTER_SECONDS, UNFREEZE_DELAY_SECONDS,
 };
 use solana_program_test::*;
 use solana_sdk::{
     signature::{Keypair, Signer},
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
+    instruction::InstructionError,
     clock::Clock,
     system_instruction,
 };
@@ -61,10 +73,13 @@ async fn test_schedule_sol_transfer() {
     // Create schedule transfer instruction
     let schedule_ix = anchor_lang::InstructionData::data(
         &scheduled_transfer::instruction::ScheduleTransfer {
-            amount,
-            execute_after,
-            nonce,
-            memo: memo.clone(),
+            params: ScheduleTransferParams {
+                amount: AmountSpec::Absolute(amount),
+                execute_after,
+                nonce,
+                memo: memo.clone(),
+                ..Default::default()
+            },
         }
     );
 
@@ -73,11 +88,21 @@ async fn test_schedule_sol_transfer() {
         sender: sender.pubkey(),
         recipient: recipient.pubkey(),
         token_mint: solana_program::system_program::id(),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(&recipient.pubkey()),
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        refund_of_transfer: None,
         sender_token_account: None,
         escrow_token_account: None,
         token_program: None,
+        associated_token_program: None,
         system_program: solana_program::system_program::id(),
         rent: solana_program::sysvar::rent::id(),
+        memo_program: None,
     };
 
     let schedule_tx = Transaction::new_signed_with_payer(
@@ -140,11 +165,35 @@ async fn test_execute_sol_transfer_before_time() {
             accounts: anchor_lang::ToAccountMetas::to_account_metas(
                 &scheduled_transfer::accounts::ExecuteScheduledTransfer {
                     transfer_account: transfer_account,
+                    executor: recipient.pubkey(),
                     recipient: recipient.pubkey(),
                     escrow_token_account: None,
                     recipient_token_account: None,
+                    recipient_output_token_account: None,
+                    new_recipient_token_account: None,
+                    token_mint: None,
+                    sender: None,
+                    sender_token_account: None,
+                    config: None,
+                    config_pda: config_pda(),
+                    denylist: denylist_pda(),
+                    registry: None,
+                    registry_pda: registry_pda(&sender.pubkey()),
+                    mint_stats: None,
+                    campaign_stats: None,
+                    previous_transfer: None,
+                    condition_program: None,
+                    swap_program: None,
+                    notify_program: None,
                     token_program: None,
                     system_program: solana_program::system_program::id(),
+                    memo_program: None,
+                    receipt_mint: None,
+                    receipt_token_account: None,
+                    receipt_metadata: None,
+                    associated_token_program: None,
+                    metadata_program: None,
+                    rent: None,
                 },
                 None,
             ),
@@ -206,6 +255,12 @@ async fn test_cancel_scheduled_transfer() {
                     sender: sender.pubkey(),
                     sender_token_account: None,
                     escrow_token_account: None,
+                    registry: None,
+                    mint_stats: None,
+                    previous_transfer: None,
+                    config: None,
+                    config_pda: config_pda(),
+                    recipient_token_account: None,
                     token_program: None,
                     system_program: solana_program::system_program::id(),
                 },
@@ -274,6 +329,12 @@ async fn test_unauthorized_cancellation() {
                     sender: unauthorized_user.pubkey(), // Wrong signer
                     sender_token_account: None,
                     escrow_token_account: None,
+                    registry: None,
+                    mint_stats: None,
+                    previous_transfer: None,
+                    config: None,
+                    config_pda: config_pda(),
+                    recipient_token_account: None,
                     token_program: None,
                     system_program: solana_program::system_program::id(),
                 },
@@ -436,10 +497,13 @@ fn create_schedule_transfer_tx(
 
     let schedule_ix = anchor_lang::InstructionData::data(
         &scheduled_transfer::instruction::ScheduleTransfer {
-            amount,
-            execute_after,
-            nonce,
-            memo,
+            params: ScheduleTransferParams {
+                amount: AmountSpec::Absolute(amount),
+                execute_after,
+                nonce,
+                memo,
+                ..Default::default()
+            },
         }
     );
 
@@ -448,11 +512,21 @@ fn create_schedule_transfer_tx(
         sender: sender.pubkey(),
         recipient: recipient.pubkey(),
         token_mint: solana_program::system_program::id(),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(&recipient.pubkey()),
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        refund_of_transfer: None,
         sender_token_account: None,
         escrow_token_account: None,
         token_program: None,
+        associated_token_program: None,
         system_program: solana_program::system_program::id(),
         rent: solana_program::sysvar::rent::id(),
+        memo_program: None,
     };
 
     Transaction::new_signed_with_payer(
@@ -465,4 +539,7364 @@ fn create_schedule_transfer_tx(
         &[payer, sender],
         recent_blockhash,
     )
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_allowed_executor_can_execute() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let keeper = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &keeper.pubkey(), 10_000_000).await;
+
+    let nonce = [3u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            allowed_executors: vec![keeper.pubkey()],
+            ..default_schedule_args(100_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute =
+        execute_sol_ix(&transfer_account, &sender.pubkey(), &keeper.pubkey(), &recipient.pubkey());
+    let result = send(&mut context, execute, &[&keeper]).await;
+    assert!(result.is_ok(), "Whitelisted keeper should execute: {:?}", result);
+
+    let recipient_balance = context
+        .banks_client
+        .get_balance(recipient.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(recipient_balance, 100_000_000);
+}
+
+#[tokio::test]
+async fn test_disallowed_executor_rejected() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let keeper = Keypair::new();
+    let intruder = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &intruder.pubkey(), 10_000_000).await;
+
+    let nonce = [4u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            allowed_executors: vec![keeper.pubkey()],
+            ..default_schedule_args(100_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &intruder.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&intruder]).await;
+    assert_transfer_error(result, TransferError::ExecutorNotAllowed);
+}
+
+#[tokio::test]
+async fn test_cancel_recurring_refunds_remaining_occurrences() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let nonce = [5u8; 32];
+    let amount = 100_000_000;
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            occurrences: 3,
+            interval_seconds: 3_600,
+            ..default_schedule_args(amount, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    let sender_balance_before = context
+        .banks_client
+        .get_balance(sender.pubkey())
+        .await
+        .unwrap();
+
+    let cancel = cancel_sol_ix(&transfer_account, &sender.pubkey());
+    send(&mut context, cancel, &[&sender]).await.unwrap();
+
+    let sender_balance_after = context
+        .banks_client
+        .get_balance(sender.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(sender_balance_after - sender_balance_before, 2 * amount);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.cancelled);
+    assert_eq!(transfer.executed_occurrences, 1);
+}
+
+#[tokio::test]
+async fn test_recipient_reassignment_permitted() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let new_recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let nonce = [6u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            allow_recipient_reassignment: true,
+            ..default_schedule_args(100_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let reassign = reassign_recipient_ix(
+        &transfer_account,
+        &recipient.pubkey(),
+        &new_recipient.pubkey(),
+    );
+    let result = send(&mut context, reassign, &[&recipient]).await;
+    assert!(result.is_ok(), "Reassignment should succeed: {:?}", result);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.recipient, new_recipient.pubkey());
+}
+
+#[tokio::test]
+async fn test_recipient_reassignment_forbidden() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let new_recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let nonce = [7u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let reassign = reassign_recipient_ix(
+        &transfer_account,
+        &recipient.pubkey(),
+        &new_recipient.pubkey(),
+    );
+    let result = send(&mut context, reassign, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::ReassignmentNotPermitted);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.recipient, recipient.pubkey());
+}
+
+#[tokio::test]
+async fn test_recipient_reassignment_checks_new_recipient() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let nonce = [11u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            allow_recipient_reassignment: true,
+            ..default_schedule_args(100_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let reassign = reassign_recipient_ix(&transfer_account, &recipient.pubkey(), &sender.pubkey());
+    let result = send(&mut context, reassign, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::SelfTransferNotAllowed);
+
+    // The transfer PDA stands in for any program-owned account
+    let reassign =
+        reassign_recipient_ix(&transfer_account, &recipient.pubkey(), &transfer_account);
+    let result = send(&mut context, reassign, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.recipient, recipient.pubkey());
+}
+
+#[tokio::test]
+async fn test_execution_event_reports_slot() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let nonce = [8u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let bank_slot = context.banks_client.get_sysvar::<Clock>().await.unwrap().slot;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+
+    let event: TransferExecuted = find_event(&logs).expect("TransferExecuted not emitted");
+    assert_eq!(event.slot, bank_slot);
+    assert_eq!(event.executed_at, execute_after);
+}
+
+#[tokio::test]
+async fn test_config_lifecycle() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+
+    // Initialize
+    let result = send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await;
+    assert!(result.is_ok(), "Failed to initialize config: {:?}", result);
+
+    let config = fetch_config(&mut context).await.expect("config not created");
+    assert_eq!(config.admin, admin.pubkey());
+
+    // Reject double initialization of a live config
+    let result = send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await;
+    assert!(result.is_err(), "Re-initializing a live config should fail");
+
+    // Non-admin cannot close
+    let intruder = Keypair::new();
+    fund_account(&mut context, &intruder.pubkey(), 10_000_000).await;
+    let result = send(&mut context, close_config_ix(&intruder.pubkey()), &[&intruder]).await;
+    assert_transfer_error(result, TransferError::UnauthorizedAdmin);
+
+    // Close returns rent to the admin
+    let admin_balance_before = context.banks_client.get_balance(admin.pubkey()).await.unwrap();
+    send(&mut context, close_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let admin_balance_after = context.banks_client.get_balance(admin.pubkey()).await.unwrap();
+    assert!(admin_balance_after > admin_balance_before);
+    assert!(fetch_config(&mut context).await.is_none());
+
+    // Re-initialize after close
+    let result = send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await;
+    assert!(result.is_ok(), "Failed to re-initialize config: {:?}", result);
+}
+
+#[tokio::test]
+async fn test_self_transfer_rejected_by_default() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &sender.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [9u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::SelfTransferNotAllowed);
+}
+
+#[tokio::test]
+async fn test_self_transfer_allowed_when_configured() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+
+    // A fresh config still disallows self-transfers
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &sender.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [10u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::SelfTransferNotAllowed);
+
+    let update = ConfigUpdate {
+        allow_self_transfer: Some(true),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &sender.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [10u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert!(result.is_ok(), "Configured self-transfer should succeed: {:?}", result);
+}
+
+#[tokio::test]
+async fn test_reduce_sol_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let nonce = [11u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(500_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let sender_balance_before = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+
+    let reduce = reduce_transfer_ix(&transfer_account, &sender.pubkey(), None, 200_000_000);
+    send(&mut context, reduce, &[&sender]).await.unwrap();
+
+    let sender_balance_after = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    assert_eq!(sender_balance_after - sender_balance_before, 200_000_000);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.amount, 300_000_000);
+    assert!(!transfer.cancelled);
+
+    // Reducing by the full remaining amount must go through cancel instead
+    let reduce = reduce_transfer_ix(&transfer_account, &sender.pubkey(), None, 300_000_000);
+    let result = send(&mut context, reduce, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidAmount);
+}
+
+#[tokio::test]
+async fn test_reduce_spl_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let nonce = [12u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(600_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 400_000);
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+    let reduce = reduce_transfer_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        Some((sender_token_account, escrow)),
+        250_000,
+    );
+    send(&mut context, reduce, &[&sender]).await.unwrap();
+
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 650_000);
+    assert_eq!(token_balance(&mut context, &escrow).await, 350_000);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.amount, 350_000);
+}
+
+#[tokio::test]
+async fn test_fallback_recipient_routing() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let contractor = Keypair::new();
+    let arbiter = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &contractor.pubkey(), 10_000_000).await;
+    fund_account(&mut context, &arbiter.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let fallback_after = execute_after + 3_600;
+    let args = |nonce| ScheduleTransferParams {
+        fallback_recipient: Some(arbiter.pubkey()),
+        fallback_after,
+        ..default_schedule_args(100_000_000, execute_after, nonce)
+    };
+
+    let before_deadline = [13u8; 32];
+    let after_deadline = [14u8; 32];
+    for nonce in [before_deadline, after_deadline] {
+        let schedule = schedule_sol_ix(&sender.pubkey(), &contractor.pubkey(), args(nonce));
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+
+    // Before the deadline the primary recipient is paid
+    warp_to_timestamp(&mut context, execute_after).await;
+    let transfer_account = transfer_pda(&sender.pubkey(), &before_deadline);
+    let execute =
+        execute_sol_ix(&transfer_account, &sender.pubkey(), &arbiter.pubkey(), &arbiter.pubkey());
+    let result = send(&mut context, execute, &[&arbiter]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &contractor.pubkey(),
+        &contractor.pubkey(),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&contractor]).await;
+    let event: TransferExecuted = find_event(&logs).unwrap();
+    assert_eq!(event.recipient, contractor.pubkey());
+
+    // After the deadline the fallback takes over
+    warp_to_timestamp(&mut context, fallback_after + 1).await;
+    let transfer_account = transfer_pda(&sender.pubkey(), &after_deadline);
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &contractor.pubkey(),
+        &contractor.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&contractor]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let arbiter_balance_before = context.banks_client.get_balance(arbiter.pubkey()).await.unwrap();
+    let execute =
+        execute_sol_ix(&transfer_account, &sender.pubkey(), &arbiter.pubkey(), &arbiter.pubkey());
+    let logs = send_with_logs(&mut context, execute, &[&arbiter]).await;
+    let event: TransferExecuted = find_event(&logs).unwrap();
+    assert_eq!(event.recipient, arbiter.pubkey());
+
+    let arbiter_balance_after = context.banks_client.get_balance(arbiter.pubkey()).await.unwrap();
+    assert_eq!(arbiter_balance_after - arbiter_balance_before, 100_000_000);
+}
+
+#[tokio::test]
+async fn test_spl_memo_emitted_on_schedule_and_execute() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let nonce = [15u8; 32];
+    let memo = "Invoice #42".to_string();
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    let schedule_accounts = scheduled_transfer::accounts::ScheduleTransfer {
+        transfer_account,
+        sender: sender.pubkey(),
+        recipient: recipient.pubkey(),
+        token_mint: solana_program::system_program::id(),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(&recipient.pubkey()),
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        refund_of_transfer: None,
+        sender_token_account: None,
+        escrow_token_account: None,
+        token_program: None,
+        associated_token_program: None,
+        system_program: solana_program::system_program::id(),
+        rent: solana_program::sysvar::rent::id(),
+        memo_program: Some(spl_memo::id()),
+    };
+    let schedule = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&schedule_accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ScheduleTransfer {
+                params: ScheduleTransferParams {
+                    memo: memo.clone(),
+                    emit_spl_memo: true,
+                    ..default_schedule_args(100_000_000, execute_after, nonce)
+                },
+            },
+        ),
+    };
+    let logs = send_with_logs(&mut context, schedule, &[&sender]).await;
+    assert!(logs.iter().any(|log| log.contains(&spl_memo::id().to_string())));
+    assert!(logs.iter().any(|log| log.contains(&memo)));
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let execute_accounts = scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        transfer_account,
+        executor: recipient.pubkey(),
+        recipient: recipient.pubkey(),
+        escrow_token_account: None,
+        recipient_token_account: None,
+        recipient_output_token_account: None,
+        new_recipient_token_account: None,
+        token_mint: None,
+        sender: None,
+        sender_token_account: None,
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        registry: None,
+        registry_pda: registry_pda(&sender.pubkey()),
+        mint_stats: None,
+        campaign_stats: None,
+        previous_transfer: None,
+        condition_program: None,
+        swap_program: None,
+        notify_program: None,
+        token_program: None,
+        system_program: solana_program::system_program::id(),
+        memo_program: Some(spl_memo::id()),
+        receipt_mint: None,
+        receipt_token_account: None,
+        receipt_metadata: None,
+        associated_token_program: None,
+        metadata_program: None,
+        rent: None,
+    };
+    let execute = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&execute_accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ExecuteScheduledTransfer {},
+        ),
+    };
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+    assert!(logs.iter().any(|log| log.contains(&spl_memo::id().to_string())));
+    assert!(logs.iter().any(|log| log.contains(&memo)));
+}
+
+#[tokio::test]
+async fn test_registry_pagination_and_middle_removal() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 2_000_000_000).await;
+
+    send(&mut context, initialize_registry_ix(&sender.pubkey()), &[&sender]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonces = [[16u8; 32], [17u8; 32], [18u8; 32]];
+    for nonce in nonces {
+        let schedule = schedule_sol_ix_with_registry(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(100_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    let [first, middle, last] = nonces.map(|nonce| transfer_pda(&sender.pubkey(), &nonce));
+
+    // Newest transfers come first, split across pages
+    let registry = fetch_registry(&mut context, &sender.pubkey()).await;
+    assert_eq!(registry.count, 3);
+    let transfers = fetch_transfers(&mut context, &[first, middle, last]).await;
+    let (page, cursor) = scheduled_transfer::next_page(registry.head, 2, |key| {
+        transfers.get(key).cloned()
+    });
+    assert_eq!(page, vec![last, middle]);
+    assert_eq!(cursor, Some(first));
+    let (page, cursor) = scheduled_transfer::next_page(cursor, 2, |key| {
+        transfers.get(key).cloned()
+    });
+    assert_eq!(page, vec![first]);
+    assert_eq!(cursor, None);
+
+    // Cancelling the middle transfer requires its predecessor
+    let cancel = build_cancel_sol_ix(
+        &middle,
+        &sender.pubkey(),
+        Some(registry_pda(&sender.pubkey())),
+        None,
+        None,
+        None,
+    );
+    let result = send(&mut context, cancel, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidListLink);
+
+    let cancel = build_cancel_sol_ix(
+        &middle,
+        &sender.pubkey(),
+        Some(registry_pda(&sender.pubkey())),
+        Some(last),
+        None,
+        None,
+    );
+    send(&mut context, cancel, &[&sender]).await.unwrap();
+
+    let registry = fetch_registry(&mut context, &sender.pubkey()).await;
+    assert_eq!(registry.count, 2);
+    let transfers = fetch_transfers(&mut context, &[first, middle, last]).await;
+    let (page, cursor) = scheduled_transfer::next_page(registry.head, 10, |key| {
+        transfers.get(key).cloned()
+    });
+    assert_eq!(page, vec![last, first]);
+    assert_eq!(cursor, None);
+    assert!(!transfers[&middle].in_registry);
+}
+
+#[tokio::test]
+async fn test_sol_escrow_stays_rent_exempt_through_execution() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let nonce = [19u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(500_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let account = context.banks_client.get_account(transfer_account).await.unwrap().unwrap();
+    let minimum_balance = rent.minimum_balance(account.data.len());
+    assert_eq!(account.lamports, minimum_balance + 500_000_000);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.rent_reserve, minimum_balance);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    let account = context.banks_client.get_account(transfer_account).await.unwrap().unwrap();
+    assert_eq!(account.lamports, minimum_balance);
+    assert!(rent.is_exempt(account.lamports, account.data.len()));
+}
+
+#[tokio::test]
+async fn test_custom_condition_program_gates_execution() {
+    let approving_program = Pubkey::new_unique();
+    let rejecting_program = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_program(
+        "mock_condition_approve",
+        approving_program,
+        processor!(approve_condition),
+    );
+    program_test.add_program(
+        "mock_condition_reject",
+        rejecting_program,
+        processor!(reject_condition),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let approved_nonce = [20u8; 32];
+    let rejected_nonce = [21u8; 32];
+    for (nonce, condition_program) in [
+        (approved_nonce, approving_program),
+        (rejected_nonce, rejecting_program),
+    ] {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                condition_program: Some(condition_program),
+                ..default_schedule_args(100_000_000, execute_after, nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &approved_nonce);
+    let execute = build_execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+        Some(approving_program),
+        None,
+        None,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert!(result.is_ok(), "Approved condition should execute: {:?}", result);
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &rejected_nonce);
+    let execute = build_execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+        Some(rejecting_program),
+        None,
+        None,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::CustomConditionFailed);
+
+    // Omitting the condition program cannot bypass the check
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::InvalidConditionProgram);
+}
+
+#[tokio::test]
+async fn test_batch_schedule_initializes_each_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 2_000_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let params: Vec<ScheduleParams> = (0..3u8)
+        .map(|i| ScheduleParams {
+            recipient: Pubkey::new_unique(),
+            amount: 100_000_000 * (i as u64 + 1),
+            execute_after: execute_after + i as i64,
+            nonce: [30 + i; 32],
+            memo: format!("batch {}", i),
+        })
+        .collect();
+
+    let accounts = scheduled_transfer::accounts::BatchSchedule {
+        sender: sender.pubkey(),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        mint_stats: None,
+        system_program: solana_program::system_program::id(),
+    };
+    let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    account_metas.extend(params.iter().flat_map(|p| batch_entry_metas(&sender.pubkey(), p)));
+    let batch = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: account_metas,
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::BatchSchedule { params: params.clone() },
+        ),
+    };
+    let logs = send_with_logs(&mut context, batch, &[&sender]).await;
+    let scheduled_events = logs
+        .iter()
+        .filter(|log| log.starts_with("Program data: "))
+        .count();
+    assert_eq!(scheduled_events, 3);
+
+    for p in &params {
+        let transfer_account = transfer_pda(&sender.pubkey(), &p.nonce);
+        let transfer = fetch_transfer(&mut context, &transfer_account).await;
+        assert_eq!(transfer.sender, sender.pubkey());
+        assert_eq!(transfer.recipient, p.recipient);
+        assert_eq!(transfer.amount, p.amount);
+        assert_eq!(transfer.execute_after, p.execute_after);
+        assert_eq!(transfer.memo, p.memo);
+        assert_eq!(transfer.total_occurrences, 1);
+        assert!(!transfer.executed && !transfer.cancelled);
+
+        let account = context.banks_client.get_account(transfer_account).await.unwrap().unwrap();
+        assert_eq!(account.lamports, transfer.rent_reserve + p.amount);
+    }
+}
+
+#[tokio::test]
+async fn test_batch_schedule_validates_like_schedule_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 500_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let batch_ix = |entry: ScheduleParams| {
+        let accounts = scheduled_transfer::accounts::BatchSchedule {
+            sender: sender.pubkey(),
+            config: None,
+            config_pda: config_pda(),
+            denylist: denylist_pda(),
+            mint_stats: None,
+            system_program: solana_program::system_program::id(),
+        };
+        let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+        account_metas.extend(batch_entry_metas(&sender.pubkey(), &entry));
+        Instruction {
+            program_id: scheduled_transfer::id(),
+            accounts: account_metas,
+            data: anchor_lang::InstructionData::data(
+                &scheduled_transfer::instruction::BatchSchedule { params: vec![entry] },
+            ),
+        }
+    };
+
+    // The clock sysvar stands in for any program-owned recipient
+    let result = send(
+        &mut context,
+        batch_ix(ScheduleParams {
+            recipient: solana_program::sysvar::clock::id(),
+            amount: 1_000_000,
+            execute_after,
+            nonce: [33u8; 32],
+            memo: String::new(),
+        }),
+        &[&sender],
+    )
+    .await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let result = send(
+        &mut context,
+        batch_ix(ScheduleParams {
+            recipient: Pubkey::new_unique(),
+            amount: 1_000_000_000,
+            execute_after,
+            nonce: [34u8; 32],
+            memo: String::new(),
+        }),
+        &[&sender],
+    )
+    .await;
+    assert_transfer_error(result, TransferError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn test_require_memo_enforcement() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let execute_after = current_timestamp(&mut context).await + 60;
+
+    // Not required by default
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [22u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert!(result.is_ok(), "Empty memo should be accepted by default: {:?}", result);
+
+    let update = ConfigUpdate {
+        require_memo: Some(true),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    // Leaving the config out does not skip the memo requirement
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [23u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [23u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MemoRequired);
+
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            memo: "Payroll October".to_string(),
+            ..default_schedule_args(100_000_000, execute_after, [23u8; 32])
+        },
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert!(result.is_ok(), "Non-empty memo should be accepted: {:?}", result);
+}
+
+#[tokio::test]
+async fn test_get_due_count_counts_only_past_due() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 2_000_000_000).await;
+
+    let now = current_timestamp(&mut context).await;
+    let schedules = [
+        ([24u8; 32], now + 60),
+        ([25u8; 32], now + 120),
+        ([26u8; 32], now + 3_600),
+    ];
+    for (nonce, execute_after) in schedules {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(100_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    let transfer_accounts: Vec<Pubkey> = schedules
+        .iter()
+        .map(|(nonce, _)| transfer_pda(&sender.pubkey(), nonce))
+        .collect();
+
+    assert_eq!(due_count(&mut context, &transfer_accounts).await, 0);
+
+    warp_to_timestamp(&mut context, now + 120).await;
+    assert_eq!(due_count(&mut context, &transfer_accounts).await, 2);
+
+    // Settled transfers are no longer due
+    let cancel = cancel_sol_ix(&transfer_accounts[0], &sender.pubkey());
+    send(&mut context, cancel, &[&sender]).await.unwrap();
+    assert_eq!(due_count(&mut context, &transfer_accounts).await, 1);
+}
+
+#[tokio::test]
+async fn test_min_keeper_fee_enforced_and_paid() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let keeper = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &keeper.pubkey(), 10_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        min_keeper_fee_lamports: Some(50_000),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [27u8; 32];
+    let args = |keeper_fee_lamports| ScheduleTransferParams {
+        allowed_executors: vec![keeper.pubkey()],
+        keeper_fee_lamports,
+        ..default_schedule_args(100_000_000, execute_after, nonce)
+    };
+
+    // Leaving the config out does not skip the fee floor
+    let schedule = schedule_sol_ix(&sender.pubkey(), &recipient.pubkey(), args(49_999));
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+
+    let schedule =
+        schedule_sol_ix_with_config(&sender.pubkey(), &recipient.pubkey(), args(49_999));
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::KeeperFeeTooLow);
+
+    let schedule =
+        schedule_sol_ix_with_config(&sender.pubkey(), &recipient.pubkey(), args(50_000));
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert!(result.is_ok(), "At-floor keeper fee should succeed: {:?}", result);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let keeper_balance_before = context.banks_client.get_balance(keeper.pubkey()).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &keeper.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&keeper]).await.unwrap();
+
+    let keeper_balance_after = context.banks_client.get_balance(keeper.pubkey()).await.unwrap();
+    assert_eq!(keeper_balance_after - keeper_balance_before, 50_000);
+    assert_eq!(
+        context.banks_client.get_balance(recipient.pubkey()).await.unwrap(),
+        100_000_000
+    );
+}
+
+#[tokio::test]
+async fn test_verify_escrow_integrity() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let nonce = [28u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            keeper_fee_lamports: 5_000,
+            ..default_schedule_args(100_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let report = verify_escrow_integrity(&mut context, &transfer_account).await;
+    assert!(report.balanced);
+    assert_eq!(report.delta, 0);
+
+    // Artificially drain part of the escrow
+    let mut account = context.banks_client.get_account(transfer_account).await.unwrap().unwrap();
+    account.lamports -= 1_000;
+    context.set_account(&transfer_account, &account.into());
+
+    let report = verify_escrow_integrity(&mut context, &transfer_account).await;
+    assert!(!report.balanced);
+    assert_eq!(report.delta, -1_000);
+}
+
+#[tokio::test]
+async fn test_frozen_recipient_token_account() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_token_account =
+        create_token_account(&mut context, &mint, &recipient.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let abort_nonce = [29u8; 32];
+    let refund_nonce = [30u8; 32];
+    for (nonce, refund_if_recipient_frozen) in [(abort_nonce, false), (refund_nonce, true)] {
+        let schedule = schedule_spl_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &mint,
+            &sender_token_account,
+            ScheduleTransferParams {
+                refund_if_recipient_frozen,
+                ..default_schedule_args(400_000, execute_after, nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 200_000);
+
+    let freeze = spl_token::instruction::freeze_account(
+        &spl_token::id(),
+        &recipient_token_account,
+        &mint,
+        &context.payer.pubkey(),
+        &[],
+    )
+    .unwrap();
+    send(&mut context, freeze, &[]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    // Default behavior aborts with a clear error
+    let transfer_account = transfer_pda(&sender.pubkey(), &abort_nonce);
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow_ata(&transfer_account, &mint),
+        &recipient_token_account,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::RecipientAccountFrozen);
+
+    // Opted-in transfers are cancelled and refunded to the sender
+    let transfer_account = transfer_pda(&sender.pubkey(), &refund_nonce);
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow_ata(&transfer_account, &mint),
+        &recipient_token_account,
+        Some((sender.pubkey(), sender_token_account)),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 600_000);
+    assert_eq!(token_balance(&mut context, &recipient_token_account).await, 0);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.cancelled);
+    assert!(!transfer.executed);
+}
+
+#[tokio::test]
+async fn test_schedule_with_precreated_escrow() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+    let execute_after = current_timestamp(&mut context).await + 60;
+
+    // A token account owned by the PDA but not at its ATA address is rejected
+    let nonce = [40u8; 32];
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let stray_account = create_token_account(&mut context, &mint, &transfer_account).await;
+    let schedule = schedule_with_escrow_ix(
+        &sender.pubkey(),
+        &recipient,
+        &mint,
+        &sender_token_account,
+        &stray_account,
+        250_000,
+        execute_after,
+        nonce,
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidEscrowAccount);
+
+    // The relayer creates the canonical ATA up front, then schedules against it
+    let escrow = create_escrow_ata(&mut context, &transfer_account, &mint).await;
+    let schedule = schedule_with_escrow_ix(
+        &sender.pubkey(),
+        &recipient,
+        &mint,
+        &sender_token_account,
+        &escrow,
+        250_000,
+        execute_after,
+        nonce,
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    assert_eq!(token_balance(&mut context, &escrow).await, 250_000);
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 750_000);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.amount, 250_000);
+    assert_eq!(transfer.token_mint, mint);
+}
+
+#[tokio::test]
+async fn test_receipt_minted_on_execution() {
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_program(
+        "mock_token_metadata",
+        anchor_spl::metadata::ID,
+        processor!(accept_metadata),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 100_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let with_receipt = [41u8; 32];
+    let without_receipt = [42u8; 32];
+    for (nonce, mint_receipt) in [(with_receipt, true), (without_receipt, false)] {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                mint_receipt,
+                ..default_schedule_args(1_000_000, execute_after, nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    // Opted-in transfers cannot execute without the receipt accounts
+    let transfer_account = transfer_pda(&sender.pubkey(), &with_receipt);
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::ReceiptMintFailed);
+
+    let receipt_mint = Keypair::new();
+    let execute = build_execute_receipt_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &receipt_mint.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient, &receipt_mint]).await.unwrap();
+
+    let receipt_account = get_associated_token_address(&recipient.pubkey(), &receipt_mint.pubkey());
+    assert_eq!(token_balance(&mut context, &receipt_account).await, 1);
+    let account = context
+        .banks_client
+        .get_account(receipt_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let receipt = spl_token::state::Account::unpack(&account.data).unwrap();
+    assert!(receipt.is_frozen());
+
+    // Without the flag no receipt mint is created, even if the accounts are passed
+    let transfer_account = transfer_pda(&sender.pubkey(), &without_receipt);
+    let receipt_mint = Keypair::new();
+    let execute = build_execute_receipt_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &receipt_mint.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient, &receipt_mint]).await.unwrap();
+
+    let mint_account = context
+        .banks_client
+        .get_account(receipt_mint.pubkey())
+        .await
+        .unwrap();
+    assert!(mint_account.is_none());
+}
+
+#[tokio::test]
+async fn test_early_execution_logs_context() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let now = current_timestamp(&mut context).await;
+    let execute_after = now + 3600;
+    let nonce = [43u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(1_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let (result, logs) = process_with_logs(&mut context, execute, &[&recipient]).await;
+    assert!(result.is_err());
+
+    let expected = format!("execute_after={}", execute_after);
+    assert!(
+        logs.iter().any(|log| {
+            log.contains("Execution time not reached: now=") && log.contains(&expected)
+        }),
+        "missing context log in {:?}",
+        logs
+    );
+}
+
+#[tokio::test]
+async fn test_transfer_ownership() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let new_authority = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &new_authority.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 3600;
+    let nonce = [44u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient,
+        default_schedule_args(5_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // Only the current authority can hand the transfer over
+    let hijack = transfer_ownership_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &new_authority.pubkey(),
+        &new_authority.pubkey(),
+    );
+    let result = send(&mut context, hijack, &[&new_authority]).await;
+    assert_transfer_error(result, TransferError::UnauthorizedAuthority);
+
+    let rotate = transfer_ownership_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &sender.pubkey(),
+        &new_authority.pubkey(),
+    );
+    send(&mut context, rotate, &[&sender]).await.unwrap();
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.authority, new_authority.pubkey());
+    assert_eq!(transfer.sender, sender.pubkey());
+
+    // The old key can no longer cancel
+    let cancel = cancel_sol_ix(&transfer_account, &sender.pubkey());
+    let result = send(&mut context, cancel, &[&sender]).await;
+    assert_transfer_error(result, TransferError::UnauthorizedCancellation);
+
+    // The new authority can, and receives the refund
+    let balance_before = context
+        .banks_client
+        .get_balance(new_authority.pubkey())
+        .await
+        .unwrap();
+    let cancel = cancel_sol_ix(&transfer_account, &new_authority.pubkey());
+    send(&mut context, cancel, &[&new_authority]).await.unwrap();
+    let balance_after = context
+        .banks_client
+        .get_balance(new_authority.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(balance_after - balance_before, 5_000_000);
+    assert!(fetch_transfer(&mut context, &transfer_account).await.cancelled);
+}
+
+#[tokio::test]
+async fn test_surplus_returned_on_execution() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_token_account =
+        create_token_account(&mut context, &mint, &recipient.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [45u8; 32];
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(300_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    // Someone sends extra tokens straight to the escrow
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+    mint_tokens(&mut context, &mint, &escrow, 50_000).await;
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow,
+        &recipient_token_account,
+        Some((sender.pubkey(), sender_token_account)),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+
+    let event = find_event::<SurplusReturned>(&logs).expect("SurplusReturned not emitted");
+    assert_eq!(event.amount, 50_000);
+    assert_eq!(event.sender, sender.pubkey());
+    assert_eq!(token_balance(&mut context, &recipient_token_account).await, 300_000);
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 750_000);
+    assert_eq!(token_balance(&mut context, &escrow).await, 0);
+}
+
+#[tokio::test]
+async fn test_transfer_info_is_executable_now() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [46u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(1_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    assert!(!transfer_info(&mut context, &transfer_account).await.is_executable_now);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    assert!(transfer_info(&mut context, &transfer_account).await.is_executable_now);
+
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    let info = transfer_info(&mut context, &transfer_account).await;
+    assert!(info.executed);
+    assert!(!info.is_executable_now);
+}
+
+#[tokio::test]
+async fn test_recipient_denylist() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let denied = Pubkey::new_unique();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    send(&mut context, initialize_denylist_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+
+    // Only the config admin manages the list
+    let result = send(
+        &mut context,
+        manage_denylist_ix(&sender.pubkey(), &denied, true),
+        &[&sender],
+    )
+    .await;
+    assert_transfer_error(result, TransferError::UnauthorizedAdmin);
+    send(&mut context, manage_denylist_ix(&admin.pubkey(), &denied, true), &[&admin])
+        .await
+        .unwrap();
+
+    // Scheduling-time denial
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &denied,
+        default_schedule_args(1_000_000, execute_after, [47u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::RecipientDenied);
+
+    // Execution-time denial for an address added after scheduling
+    let nonce = [48u8; 32];
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(1_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let deny = manage_denylist_ix(&admin.pubkey(), &recipient.pubkey(), true);
+    send(&mut context, deny, &[&admin]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::RecipientDenied);
+
+    // Removing the address lets the transfer go through
+    let allow = manage_denylist_ix(&admin.pubkey(), &recipient.pubkey(), false);
+    send(&mut context, allow, &[&admin]).await.unwrap();
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    assert!(fetch_transfer(&mut context, &transfer_account).await.executed);
+}
+
+#[tokio::test]
+async fn test_on_time_rebate() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        rebate_window_seconds: Some(100),
+        rebate_lamports: Some(20_000),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+    // Anyone can top up the rebate pool held by the config account
+    fund_account(&mut context, &config_pda(), 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let prompt_nonce = [49u8; 32];
+    let late_nonce = [50u8; 32];
+    for nonce in [prompt_nonce, late_nonce] {
+        let schedule = schedule_sol_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(1_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+
+    // Inside the window the recipient earns the rebate on top of the amount
+    warp_to_timestamp(&mut context, execute_after + 50).await;
+    let transfer_account = transfer_pda(&sender.pubkey(), &prompt_nonce);
+    let balance_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+    let balance_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(balance_after - balance_before, 1_020_000);
+    let event = find_event::<RebatePaid>(&logs).expect("RebatePaid not emitted");
+    assert_eq!(event.amount, 20_000);
+    assert_eq!(event.recipient, recipient.pubkey());
+
+    // Outside the window only the amount is paid
+    warp_to_timestamp(&mut context, execute_after + 101).await;
+    let transfer_account = transfer_pda(&sender.pubkey(), &late_nonce);
+    let balance_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+    let balance_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(balance_after - balance_before, 1_000_000);
+    assert!(find_event::<RebatePaid>(&logs).is_none());
+}
+
+#[tokio::test]
+async fn test_irrevocable_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [51u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            irrevocable: true,
+            ..default_schedule_args(2_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    let cancel = cancel_sol_ix(&transfer_account, &sender.pubkey());
+    let result = send(&mut context, cancel, &[&sender]).await;
+    assert_transfer_error(result, TransferError::TransferIrrevocable);
+
+    let reduce = reduce_transfer_ix(&transfer_account, &sender.pubkey(), None, 500_000);
+    let result = send(&mut context, reduce, &[&sender]).await;
+    assert_transfer_error(result, TransferError::TransferIrrevocable);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let balance_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    let balance_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(balance_after - balance_before, 2_000_000);
+    assert!(fetch_transfer(&mut context, &transfer_account).await.executed);
+}
+
+#[tokio::test]
+async fn test_get_program_config_and_change_events() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+
+    let config = program_config(&mut context).await;
+    assert_eq!(config.admin, admin.pubkey());
+    assert!(!config.require_memo);
+    assert_eq!(config.min_keeper_fee_lamports, 0);
+
+    let update = ConfigUpdate {
+        require_memo: Some(true),
+        min_keeper_fee_lamports: Some(7_000),
+        ..ConfigUpdate::default()
+    };
+    let logs = send_with_logs(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin])
+        .await;
+
+    let fee = find_event::<FeeChanged>(&logs).expect("FeeChanged not emitted");
+    assert_eq!(fee.old_min_keeper_fee_lamports, 0);
+    assert_eq!(fee.new_min_keeper_fee_lamports, 7_000);
+    let policy = find_event::<PolicyChanged>(&logs).expect("PolicyChanged not emitted");
+    assert!(!policy.old_require_memo);
+    assert!(policy.new_require_memo);
+    assert_eq!(policy.old_allow_self_transfer, policy.new_allow_self_transfer);
+
+    let config = program_config(&mut context).await;
+    assert!(config.require_memo);
+    assert_eq!(config.min_keeper_fee_lamports, 7_000);
+
+    // Untouched groups emit nothing
+    let update = ConfigUpdate {
+        min_keeper_fee_lamports: Some(9_000),
+        ..ConfigUpdate::default()
+    };
+    let logs = send_with_logs(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin])
+        .await;
+    assert_eq!(find_event::<FeeChanged>(&logs).unwrap().old_min_keeper_fee_lamports, 7_000);
+    assert!(find_event::<PolicyChanged>(&logs).is_none());
+}
+
+#[tokio::test]
+async fn test_max_execution_attempts_auto_refund() {
+    let rejecting_program = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_program(
+        "mock_condition_reject",
+        rejecting_program,
+        processor!(reject_condition),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [52u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            condition_program: Some(rejecting_program),
+            max_execution_attempts: 2,
+            ..default_schedule_args(3_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = || {
+        build_execute_sol_ix(
+            &transfer_account,
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &recipient.pubkey(),
+            Some(rejecting_program),
+            None,
+            Some(sender.pubkey()),
+            None,
+        )
+    };
+
+    // Not due yet: fails without counting an attempt
+    let result = send(&mut context, execute(), &[&recipient]).await;
+    assert_transfer_error(result, TransferError::ExecutionTimeNotReached);
+    assert_eq!(fetch_transfer(&mut context, &transfer_account).await.execution_attempts, 0);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    // First failed condition is recorded and the transfer stays pending
+    send(&mut context, execute(), &[&recipient]).await.unwrap();
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.execution_attempts, 1);
+    assert!(!transfer.cancelled);
+
+    // Hitting the limit cancels and refunds the sender
+    let balance_before = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    let logs = send_with_logs(&mut context, execute(), &[&recipient]).await;
+    let balance_after = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    assert_eq!(balance_after - balance_before, 3_000_000);
+
+    let event = find_event::<MaxAttemptsExceeded>(&logs).expect("MaxAttemptsExceeded not emitted");
+    assert_eq!(event.attempts, 2);
+    assert_eq!(event.refunded, 3_000_000);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.cancelled);
+    assert!(!transfer.executed);
+}
+
+#[tokio::test]
+async fn test_min_escrow_duration_enforced_at_execution() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        min_escrow_seconds: Some(3600),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [53u8; 32];
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(1_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let created_at = fetch_transfer(&mut context, &transfer_account).await.created_at;
+
+    // `execute_after` has passed but the escrow floor has not
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::MinEscrowNotElapsed);
+
+    // Leaving the config out does not skip the escrow floor
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+
+    warp_to_timestamp(&mut context, created_at + 3600).await;
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    assert!(fetch_transfer(&mut context, &transfer_account).await.executed);
+}
+
+#[tokio::test]
+async fn test_swap_on_execution() {
+    let swap_program = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_program("mock_swap", swap_program, processor!(mock_swap));
+    let mut context = program_test.start_with_context().await;
+
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        swap_program: Some(swap_program),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    // The mock pays out 2 output tokens per input token, minting under its own PDA
+    let input_mint = create_mint(&mut context).await;
+    let output_mint = create_mint(&mut context).await;
+    let (mint_authority, _) = Pubkey::find_program_address(&[b"mint"], &swap_program);
+    let hand_over = spl_token::instruction::set_authority(
+        &spl_token::id(),
+        &output_mint,
+        Some(&mint_authority),
+        spl_token::instruction::AuthorityType::MintTokens,
+        &context.payer.pubkey(),
+        &[],
+    )
+    .unwrap();
+    send(&mut context, hand_over, &[]).await.unwrap();
+    let vault = create_token_account(&mut context, &input_mint, &swap_program).await;
+
+    let sender_token_account =
+        create_token_account(&mut context, &input_mint, &sender.pubkey()).await;
+    let recipient_output =
+        create_token_account(&mut context, &output_mint, &recipient.pubkey()).await;
+    mint_tokens(&mut context, &input_mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let swapped_nonce = [54u8; 32];
+    let greedy_nonce = [55u8; 32];
+    for (nonce, min_out) in [(swapped_nonce, 200_000), (greedy_nonce, 200_001)] {
+        let schedule = schedule_spl_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &input_mint,
+            &sender_token_account,
+            ScheduleTransferParams {
+                output_mint: Some(output_mint),
+                min_out,
+                ..default_schedule_args(100_000, execute_after, nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let swap_execute = |transfer_account: Pubkey| {
+        let accounts = scheduled_transfer::accounts::ExecuteScheduledTransfer {
+            transfer_account,
+            executor: recipient.pubkey(),
+            recipient: recipient.pubkey(),
+            escrow_token_account: Some(escrow_ata(&transfer_account, &input_mint)),
+            recipient_token_account: None,
+            recipient_output_token_account: Some(recipient_output),
+            new_recipient_token_account: None,
+            token_mint: None,
+            sender: None,
+            sender_token_account: None,
+            config: Some(config_pda()),
+            config_pda: config_pda(),
+            denylist: denylist_pda(),
+            registry: None,
+            registry_pda: registry_pda(&sender.pubkey()),
+            mint_stats: None,
+            campaign_stats: None,
+            previous_transfer: None,
+            condition_program: None,
+            swap_program: Some(swap_program),
+            notify_program: None,
+            token_program: Some(spl_token::id()),
+            system_program: solana_program::system_program::id(),
+            memo_program: None,
+            receipt_mint: None,
+            receipt_token_account: None,
+            receipt_metadata: None,
+            associated_token_program: None,
+            metadata_program: None,
+            rent: None,
+        };
+        let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+        account_metas.extend([
+            AccountMeta::new(vault, false),
+            AccountMeta::new(output_mint, false),
+            AccountMeta::new_readonly(mint_authority, false),
+        ]);
+
+        Instruction {
+            program_id: scheduled_transfer::id(),
+            accounts: account_metas,
+            data: anchor_lang::InstructionData::data(
+                &scheduled_transfer::instruction::ExecuteScheduledTransfer {},
+            ),
+        }
+    };
+
+    // The mock's output misses the stricter floor
+    let result = send(
+        &mut context,
+        swap_execute(transfer_pda(&sender.pubkey(), &greedy_nonce)),
+        &[&recipient],
+    )
+    .await;
+    assert_transfer_error(result, TransferError::SlippageExceeded);
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &swapped_nonce);
+    send(&mut context, swap_execute(transfer_account), &[&recipient]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &recipient_output).await, 200_000);
+    assert_eq!(token_balance(&mut context, &vault).await, 100_000);
+    assert_eq!(
+        token_balance(&mut context, &escrow_ata(&transfer_account, &input_mint)).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_last_modified_at_tracks_mutations() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let now = current_timestamp(&mut context).await;
+    let nonce = [56u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient,
+        default_schedule_args(1_000_000, now + 3600, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    let info = transfer_info(&mut context, &transfer_account).await;
+    assert_eq!(info.last_modified_at, info.created_at);
+    let created_at = info.created_at;
+
+    warp_to_timestamp(&mut context, created_at + 600).await;
+    let reduce = reduce_transfer_ix(&transfer_account, &sender.pubkey(), None, 400_000);
+    send(&mut context, reduce, &[&sender]).await.unwrap();
+
+    let info = transfer_info(&mut context, &transfer_account).await;
+    assert_eq!(info.created_at, created_at);
+    assert!(info.last_modified_at >= created_at + 600);
+}
+
+#[tokio::test]
+async fn test_recurring_escalation() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [57u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            occurrences: 3,
+            interval_seconds: 1000,
+            escalation_bps: 300,
+            ..default_schedule_args(1_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // 1_000_000 + 1_030_000 + 1_060_900 is escrowed up front
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.remaining_escrow().unwrap(), 3_090_900);
+    assert!(verify_escrow_integrity(&mut context, &transfer_account).await.balanced);
+
+    let mut payments = vec![];
+    for occurrence in 0..2 {
+        warp_to_timestamp(&mut context, execute_after + occurrence * 1000).await;
+        let balance_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+        let execute = execute_sol_ix(
+            &transfer_account,
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &recipient.pubkey(),
+        );
+        send(&mut context, execute, &[&recipient]).await.unwrap();
+        let balance_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+        payments.push(balance_after - balance_before);
+    }
+    assert_eq!(payments, vec![1_000_000, 1_030_000]);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.amount, 1_060_900);
+    assert_eq!(transfer.remaining_escrow().unwrap(), 1_060_900);
+}
+
+#[tokio::test]
+async fn test_assert_nonce_available() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let nonce = [58u8; 32];
+    assert_eq!(
+        scheduled_transfer::transfer_address(&sender.pubkey(), &nonce),
+        transfer_pda(&sender.pubkey(), &nonce)
+    );
+    send(&mut context, assert_nonce_available_ix(&sender.pubkey(), nonce), &[])
+        .await
+        .unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(1_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let result = send(&mut context, assert_nonce_available_ix(&sender.pubkey(), nonce), &[]).await;
+    assert_transfer_error(result, TransferError::NonceAlreadyUsed);
+
+    // Nonces are per sender
+    let other_sender = Keypair::new();
+    send(&mut context, assert_nonce_available_ix(&other_sender.pubkey(), nonce), &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_multibyte_memo_limits() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 3600;
+    let schedule_with_memo = |memo: String, nonce: u8| {
+        schedule_sol_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                memo,
+                ..default_schedule_args(1_000_000, execute_after, [nonce; 32])
+            },
+        )
+    };
+
+    // The byte cap counts UTF-8 bytes: 50 emoji are exactly 200 bytes
+    let schedule = schedule_with_memo("\u{1F680}".repeat(50), 59);
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let schedule = schedule_with_memo("\u{1F680}".repeat(51), 60);
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MemoTooLong);
+
+    let update = ConfigUpdate {
+        max_memo_chars: Some(40),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    // 40 CJK characters (120 bytes) fit the character limit, 41 do not
+    let schedule = schedule_with_memo("\u{6C47}".repeat(40), 61);
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let schedule = schedule_with_memo("\u{6C47}".repeat(41), 62);
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MemoTooManyChars);
+
+    // Leaving the config out does not skip the character limit
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            memo: "\u{6C47}".repeat(41),
+            ..default_schedule_args(1_000_000, execute_after, [62u8; 32])
+        },
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+
+    // Within the character limit but over the byte cap is still a byte error
+    let update = ConfigUpdate {
+        max_memo_chars: Some(60),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+    let schedule = schedule_with_memo("\u{1F680}".repeat(51), 63);
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MemoTooLong);
+}
+
+#[tokio::test]
+async fn test_treasury_sponsors_recipient_token_account() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        sponsor_recipient_ata: Some(true),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [64u8; 32];
+    let schedule = schedule_spl_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(400_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let recipient_ata = get_associated_token_address(&recipient.pubkey(), &mint);
+
+    // An empty treasury cannot sponsor the account
+    let execute =
+        build_execute_sponsored_ix(&transfer_account, &sender.pubkey(), &recipient.pubkey(), &mint);
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::TreasuryInsufficient);
+
+    fund_account(&mut context, &config_pda(), 10_000_000).await;
+    let treasury_before = context.banks_client.get_balance(config_pda()).await.unwrap();
+    let recipient_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+
+    let execute =
+        build_execute_sponsored_ix(&transfer_account, &sender.pubkey(), &recipient.pubkey(), &mint);
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let treasury_after = context.banks_client.get_balance(config_pda()).await.unwrap();
+    let recipient_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(treasury_before - treasury_after, rent.minimum_balance(TokenAccount::LEN));
+    assert_eq!(recipient_after, recipient_before, "Executor is reimbursed the rent");
+    assert_eq!(token_balance(&mut context, &recipient_ata).await, 400_000);
+}
+
+#[tokio::test]
+async fn test_reserves_track_live_escrow() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let sol = solana_program::system_program::id();
+    let payer = context.payer.pubkey();
+    send(&mut context, initialize_mint_stats_ix(&payer, &sol), &[]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let recurring_nonce = [65u8; 32];
+    let cancelled_nonce = [66u8; 32];
+    let pending_nonce = [67u8; 32];
+    let schedules = [
+        (recurring_nonce, 1_000_000, 3),
+        (cancelled_nonce, 2_000_000, 1),
+        (pending_nonce, 4_000_000, 1),
+    ];
+    for (nonce, amount, occurrences) in schedules {
+        let schedule = schedule_sol_ix_with_mint_stats(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                occurrences,
+                interval_seconds: 1000,
+                ..default_schedule_args(amount, execute_after, nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    let expected = vec![MintReserve { mint: sol, total_outstanding: 9_000_000 }];
+    assert_eq!(reserves(&mut context, &[sol]).await, expected);
+
+    // A tracked transfer cannot shrink its escrow without updating the reserves
+    let cancelled = transfer_pda(&sender.pubkey(), &cancelled_nonce);
+    let result = send(&mut context, cancel_sol_ix(&cancelled, &sender.pubkey()), &[&sender]).await;
+    assert_transfer_error(result, TransferError::MintStatsRequired);
+
+    let cancel = cancel_sol_ix_with_mint_stats(&cancelled, &sender.pubkey());
+    send(&mut context, cancel, &[&sender]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let recurring = transfer_pda(&sender.pubkey(), &recurring_nonce);
+    let execute = execute_sol_ix_with_mint_stats(
+        &recurring,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    let mut live_escrow = 0;
+    for nonce in [recurring_nonce, cancelled_nonce, pending_nonce] {
+        let transfer = fetch_transfer(&mut context, &transfer_pda(&sender.pubkey(), &nonce)).await;
+        if !transfer.executed && !transfer.cancelled {
+            live_escrow += transfer.remaining_escrow().unwrap();
+        }
+    }
+    assert_eq!(live_escrow, 6_000_000);
+    let expected = vec![MintReserve { mint: sol, total_outstanding: live_escrow }];
+    assert_eq!(reserves(&mut context, &[sol]).await, expected);
+}
+
+#[tokio::test]
+async fn test_large_transfer_extra_delay() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        large_transfer_threshold: Some(10_000_000),
+        large_transfer_extra_delay: Some(3600),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let now = current_timestamp(&mut context).await;
+    let schedule = |amount: u64, execute_after: i64, nonce: u8| {
+        schedule_sol_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(amount, execute_after, [nonce; 32]),
+        )
+    };
+
+    // Just below the threshold only the usual rules apply
+    let logs = send_with_logs(&mut context, schedule(9_999_999, now + 60, 68), &[&sender]).await;
+    assert!(find_event::<LargeTransferScheduled>(&logs).is_none());
+
+    // At the threshold the extra delay is mandatory
+    let result = send(&mut context, schedule(10_000_000, now + 60, 69), &[&sender]).await;
+    assert_transfer_error(result, TransferError::LargeTransferDelayRequired);
+
+    // Leaving the config out does not skip the extra delay
+    let schedule_without_config = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(10_000_000, now + 60, [69u8; 32]),
+    );
+    let result = send(&mut context, schedule_without_config, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+
+    let execute_after = now + 7200;
+    let logs =
+        send_with_logs(&mut context, schedule(10_000_000, execute_after, 70), &[&sender]).await;
+    let event =
+        find_event::<LargeTransferScheduled>(&logs).expect("LargeTransferScheduled not emitted");
+    assert_eq!(event.transfer_id, transfer_pda(&sender.pubkey(), &[70u8; 32]));
+    assert_eq!(event.amount, 10_000_000);
+    assert_eq!(event.execute_after, execute_after);
+}
+
+#[tokio::test]
+async fn test_recovery_key_cancel() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let recovery = Keypair::new();
+    let stranger = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recovery.pubkey(), 10_000_000).await;
+    fund_account(&mut context, &stranger.pubkey(), 10_000_000).await;
+
+    let now = current_timestamp(&mut context).await;
+    let recovery_available_after = now + 7 * 24 * 60 * 60;
+    let nonce = [71u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            recovery_key: Some(recovery.pubkey()),
+            recovery_available_after,
+            ..default_schedule_args(100_000_000, now + 3600, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // Before the unlock time the recovery key is refused
+    let cancel = cancel_sol_ix(&transfer_account, &recovery.pubkey());
+    let result = send(&mut context, cancel, &[&recovery]).await;
+    assert_transfer_error(result, TransferError::UnauthorizedRecovery);
+
+    warp_to_timestamp(&mut context, recovery_available_after).await;
+
+    // Other keys never qualify
+    let cancel = cancel_sol_ix(&transfer_account, &stranger.pubkey());
+    let result = send(&mut context, cancel, &[&stranger]).await;
+    assert_transfer_error(result, TransferError::UnauthorizedCancellation);
+
+    let balance_before = context.banks_client.get_balance(recovery.pubkey()).await.unwrap();
+    let cancel = cancel_sol_ix(&transfer_account, &recovery.pubkey());
+    send(&mut context, cancel, &[&recovery]).await.unwrap();
+    let balance_after = context.banks_client.get_balance(recovery.pubkey()).await.unwrap();
+
+    assert_eq!(balance_after - balance_before, 100_000_000);
+    assert!(fetch_transfer(&mut context, &transfer_account).await.cancelled);
+}
+
+#[tokio::test]
+async fn test_get_transfers_batch() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonces = [[72u8; 32], [73u8; 32], [74u8; 32]];
+    for (index, nonce) in nonces.iter().enumerate() {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                memo: format!("invoice {}", index),
+                ..default_schedule_args(1_000_000 * (index as u64 + 1), execute_after, *nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    let transfer_accounts: Vec<Pubkey> =
+        nonces.iter().map(|nonce| transfer_pda(&sender.pubkey(), nonce)).collect();
+
+    // Leave the three in different states
+    send(&mut context, cancel_sol_ix(&transfer_accounts[1], &sender.pubkey()), &[&sender])
+        .await
+        .unwrap();
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = execute_sol_ix(
+        &transfer_accounts[2],
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    let batch = transfers_batch(&mut context, &transfer_accounts).await;
+    assert_eq!(batch.len(), 3);
+    for (info, transfer_account) in batch.iter().zip(&transfer_accounts) {
+        assert_eq!(info, &transfer_info(&mut context, transfer_account).await);
+    }
+    assert!(batch[0].is_executable_now);
+    assert!(batch[1].cancelled);
+    assert!(batch[2].executed);
+    assert_eq!(batch[2].memo, "invoice 2");
+}
+
+#[tokio::test]
+async fn test_delegated_schedule_budget() {
+    let mut context = start_program_test().await;
+    let owner = Keypair::new();
+    let delegate = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &owner.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &delegate.pubkey(), 1_000_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let owner_token_account = create_token_account(&mut context, &mint, &owner.pubkey()).await;
+    mint_tokens(&mut context, &mint, &owner_token_account, 1_000_000).await;
+
+    let create = create_delegation_ix(
+        &owner.pubkey(),
+        &delegate.pubkey(),
+        &owner_token_account,
+        500_000,
+    );
+    send(&mut context, create, &[&owner]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule_for = |amount: u64, nonce: u8| {
+        schedule_transfer_for_ix(
+            &owner.pubkey(),
+            &delegate.pubkey(),
+            &recipient.pubkey(),
+            &mint,
+            &owner_token_account,
+            scheduled_transfer::instruction::ScheduleTransferFor {
+                amount,
+                execute_after,
+                nonce: [nonce; 32],
+                memo: String::new(),
+                campaign_id: 0,
+            },
+        )
+    };
+
+    // Within budget: the owner's tokens are escrowed under the owner's transfer
+    send(&mut context, schedule_for(300_000, 75), &[&delegate]).await.unwrap();
+    let transfer_account = transfer_pda(&owner.pubkey(), &[75u8; 32]);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.sender, owner.pubkey());
+    assert_eq!(transfer.authority, owner.pubkey());
+    assert_eq!(token_balance(&mut context, &owner_token_account).await, 700_000);
+    assert_eq!(
+        token_balance(&mut context, &escrow_ata(&transfer_account, &mint)).await,
+        300_000
+    );
+    let delegation = fetch_delegation(&mut context, &owner.pubkey(), &delegate.pubkey()).await;
+    assert_eq!(delegation.remaining_budget, 200_000);
+
+    // Over budget is rejected even though the owner holds enough tokens
+    let result = send(&mut context, schedule_for(200_001, 76), &[&delegate]).await;
+    assert_transfer_error(result, TransferError::DelegationBudgetExceeded);
+
+    send(&mut context, schedule_for(200_000, 77), &[&delegate]).await.unwrap();
+    let delegation = fetch_delegation(&mut context, &owner.pubkey(), &delegate.pubkey()).await;
+    assert_eq!(delegation.remaining_budget, 0);
+    assert_eq!(token_balance(&mut context, &owner_token_account).await, 500_000);
+}
+
+#[tokio::test]
+async fn test_schedule_confirm_execute() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let now = current_timestamp(&mut context).await;
+    let nonce = [78u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            require_confirmation: true,
+            ..default_schedule_args(100_000_000, now + 3600, nonce)
+        },
+    );
+    let balance_before = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // Only the account rent left the sender; the amount is merely reserved
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.pending_confirmation);
+    let balance_after = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    assert_eq!(balance_before - balance_after, transfer.rent_reserve);
+
+    let confirm = confirm_sol_ix(&transfer_account, &sender.pubkey());
+    let result = send(&mut context, confirm, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfirmationTooEarly);
+
+    // Due but unconfirmed transfers cannot execute
+    warp_to_timestamp(&mut context, now + 3600).await;
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::NotConfirmed);
+
+    let confirm = confirm_sol_ix(&transfer_account, &sender.pubkey());
+    send(&mut context, confirm, &[&sender]).await.unwrap();
+    assert!(!fetch_transfer(&mut context, &transfer_account).await.pending_confirmation);
+    assert!(verify_escrow_integrity(&mut context, &transfer_account).await.balanced);
+
+    let recipient_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    let recipient_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(recipient_after - recipient_before, 100_000_000);
+}
+
+#[tokio::test]
+async fn test_unconfirmed_transfer_reserved_once_confirmed() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let sol = solana_program::system_program::id();
+    let payer = context.payer.pubkey();
+    send(&mut context, initialize_mint_stats_ix(&payer, &sol), &[]).await.unwrap();
+
+    let now = current_timestamp(&mut context).await;
+    let nonce = [174u8; 32];
+    let schedule = schedule_sol_ix_with_mint_stats(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            require_confirmation: true,
+            ..default_schedule_args(100_000_000, now + 3600, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // Nothing is escrowed yet, so nothing is reserved and nothing is due
+    let expected = vec![MintReserve { mint: sol, total_outstanding: 0 }];
+    assert_eq!(reserves(&mut context, &[sol]).await, expected);
+    warp_to_timestamp(&mut context, now + 3600).await;
+    assert_eq!(due_count(&mut context, &[transfer_account]).await, 0);
+
+    let mint_stats = Some(mint_stats_pda(&sol));
+    let confirm = build_confirm_sol_ix(&transfer_account, &sender.pubkey(), mint_stats);
+    send(&mut context, confirm, &[&sender]).await.unwrap();
+    assert!(fetch_transfer(&mut context, &transfer_account).await.in_mint_stats);
+    let expected = vec![MintReserve { mint: sol, total_outstanding: 100_000_000 }];
+    assert_eq!(reserves(&mut context, &[sol]).await, expected);
+    assert_eq!(due_count(&mut context, &[transfer_account]).await, 1);
+}
+
+#[tokio::test]
+async fn test_keeper_executes_within_authorized_window() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let keeper = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &keeper.pubkey(), 10_000_000).await;
+
+    let nonce = [79u8; 32];
+    let now = current_timestamp(&mut context).await;
+    let execute_after = now + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let authorize = authorize_execution_ix(&transfer_account, &recipient.pubkey(), now + 3_600);
+    send(&mut context, authorize, &[&recipient]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let execute =
+        execute_sol_ix(&transfer_account, &sender.pubkey(), &keeper.pubkey(), &recipient.pubkey());
+    let result = send(&mut context, execute, &[&keeper]).await;
+    assert!(result.is_ok(), "Authorized keeper should execute: {:?}", result);
+
+    let recipient_balance = context
+        .banks_client
+        .get_balance(recipient.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(recipient_balance, 100_000_000);
+}
+
+#[tokio::test]
+async fn test_keeper_rejected_after_authorization_expires() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let keeper = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &keeper.pubkey(), 10_000_000).await;
+
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let nonce = [80u8; 32];
+    let now = current_timestamp(&mut context).await;
+    let execute_after = now + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let authorize = authorize_execution_ix(&transfer_account, &recipient.pubkey(), now + 120);
+    send(&mut context, authorize, &[&recipient]).await.unwrap();
+
+    warp_to_timestamp(&mut context, now + 600).await;
+
+    let execute =
+        execute_sol_ix(&transfer_account, &sender.pubkey(), &keeper.pubkey(), &recipient.pubkey());
+    let result = send(&mut context, execute, &[&keeper]).await;
+    assert_transfer_error(result, TransferError::AuthorizationExpired);
+
+    // The recipient can still execute it themselves
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert!(result.is_ok(), "Recipient should still execute: {:?}", result);
+}
+
+#[tokio::test]
+async fn test_campaign_stats_count_per_campaign() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let payer = context.payer.pubkey();
+    for campaign_id in [7, 8] {
+        send(&mut context, initialize_campaign_stats_ix(&payer, campaign_id), &[])
+            .await
+            .unwrap();
+    }
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let entries = [(81u8, 7, 10_000_000), (82u8, 7, 20_000_000), (83u8, 8, 5_000_000)];
+    for (nonce, campaign_id, amount) in entries {
+        let schedule = schedule_sol_ix_with_campaign(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                campaign_id,
+                ..default_schedule_args(amount, execute_after, [nonce; 32])
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &[81u8; 32]);
+    let execute = execute_sol_ix_with_campaign(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+        7,
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    assert_eq!(fetch_transfer(&mut context, &transfer_account).await.campaign_id, 7);
+
+    let first = campaign_stats(&mut context, 7).await;
+    assert_eq!(first.scheduled_count, 2);
+    assert_eq!(first.scheduled_volume, 30_000_000);
+    assert_eq!(first.executed_count, 1);
+    assert_eq!(first.executed_volume, 10_000_000);
+
+    let second = campaign_stats(&mut context, 8).await;
+    assert_eq!(second.scheduled_count, 1);
+    assert_eq!(second.scheduled_volume, 5_000_000);
+    assert_eq!(second.executed_count, 0);
+    assert_eq!(second.executed_volume, 0);
+
+    // Another campaign's counters cannot be credited
+    let mismatched = build_schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        None,
+        None,
+        None,
+        Some(campaign_stats_pda(8)),
+        ScheduleTransferParams {
+            campaign_id: 7,
+            ..default_schedule_args(1_000_000, execute_after + 60, [84u8; 32])
+        },
+    );
+    let result = send(&mut context, mismatched, &[&sender]).await;
+    assert_transfer_error(result, TransferError::CampaignMismatch);
+}
+
+#[tokio::test]
+async fn test_execute_rejects_wrong_mint_token_accounts() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let other_mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_token_account =
+        create_token_account(&mut context, &mint, &recipient.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let nonce = [85u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(400_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    // A PDA-owned account of another mint cannot stand in for the escrow
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let wrong_escrow = create_token_account(&mut context, &other_mint, &transfer_account).await;
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &wrong_escrow,
+        &recipient_token_account,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::InvalidTokenMint);
+
+    // Nor can the recipient be paid into an account of another mint
+    let wrong_recipient_account =
+        create_token_account(&mut context, &other_mint, &recipient.pubkey()).await;
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow_ata(&transfer_account, &mint),
+        &wrong_recipient_account,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::InvalidTokenMint);
+
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow_ata(&transfer_account, &mint),
+        &recipient_token_account,
+        None,
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &recipient_token_account).await, 400_000);
+}
+
+#[tokio::test]
+async fn test_created_slot_orders_transfers_within_a_second() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let execute_after = clock.unix_timestamp + 60;
+    let nonces = [[86u8; 32], [87u8; 32]];
+    for nonce in nonces {
+        // Same second, next slot
+        clock.slot += 1;
+        context.set_sysvar(&clock);
+
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(10_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+
+    let first = transfer_info(&mut context, &transfer_pda(&sender.pubkey(), &nonces[0])).await;
+    let second = transfer_info(&mut context, &transfer_pda(&sender.pubkey(), &nonces[1])).await;
+    assert_eq!(first.created_at, second.created_at);
+    assert!(
+        first.created_slot < second.created_slot,
+        "created_slot should be monotonic: {} then {}",
+        first.created_slot,
+        second.created_slot
+    );
+}
+
+#[tokio::test]
+async fn test_monthly_recurrence_clamps_to_month_end() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    // 2030-01-31, 2030-02-28, 2030-03-31 and 2030-04-30, each at 09:00 UTC
+    let due_dates = [1_896_080_400, 1_898_499_600, 1_901_178_000, 1_903_770_000];
+    let nonce = [88u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            occurrences: 4,
+            monthly: true,
+            ..default_schedule_args(10_000_000, due_dates[0], nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    for window in due_dates.windows(2) {
+        warp_to_timestamp(&mut context, window[0]).await;
+        let execute = execute_sol_ix(
+            &transfer_account,
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &recipient.pubkey(),
+        );
+        send(&mut context, execute, &[&recipient]).await.unwrap();
+
+        // Counted from the anchor, so February's clamp does not carry into March
+        let transfer = fetch_transfer(&mut context, &transfer_account).await;
+        assert_eq!(transfer.execute_after, window[1]);
+    }
+
+    // A monthly recurrence cannot also set a fixed interval
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            occurrences: 2,
+            interval_seconds: 3_600,
+            monthly: true,
+            ..default_schedule_args(10_000_000, due_dates[3] + 60, [89u8; 32])
+        },
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidInterval);
+}
+
+#[tokio::test]
+async fn test_consolidate_transfers_merges_escrow() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let other_recipient = Pubkey::new_unique();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let target_nonce = [90u8; 32];
+    let source_nonce = [91u8; 32];
+    let mismatched_nonce = [92u8; 32];
+    for (nonce, to, amount) in [
+        (target_nonce, recipient.pubkey(), 30_000_000),
+        (source_nonce, recipient.pubkey(), 20_000_000),
+        (mismatched_nonce, other_recipient, 5_000_000),
+    ] {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &to,
+            default_schedule_args(amount, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+
+    let target = transfer_pda(&sender.pubkey(), &target_nonce);
+    let source = transfer_pda(&sender.pubkey(), &source_nonce);
+    let mismatched = transfer_pda(&sender.pubkey(), &mismatched_nonce);
+
+    // A transfer to another recipient cannot be folded in
+    let consolidate = consolidate_sol_ix(&target, &sender.pubkey(), &[mismatched]);
+    let result = send(&mut context, consolidate, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConsolidationMismatch);
+
+    let source_lamports_before = context.banks_client.get_balance(source).await.unwrap();
+    let consolidate = consolidate_sol_ix(&target, &sender.pubkey(), &[source]);
+    send(&mut context, consolidate, &[&sender]).await.unwrap();
+
+    let merged = fetch_transfer(&mut context, &target).await;
+    assert_eq!(merged.amount, 50_000_000);
+    let cancelled = fetch_transfer(&mut context, &source).await;
+    assert!(cancelled.cancelled);
+    let source_lamports_after = context.banks_client.get_balance(source).await.unwrap();
+    assert_eq!(source_lamports_before - source_lamports_after, 20_000_000);
+    assert!(verify_escrow_integrity(&mut context, &target).await.balanced);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let recipient_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let execute =
+        execute_sol_ix(&target, &sender.pubkey(), &recipient.pubkey(), &recipient.pubkey());
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    let recipient_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(recipient_after - recipient_before, 50_000_000);
+
+    // The merged transfer can no longer execute on its own
+    let execute =
+        execute_sol_ix(&source, &sender.pubkey(), &recipient.pubkey(), &recipient.pubkey());
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::TransferCancelled);
+}
+
+#[tokio::test]
+async fn test_config_execution_modes() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let keeper = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    fund_account(&mut context, &keeper.pubkey(), 10_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    assert_eq!(
+        fetch_config(&mut context).await.unwrap().execution_mode,
+        ExecutionMode::RecipientOnly
+    );
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let restricted_nonce = [93u8; 32];
+    let open_nonce = [94u8; 32];
+    let whitelisted_nonce = [95u8; 32];
+    for (nonce, allowed_executors) in [
+        (restricted_nonce, vec![]),
+        (open_nonce, vec![]),
+        (whitelisted_nonce, vec![keeper.pubkey()]),
+    ] {
+        let schedule = schedule_sol_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                allowed_executors,
+                ..default_schedule_args(10_000_000, execute_after, nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    // Recipient-only: an arbitrary keeper is turned away
+    let transfer_account = transfer_pda(&sender.pubkey(), &restricted_nonce);
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &keeper.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&keeper]).await;
+    assert_transfer_error(result, TransferError::ExecutorNotAllowed);
+
+    // Permissionless: the same keeper may execute
+    let update = ConfigUpdate {
+        execution_mode: Some(ExecutionMode::Permissionless),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &keeper.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&keeper]).await;
+    assert!(result.is_ok(), "Permissionless execution should succeed: {:?}", result);
+
+    // A transfer's own whitelist still binds when execution is permissionless
+    let whitelisted = transfer_pda(&sender.pubkey(), &whitelisted_nonce);
+    let execute = execute_sol_ix_with_config(
+        &whitelisted,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::ExecutorNotAllowed);
+
+    // Allowlist-only: not even the recipient may execute without a whitelist
+    let update = ConfigUpdate {
+        execution_mode: Some(ExecutionMode::AllowlistOnly),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &open_nonce);
+    for executor in [&keeper, &recipient] {
+        let execute = execute_sol_ix_with_config(
+            &transfer_account,
+            &sender.pubkey(),
+            &executor.pubkey(),
+            &recipient.pubkey(),
+        );
+        let result = send(&mut context, execute, &[executor]).await;
+        assert_transfer_error(result, TransferError::ExecutorNotAllowed);
+    }
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &whitelisted_nonce);
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &keeper.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&keeper]).await;
+    assert!(result.is_ok(), "Whitelisted keeper should execute: {:?}", result);
+}
+
+#[tokio::test]
+async fn test_schedule_more_than_balance_rejected() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 50_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [96u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InsufficientFunds);
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000).await;
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(5_000, execute_after, [97u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InsufficientFunds);
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 1_000);
+}
+
+#[tokio::test]
+async fn test_new_transfers_carry_program_version() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let nonce = [98u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(10_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.program_version, scheduled_transfer::PROGRAM_VERSION);
+    let info = transfer_info(&mut context, &transfer_account).await;
+    assert_eq!(info.program_version, scheduled_transfer::PROGRAM_VERSION);
+}
+
+#[tokio::test]
+async fn test_ping_emits_pong() {
+    let mut context = start_program_test().await;
+    let before = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(
+            &scheduled_transfer::accounts::Ping {},
+            None,
+        ),
+        data: anchor_lang::InstructionData::data(&scheduled_transfer::instruction::Ping {}),
+    };
+    let logs = send_with_logs(&mut context, instruction, &[]).await;
+
+    let after = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let pong = find_event::<Pong>(&logs).expect("Pong not emitted");
+    assert!(before.slot <= pong.slot && pong.slot <= after.slot);
+    assert!(before.unix_timestamp <= pong.timestamp && pong.timestamp <= after.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_mark_unexecutable_recovers_stuck_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_token_account =
+        create_token_account(&mut context, &mint, &recipient.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let nonce = [99u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        ScheduleTransferParams {
+            irrevocable: true,
+            ..default_schedule_args(400_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    // The payee's account is frozen, so every execution attempt fails
+    let freeze = spl_token::instruction::freeze_account(
+        &spl_token::id(),
+        &recipient_token_account,
+        &mint,
+        &context.payer.pubkey(),
+        &[],
+    )
+    .unwrap();
+    send(&mut context, freeze, &[]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow,
+        &recipient_token_account,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::RecipientAccountFrozen);
+
+    // Too soon to give up on it
+    let mark = mark_unexecutable_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &sender_token_account,
+        &escrow,
+    );
+    let result = send(&mut context, mark, &[&sender]).await;
+    assert_transfer_error(result, TransferError::NotYetUnexecutable);
+
+    warp_to_timestamp(&mut context, execute_after + UNEXECUTABLE_AFTER_SECONDS).await;
+    let mark = mark_unexecutable_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &sender_token_account,
+        &escrow,
+    );
+    send(&mut context, mark, &[&sender]).await.unwrap();
+
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 1_000_000);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.unexecutable);
+    assert!(transfer.cancelled);
+}
+
+#[tokio::test]
+async fn test_global_pending_limit() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        max_global_pending: Some(2),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    for nonce in [[100u8; 32], [101u8; 32]] {
+        let schedule = schedule_sol_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(100_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    assert_eq!(fetch_config(&mut context).await.unwrap().global_pending, 2);
+
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [102u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::GlobalPendingLimitReached);
+
+    // Settling a counted transfer requires the config and frees its slot
+    let first = transfer_pda(&sender.pubkey(), &[100u8; 32]);
+    let result = send(&mut context, cancel_sol_ix(&first, &sender.pubkey()), &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+
+    let cancel =
+        build_cancel_sol_ix(&first, &sender.pubkey(), None, None, None, Some(config_pda()));
+    send(&mut context, cancel, &[&sender]).await.unwrap();
+    assert_eq!(fetch_config(&mut context).await.unwrap().global_pending, 1);
+    assert!(!fetch_transfer(&mut context, &first).await.in_global_pending);
+
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [102u8; 32]),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    assert_eq!(fetch_config(&mut context).await.unwrap().global_pending, 2);
+
+    // Closing and re-creating the config would reset the count
+    let result = send(&mut context, close_config_ix(&admin.pubkey()), &[&admin]).await;
+    assert_transfer_error(result, TransferError::ConfigInUse);
+}
+
+#[tokio::test]
+async fn test_escrow_address_matches_created_escrow() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [103u8; 32];
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(250_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_address(&mut context, &transfer_account).await;
+    assert_eq!(escrow, escrow_ata(&transfer_account, &mint));
+
+    let account = context.banks_client.get_account(escrow).await.unwrap().unwrap();
+    let escrow_account = spl_token::state::Account::unpack(&account.data).unwrap();
+    assert_eq!(escrow_account.owner, transfer_account);
+    assert_eq!(escrow_account.mint, mint);
+    assert_eq!(escrow_account.amount, 250_000);
+
+    // SOL escrow is held by the transfer account itself
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [104u8; 32]),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let sol_transfer = transfer_pda(&sender.pubkey(), &[104u8; 32]);
+    assert_eq!(escrow_address(&mut context, &sol_transfer).await, sol_transfer);
+}
+
+#[tokio::test]
+async fn test_refund_references_executed_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 1_000_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(300_000_000, execute_after, [105u8; 32]),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    warp_to_timestamp(&mut context, execute_after).await;
+    let original = transfer_pda(&sender.pubkey(), &[105u8; 32]);
+    let execute =
+        execute_sol_ix(&original, &sender.pubkey(), &recipient.pubkey(), &recipient.pubkey());
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    let refund_after = current_timestamp(&mut context).await + 60;
+    let refund_args = |nonce| ScheduleTransferParams {
+        refund_of: Some(original),
+        ..default_schedule_args(100_000_000, refund_after, nonce)
+    };
+
+    // The original must run from the refund's recipient to its sender
+    let schedule = schedule_sol_refund_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &original,
+        refund_args([106u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidRefundReference);
+
+    let schedule = schedule_sol_refund_ix(
+        &recipient.pubkey(),
+        &sender.pubkey(),
+        &original,
+        refund_args([107u8; 32]),
+    );
+    send(&mut context, schedule, &[&recipient]).await.unwrap();
+
+    let refund = transfer_pda(&recipient.pubkey(), &[107u8; 32]);
+    assert_eq!(fetch_transfer(&mut context, &refund).await.refund_of, Some(original));
+
+    // Without the referenced account the reference is recorded unchecked
+    let schedule = schedule_sol_ix(
+        &recipient.pubkey(),
+        &sender.pubkey(),
+        refund_args([108u8; 32]),
+    );
+    send(&mut context, schedule, &[&recipient]).await.unwrap();
+    let refund = transfer_pda(&recipient.pubkey(), &[108u8; 32]);
+    assert_eq!(fetch_transfer(&mut context, &refund).await.refund_of, Some(original));
+}
+
+#[tokio::test]
+async fn test_sweep_all_escrows_spare_balance() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let sweep_args = |amount, nonce| ScheduleTransferParams {
+        sweep_all: true,
+        ..default_schedule_args(amount, execute_after, nonce)
+    };
+
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        sweep_args(1, [109u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::SweepAmountConflict);
+
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        sweep_args(0, [110u8; 32]),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    // Only the sender's own rent-exempt minimum stays behind
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &[110u8; 32]);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(
+        context.banks_client.get_balance(sender.pubkey()).await.unwrap(),
+        rent.minimum_balance(0)
+    );
+    assert_eq!(
+        transfer.amount,
+        1_000_000_000 - transfer.rent_reserve - rent.minimum_balance(0)
+    );
+    assert_eq!(
+        context.banks_client.get_balance(transfer_account).await.unwrap(),
+        transfer.rent_reserve + transfer.amount
+    );
+}
+
+#[tokio::test]
+async fn test_spl_execute_rejects_foreign_recipient_token_account() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let stranger = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    fund_account(&mut context, &stranger.pubkey(), 10_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_token_account =
+        create_token_account(&mut context, &mint, &recipient.pubkey()).await;
+    let stranger_token_account =
+        create_token_account(&mut context, &mint, &stranger.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [111u8; 32];
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(500_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+
+    // The stored recipient cannot route the payout into someone else's account
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow,
+        &stranger_token_account,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    // Nor can another account claim it as recipient with its own token account
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &stranger.pubkey(),
+        &escrow,
+        &stranger_token_account,
+        None,
+    );
+    let result = send(&mut context, execute, &[&stranger]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow,
+        &recipient_token_account,
+        None,
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &recipient_token_account).await, 500_000);
+    assert_eq!(token_balance(&mut context, &stranger_token_account).await, 0);
+}
+
+#[tokio::test]
+async fn test_close_stale_after_retention() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    for nonce in [[112u8; 32], [113u8; 32]] {
+        let schedule = schedule_sol_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(100_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    let cancelled = transfer_pda(&sender.pubkey(), &[112u8; 32]);
+    let pending = transfer_pda(&sender.pubkey(), &[113u8; 32]);
+    let cancel =
+        build_cancel_sol_ix(&cancelled, &sender.pubkey(), None, None, None, Some(config_pda()));
+    send(&mut context, cancel, &[&sender]).await.unwrap();
+
+    // Closing is disabled until the config sets a retention period
+    let close = close_stale_ix(&cancelled, &sender.pubkey(), None, None);
+    let result = send(&mut context, close, &[]).await;
+    assert_transfer_error(result, TransferError::NotYetCloseable);
+
+    let update = ConfigUpdate {
+        auto_close_after_seconds: Some(3_600),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+    let cancelled_at = fetch_transfer(&mut context, &cancelled).await.cancelled_at;
+
+    warp_to_timestamp(&mut context, cancelled_at + 3_599).await;
+    let close = close_stale_ix(&cancelled, &sender.pubkey(), None, None);
+    let result = send(&mut context, close, &[]).await;
+    assert_transfer_error(result, TransferError::NotYetCloseable);
+
+    warp_to_timestamp(&mut context, cancelled_at + 3_600).await;
+    let close = close_stale_ix(&pending, &sender.pubkey(), None, None);
+    let result = send(&mut context, close, &[]).await;
+    assert_transfer_error(result, TransferError::NotYetCloseable);
+
+    let rent_reserve = fetch_transfer(&mut context, &cancelled).await.rent_reserve;
+    let sender_before = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    let close = close_stale_ix(&cancelled, &sender.pubkey(), None, None);
+    send(&mut context, close, &[]).await.unwrap();
+
+    // All rent but the tombstone's goes back to the sender
+    let tombstone = context.banks_client.get_account(cancelled).await.unwrap().unwrap();
+    assert_eq!(tombstone.owner, scheduled_transfer::id());
+    assert_eq!(tombstone.data, ClosedTransfer::DISCRIMINATOR.to_vec());
+    let sender_after = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    assert_eq!(sender_after - sender_before, rent_reserve - tombstone.lamports);
+
+    // The tombstone keeps the settled transfer's nonce from being reused
+    let assert_available = assert_nonce_available_ix(&sender.pubkey(), [112u8; 32]);
+    let result = send(&mut context, assert_available, &[]).await;
+    assert_transfer_error(result, TransferError::NonceAlreadyUsed);
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [112u8; 32]),
+    );
+    assert!(send(&mut context, schedule, &[&sender]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_close_stale_closes_spl_escrow() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        auto_close_after_seconds: Some(60),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_token_account =
+        create_token_account(&mut context, &mint, &recipient.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [114u8; 32];
+    let schedule = schedule_spl_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(500_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow,
+        &recipient_token_account,
+        None,
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    let executed_at = fetch_transfer(&mut context, &transfer_account).await.executed_at;
+    warp_to_timestamp(&mut context, executed_at + 60).await;
+
+    // SPL transfers must bring their escrow along
+    let close = close_stale_ix(&transfer_account, &sender.pubkey(), None, None);
+    let result = send(&mut context, close, &[]).await;
+    assert_transfer_error(result, TransferError::InvalidEscrowAccount);
+
+    // Tokens donated to the escrow after execution go back to the sender
+    mint_tokens(&mut context, &mint, &escrow, 7).await;
+    let close = close_stale_ix(&transfer_account, &sender.pubkey(), Some(escrow), None);
+    let result = send(&mut context, close, &[]).await;
+    assert_transfer_error(result, TransferError::InvalidTokenAccount);
+
+    let close = close_stale_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        Some(escrow),
+        Some(sender_token_account),
+    );
+    send(&mut context, close, &[]).await.unwrap();
+    let tombstone = context.banks_client.get_account(transfer_account).await.unwrap().unwrap();
+    assert_eq!(tombstone.data, ClosedTransfer::DISCRIMINATOR.to_vec());
+    assert!(context.banks_client.get_account(escrow).await.unwrap().is_none());
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 500_007);
+}
+
+#[tokio::test]
+async fn test_claimable_total_sums_due_transfers_per_mint() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let other = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 2_000_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let now = current_timestamp(&mut context).await;
+    let sol_schedules = [
+        ([115u8; 32], recipient.pubkey(), 100_000_000, now + 60),
+        ([116u8; 32], recipient.pubkey(), 200_000_000, now + 120),
+        ([117u8; 32], recipient.pubkey(), 400_000_000, now + 3_600),
+        ([118u8; 32], other.pubkey(), 300_000_000, now + 60),
+    ];
+    for (nonce, to, amount, execute_after) in sol_schedules {
+        let args = default_schedule_args(amount, execute_after, nonce);
+        let schedule = schedule_sol_ix(&sender.pubkey(), &to, args);
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(250_000, now + 60, [119u8; 32]),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_accounts: Vec<Pubkey> = [115u8, 116, 117, 118, 119]
+        .iter()
+        .map(|byte| transfer_pda(&sender.pubkey(), &[*byte; 32]))
+        .collect();
+    let totals = claimable_total(&mut context, &recipient.pubkey(), &transfer_accounts).await;
+    assert!(totals.is_empty());
+
+    // Only the due transfers paying this recipient count
+    warp_to_timestamp(&mut context, now + 120).await;
+    let totals = claimable_total(&mut context, &recipient.pubkey(), &transfer_accounts).await;
+    assert_eq!(
+        totals,
+        vec![
+            ClaimableTotal {
+                mint: solana_program::system_program::id(),
+                amount: 300_000_000,
+            },
+            ClaimableTotal {
+                mint,
+                amount: 250_000,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_derived_nonce_required_when_configured() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        require_derived_nonce: Some(true),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [120u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::NonceNotDerived);
+
+    // Leaving the config out does not skip the derivation check
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [120u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+
+    let nonce = derived_nonce(&sender.pubkey(), &recipient.pubkey(), 100_000_000, execute_after);
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer = fetch_transfer(&mut context, &transfer_pda(&sender.pubkey(), &nonce)).await;
+    assert_eq!(transfer.amount, 100_000_000);
+}
+
+#[tokio::test]
+async fn test_milestones_released_and_rejected_by_arbiter() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let arbiter = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    fund_account(&mut context, &arbiter.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [121u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(300_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // Milestones must add up to the escrow
+    let attach = attach_milestones_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &arbiter.pubkey(),
+        vec![100_000_000, 100_000_000],
+    );
+    let result = send(&mut context, attach, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidMilestones);
+
+    let attach = attach_milestones_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &arbiter.pubkey(),
+        vec![100_000_000, 200_000_000],
+    );
+    send(&mut context, attach, &[&sender]).await.unwrap();
+
+    // Neither execution nor cancellation bypasses the arbiter
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::MilestoneGated);
+    let cancel = cancel_sol_ix(&transfer_account, &sender.pubkey());
+    let result = send(&mut context, cancel, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MilestoneGated);
+    let mark = mark_unexecutable_sol_ix(&transfer_account, &sender.pubkey());
+    let result = send(&mut context, mark, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MilestoneGated);
+    assert_eq!(due_count(&mut context, &[transfer_account]).await, 0);
+
+    let release = settle_milestone_sol_ix(
+        &transfer_account,
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+        0,
+        true,
+    );
+    let result = send(&mut context, release, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::UnauthorizedArbiter);
+
+    let recipient_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let release = settle_milestone_sol_ix(
+        &transfer_account,
+        &arbiter.pubkey(),
+        &recipient.pubkey(),
+        0,
+        true,
+    );
+    send(&mut context, release, &[&arbiter]).await.unwrap();
+    let recipient_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(recipient_after - recipient_before, 100_000_000);
+
+    let reject = settle_milestone_sol_ix(
+        &transfer_account,
+        &arbiter.pubkey(),
+        &sender.pubkey(),
+        0,
+        false,
+    );
+    let result = send(&mut context, reject, &[&arbiter]).await;
+    assert_transfer_error(result, TransferError::MilestoneAlreadySettled);
+
+    // Rejected milestones go back to the sender, never the recipient
+    let reject = settle_milestone_sol_ix(
+        &transfer_account,
+        &arbiter.pubkey(),
+        &recipient.pubkey(),
+        1,
+        false,
+    );
+    let result = send(&mut context, reject, &[&arbiter]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let sender_before = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    let reject = settle_milestone_sol_ix(
+        &transfer_account,
+        &arbiter.pubkey(),
+        &sender.pubkey(),
+        1,
+        false,
+    );
+    send(&mut context, reject, &[&arbiter]).await.unwrap();
+    let sender_after = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    assert_eq!(sender_after - sender_before, 200_000_000);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.executed);
+    assert!(!transfer.cancelled);
+    assert_eq!(transfer.amount, 0);
+    assert!(transfer.milestones[0].approved);
+    assert!(transfer.milestones[1].rejected);
+}
+
+#[tokio::test]
+async fn test_settling_last_milestone_unlinks_from_registry() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let arbiter = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &arbiter.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_registry_ix(&sender.pubkey()), &[&sender]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [170u8; 32];
+    let schedule = schedule_sol_ix_with_registry(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let attach = attach_milestones_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &arbiter.pubkey(),
+        vec![100_000_000],
+    );
+    send(&mut context, attach, &[&sender]).await.unwrap();
+
+    let release = build_settle_milestone_sol_ix(
+        &transfer_account,
+        &arbiter.pubkey(),
+        &recipient.pubkey(),
+        0,
+        true,
+        Some(registry_pda(&sender.pubkey())),
+    );
+    send(&mut context, release, &[&arbiter]).await.unwrap();
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.executed);
+    assert!(!transfer.in_registry);
+    let registry = fetch_registry(&mut context, &sender.pubkey()).await;
+    assert_eq!(registry.count, 0);
+    assert_eq!(registry.head, None);
+}
+
+#[tokio::test]
+async fn test_rejecting_every_milestone_cancels_transfer() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let arbiter = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &arbiter.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [122u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(150_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let attach = attach_milestones_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &arbiter.pubkey(),
+        vec![150_000_000],
+    );
+    send(&mut context, attach, &[&sender]).await.unwrap();
+
+    let sender_before = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    let reject = settle_milestone_sol_ix(
+        &transfer_account,
+        &arbiter.pubkey(),
+        &sender.pubkey(),
+        0,
+        false,
+    );
+    send(&mut context, reject, &[&arbiter]).await.unwrap();
+    let sender_after = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    assert_eq!(sender_after - sender_before, 150_000_000);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.cancelled);
+    assert!(!transfer.executed);
+    assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_sol_recipient_must_be_system_owned_unless_pda() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    // A wallet (even one that does not exist yet) is a valid SOL recipient
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [123u8; 32]),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    // Another transfer account is owned by this program
+    let program_owned = transfer_pda(&sender.pubkey(), &[123u8; 32]);
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &program_owned,
+        default_schedule_args(100_000_000, execute_after, [124u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &program_owned,
+        ScheduleTransferParams {
+            recipient_is_pda: true,
+            ..default_schedule_args(100_000_000, execute_after, [124u8; 32])
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_failed_condition_sets_retry_backoff() {
+    let rejecting_program = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_program(
+        "mock_condition_reject",
+        rejecting_program,
+        processor!(reject_condition),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        condition_retry_backoff_seconds: Some(300),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [125u8; 32];
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            condition_program: Some(rejecting_program),
+            max_execution_attempts: 3,
+            ..default_schedule_args(3_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let execute = || {
+        build_execute_sol_ix(
+            &transfer_account,
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &recipient.pubkey(),
+            Some(rejecting_program),
+            Some(config_pda()),
+            Some(sender.pubkey()),
+            None,
+        )
+    };
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    send(&mut context, execute(), &[&recipient]).await.unwrap();
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.execution_attempts, 1);
+    assert_eq!(transfer.next_attempt_allowed_after, execute_after + 300);
+
+    // A premature retry is rejected without counting an attempt
+    warp_to_timestamp(&mut context, execute_after + 299).await;
+    let result = send(&mut context, execute(), &[&recipient]).await;
+    assert_transfer_error(result, TransferError::RetryTooSoon);
+    assert_eq!(fetch_transfer(&mut context, &transfer_account).await.execution_attempts, 1);
+
+    // Nor can the retry dodge the backoff by leaving the config out
+    let execute_without_config = build_execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+        Some(rejecting_program),
+        None,
+        Some(sender.pubkey()),
+        None,
+    );
+    let result = send(&mut context, execute_without_config, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+    assert_eq!(fetch_transfer(&mut context, &transfer_account).await.execution_attempts, 1);
+
+    warp_to_timestamp(&mut context, execute_after + 300).await;
+    send(&mut context, execute(), &[&recipient]).await.unwrap();
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.execution_attempts, 2);
+    assert_eq!(transfer.next_attempt_allowed_after, execute_after + 600);
+}
+
+#[tokio::test]
+async fn test_migrate_to_program_moves_escrow() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_token_account =
+        create_token_account(&mut context, &mint, &recipient.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [126u8; 32];
+    let schedule = schedule_spl_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(400_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+
+    // Stand-in for the successor program's escrow: a token account owned by its PDA
+    let new_program = Pubkey::new_unique();
+    let (vault_authority, vault_bump) = Pubkey::find_program_address(&[b"vault"], &new_program);
+    let destination = create_token_account(&mut context, &mint, &vault_authority).await;
+    let owner_seeds = vec![b"vault".to_vec(), vec![vault_bump]];
+
+    let outsider = Keypair::new();
+    fund_account(&mut context, &outsider.pubkey(), 10_000_000).await;
+    let migrate = |signer: &Pubkey, destination: &Pubkey| {
+        migrate_to_program_ix(
+            &transfer_account,
+            signer,
+            &sender.pubkey(),
+            &escrow,
+            destination,
+            owner_seeds.clone(),
+        )
+    };
+    let migrate_as_outsider = migrate(&outsider.pubkey(), &destination);
+    let result = send(&mut context, migrate_as_outsider, &[&outsider]).await;
+    assert_transfer_error(result, TransferError::UnauthorizedAdmin);
+
+    // Migration stays closed until the admin sunsets the program
+    let result = send(&mut context, migrate(&admin.pubkey(), &destination), &[&admin]).await;
+    assert_transfer_error(result, TransferError::ProgramNotSunset);
+
+    let sunset_only = ConfigUpdate {
+        sunset: Some(true),
+        ..ConfigUpdate::default()
+    };
+    let update = update_config_ix(&admin.pubkey(), sunset_only);
+    let result = send(&mut context, update, &[&admin]).await;
+    assert_transfer_error(result, TransferError::SuccessorProgramRequired);
+
+    let update = ConfigUpdate {
+        sunset: Some(true),
+        successor_program: Some(new_program),
+        ..ConfigUpdate::default()
+    };
+    let update = update_config_ix(&admin.pubkey(), update);
+    let logs = send_with_logs(&mut context, update, &[&admin]).await;
+    let event = find_event::<SunsetChanged>(&logs).expect("SunsetChanged not emitted");
+    assert!(event.new_sunset);
+    assert_eq!(event.new_successor_program, Some(new_program));
+
+    // A token account the successor program does not control is refused
+    let wallet_destination = create_token_account(&mut context, &mint, &admin.pubkey()).await;
+    let migrate_to_wallet = migrate(&admin.pubkey(), &wallet_destination);
+    let result = send(&mut context, migrate_to_wallet, &[&admin]).await;
+    assert_transfer_error(result, TransferError::InvalidTokenAccount);
+
+    let migrate_as_admin = migrate(&admin.pubkey(), &destination);
+    let logs = send_with_logs(&mut context, migrate_as_admin, &[&admin]).await;
+    let event = find_event::<TransferMigrated>(&logs).expect("TransferMigrated not emitted");
+    assert_eq!(event.new_program, new_program);
+    assert_eq!(event.destination, destination);
+    assert_eq!(event.amount, 400_000);
+    assert_eq!(token_balance(&mut context, &destination).await, 400_000);
+    assert_eq!(token_balance(&mut context, &escrow).await, 0);
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.migrated);
+    assert!(transfer.cancelled);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow,
+        &recipient_token_account,
+        None,
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::TransferCancelled);
+}
+
+#[tokio::test]
+async fn test_non_positive_execute_after_rejected() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    for execute_after in [0, -1_700_000_000] {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(1_000_000, execute_after, [127u8; 32]),
+        );
+        let result = send(&mut context, schedule, &[&sender]).await;
+        assert_transfer_error(result, TransferError::NonPositiveExecutionTime);
+    }
+}
+
+#[tokio::test]
+async fn test_amount_spec_absolute_and_bps() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = |amount, nonce| {
+        schedule_spl_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &mint,
+            &sender_token_account,
+            ScheduleTransferParams {
+                amount,
+                ..default_schedule_args(0, execute_after, nonce)
+            },
+        )
+    };
+
+    let over_full = schedule(AmountSpec::Bps(10_001), [128u8; 32]);
+    let result = send(&mut context, over_full, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidAmountBps);
+
+    // Each spec applies to what the sender holds when it is scheduled
+    let cases = [
+        (AmountSpec::Absolute(100_000), [129u8; 32], 100_000),
+        (AmountSpec::Bps(5_000), [130u8; 32], 450_000),
+        (AmountSpec::Bps(10_000), [131u8; 32], 450_000),
+    ];
+    for (spec, nonce, expected) in cases {
+        send(&mut context, schedule(spec, nonce), &[&sender]).await.unwrap();
+        let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+        assert_eq!(fetch_transfer(&mut context, &transfer_account).await.amount, expected);
+        let escrow = escrow_ata(&transfer_account, &mint);
+        assert_eq!(token_balance(&mut context, &escrow).await, expected);
+    }
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 0);
+}
+
+#[tokio::test]
+async fn test_compact_events_when_not_verbose() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+
+    // Full events by default
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(1_000_000, execute_after, [132u8; 32]),
+    );
+    let logs = send_with_logs(&mut context, schedule, &[&sender]).await;
+    assert!(find_event::<TransferScheduled>(&logs).is_some());
+    assert!(find_event::<TransferStatusChanged>(&logs).is_none());
+
+    let update = ConfigUpdate {
+        verbose_events: Some(false),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let nonce = [133u8; 32];
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(1_000_000, execute_after, nonce),
+    );
+    let logs = send_with_logs(&mut context, schedule, &[&sender]).await;
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    assert!(find_event::<TransferScheduled>(&logs).is_none());
+    let event = find_event::<TransferStatusChanged>(&logs).expect("compact event not emitted");
+    assert_eq!(event.transfer_id, transfer_account);
+    assert_eq!(event.status, TransferStatus::Scheduled);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+    assert!(find_event::<TransferExecuted>(&logs).is_none());
+    let event = find_event::<TransferStatusChanged>(&logs).expect("compact event not emitted");
+    assert_eq!(event.transfer_id, transfer_account);
+    assert_eq!(event.status, TransferStatus::Executed);
+}
+
+#[tokio::test]
+async fn test_reclaim_after_recipient_ata_closed() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let mint = create_mint(&mut context).await;
+    let sender_token_account = create_token_account(&mut context, &mint, &sender.pubkey()).await;
+    let recipient_ata = create_ata(&mut context, &recipient.pubkey(), &mint).await;
+    mint_tokens(&mut context, &mint, &sender_token_account, 1_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [134u8; 32];
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        default_schedule_args(300_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+    let reclaim = || {
+        reclaim_undeliverable_ix(
+            &transfer_account,
+            &sender.pubkey(),
+            &sender_token_account,
+            &escrow,
+            &recipient_ata,
+        )
+    };
+
+    let irrevocable_nonce = [171u8; 32];
+    let schedule = schedule_spl_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &mint,
+        &sender_token_account,
+        ScheduleTransferParams {
+            irrevocable: true,
+            ..default_schedule_args(200_000, execute_after, irrevocable_nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let irrevocable_transfer = transfer_pda(&sender.pubkey(), &irrevocable_nonce);
+    let reclaim_irrevocable = reclaim_undeliverable_ix(
+        &irrevocable_transfer,
+        &sender.pubkey(),
+        &sender_token_account,
+        &escrow_ata(&irrevocable_transfer, &mint),
+        &recipient_ata,
+    );
+
+    // Not before the grace period after the due time, nor while the payee can
+    // still be paid
+    let result = send(&mut context, reclaim(), &[&sender]).await;
+    assert_transfer_error(result, TransferError::ReclaimGracePending);
+    warp_to_timestamp(&mut context, execute_after + RECLAIM_GRACE_SECONDS - 1).await;
+    let result = send(&mut context, reclaim(), &[&sender]).await;
+    assert_transfer_error(result, TransferError::ReclaimGracePending);
+    warp_to_timestamp(&mut context, execute_after + RECLAIM_GRACE_SECONDS).await;
+    let result = send(&mut context, reclaim(), &[&sender]).await;
+    assert_transfer_error(result, TransferError::UndeliverableRecipient);
+
+    let close = spl_token::instruction::close_account(
+        &spl_token::id(),
+        &recipient_ata,
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+        &[],
+    )
+    .unwrap();
+    send(&mut context, close, &[&recipient]).await.unwrap();
+
+    let execute = build_execute_spl_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &escrow,
+        &recipient_ata,
+        None,
+    );
+    assert!(send(&mut context, execute, &[&recipient]).await.is_err());
+
+    send(&mut context, reclaim(), &[&sender]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &sender_token_account).await, 800_000);
+    assert!(fetch_transfer(&mut context, &transfer_account).await.cancelled);
+
+    // Irrevocable escrow only ever leaves through execution
+    let result = send(&mut context, reclaim_irrevocable, &[&sender]).await;
+    assert_transfer_error(result, TransferError::TransferIrrevocable);
+}
+
+#[tokio::test]
+async fn test_transfer_space_fits_inputs() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let arbiter = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    // Whatever the memo and executors, only the milestone reservation is spare
+    let milestone_room = MAX_MILESTONES * <Milestone as anchor_lang::Space>::INIT_SPACE;
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let shapes = [
+        (String::new(), vec![], [135u8; 32]),
+        ("x".repeat(40), vec![Pubkey::new_unique(), Pubkey::new_unique()], [136u8; 32]),
+    ];
+    for (memo, allowed_executors, nonce) in shapes {
+        let expected = ScheduledTransfer::space(memo.len(), allowed_executors.len());
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            ScheduleTransferParams {
+                memo,
+                allowed_executors,
+                ..default_schedule_args(5_000_000, execute_after, nonce)
+            },
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+        let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+        let (data_len, serialized) = transfer_sizes(&mut context, &transfer_account).await;
+        assert_eq!(data_len, expected);
+        assert_eq!(data_len, 8 + serialized + milestone_room);
+    }
+
+    // Attaching every milestone uses up the reservation exactly
+    let transfer_account = transfer_pda(&sender.pubkey(), &[135u8; 32]);
+    let attach = attach_milestones_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &arbiter.pubkey(),
+        vec![1_000_000; MAX_MILESTONES],
+    );
+    send(&mut context, attach, &[&sender]).await.unwrap();
+    let (data_len, serialized) = transfer_sizes(&mut context, &transfer_account).await;
+    assert_eq!(data_len, 8 + serialized);
+
+    let entry = ScheduleParams {
+        recipient: recipient.pubkey(),
+        amount: 5_000_000,
+        execute_after,
+        nonce: [137u8; 32],
+        memo: "m".repeat(200),
+    };
+    let accounts = scheduled_transfer::accounts::BatchSchedule {
+        sender: sender.pubkey(),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        mint_stats: None,
+        system_program: solana_program::system_program::id(),
+    };
+    let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    account_metas.extend(batch_entry_metas(&sender.pubkey(), &entry));
+    let batch = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: account_metas,
+        data: anchor_lang::InstructionData::data(&scheduled_transfer::instruction::BatchSchedule {
+            params: vec![entry.clone()],
+        }),
+    };
+    send(&mut context, batch, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &entry.nonce);
+    let (data_len, serialized) = transfer_sizes(&mut context, &transfer_account).await;
+    assert_eq!(data_len, compute_space(&entry));
+    assert_eq!(data_len, 8 + serialized + milestone_room);
+}
+
+#[tokio::test]
+async fn test_emergency_freeze_blocks_execution_until_unfrozen() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_registry_ix(&sender.pubkey()), &[&sender]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [138u8; 32];
+    let schedule = schedule_sol_ix_with_registry(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // Scheduled without the registry, but still covered by its freeze
+    let unregistered_nonce = [175u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, unregistered_nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let unregistered_account = transfer_pda(&sender.pubkey(), &unregistered_nonce);
+
+    let frozen_at = current_timestamp(&mut context).await;
+    let freeze = freeze_ix(
+        &sender.pubkey(),
+        anchor_lang::InstructionData::data(&scheduled_transfer::instruction::EmergencyFreeze {}),
+    );
+    send(&mut context, freeze, &[&sender]).await.unwrap();
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = |transfer_account: Pubkey| {
+        execute_ix(scheduled_transfer::accounts::ExecuteScheduledTransfer {
+            registry: Some(registry_pda(&sender.pubkey())),
+            ..execute_sol_accounts(
+                &transfer_account,
+                &sender.pubkey(),
+                &recipient.pubkey(),
+                &recipient.pubkey(),
+            )
+        })
+    };
+    let result = send(&mut context, execute(transfer_account), &[&recipient]).await;
+    assert_transfer_error(result, TransferError::SenderFrozen);
+    let result = send(&mut context, execute(unregistered_account), &[&recipient]).await;
+    assert_transfer_error(result, TransferError::SenderFrozen);
+
+    // Nor can the transfer be handed to another authority while frozen
+    let new_authority = Keypair::new();
+    let hand_over = |registry: Option<Pubkey>| {
+        build_transfer_ownership_ix(
+            &unregistered_account,
+            &sender.pubkey(),
+            &sender.pubkey(),
+            &new_authority.pubkey(),
+            registry,
+        )
+    };
+    let result = send(&mut context, hand_over(None), &[&sender]).await;
+    assert_transfer_error(result, TransferError::RegistryRequired);
+    let registry = Some(registry_pda(&sender.pubkey()));
+    let result = send(&mut context, hand_over(registry), &[&sender]).await;
+    assert_transfer_error(result, TransferError::SenderFrozen);
+
+    // Leaving the registry out does not sidestep the freeze
+    let execute_without_registry = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute_without_registry, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::RegistryRequired);
+
+    let unfreeze = || {
+        freeze_ix(
+            &sender.pubkey(),
+            anchor_lang::InstructionData::data(&scheduled_transfer::instruction::Unfreeze {}),
+        )
+    };
+    let result = send(&mut context, unfreeze(), &[&sender]).await;
+    assert_transfer_error(result, TransferError::UnfreezeLocked);
+
+    warp_to_timestamp(&mut context, frozen_at + UNFREEZE_DELAY_SECONDS).await;
+    send(&mut context, unfreeze(), &[&sender]).await.unwrap();
+    send(&mut context, execute(transfer_account), &[&recipient]).await.unwrap();
+    assert!(fetch_transfer(&mut context, &transfer_account).await.executed);
+    send(&mut context, hand_over(registry), &[&sender]).await.unwrap();
+    let handed_over = fetch_transfer(&mut context, &unregistered_account).await;
+    assert_eq!(handed_over.authority, new_authority.pubkey());
+}
+
+#[tokio::test]
+async fn test_require_ack_finalizes_on_acknowledgment() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let outsider = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    fund_account(&mut context, &outsider.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [139u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            require_ack: true,
+            ..default_schedule_args(200_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    // Execution only opens the acknowledgment window
+    warp_to_timestamp(&mut context, execute_after).await;
+    let recipient_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+    let event = find_event::<AcknowledgmentRequested>(&logs)
+        .expect("AcknowledgmentRequested not emitted");
+    assert_eq!(event.payee, recipient.pubkey());
+    assert_eq!(event.held_amount, 200_000_000);
+    assert_eq!(event.ack_deadline, execute_after + ACK_WINDOW_SECONDS);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(!transfer.executed);
+    assert_eq!(transfer.ack_deadline, execute_after + ACK_WINDOW_SECONDS);
+
+    // Neither another execution nor the sender can move the funds meanwhile
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::AckPending);
+    let cancel = cancel_sol_ix(&transfer_account, &sender.pubkey());
+    let result = send(&mut context, cancel, &[&sender]).await;
+    assert_transfer_error(result, TransferError::AckPending);
+
+    let acknowledge = acknowledge_receipt_sol_ix(&transfer_account, &outsider.pubkey());
+    let result = send(&mut context, acknowledge, &[&outsider]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let acknowledge = acknowledge_receipt_sol_ix(&transfer_account, &recipient.pubkey());
+    send(&mut context, acknowledge, &[&recipient]).await.unwrap();
+    let recipient_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(recipient_after - recipient_before, 200_000_000);
+    assert!(fetch_transfer(&mut context, &transfer_account).await.executed);
+}
+
+#[tokio::test]
+async fn test_require_ack_reclaimed_after_window() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [140u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            require_ack: true,
+            ..default_schedule_args(200_000_000, execute_after, nonce)
+        },
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+
+    let reclaim = || reclaim_unacknowledged_sol_ix(&transfer_account, &sender.pubkey());
+    let result = send(&mut context, reclaim(), &[&sender]).await;
+    assert_transfer_error(result, TransferError::AckPending);
+
+    warp_to_timestamp(&mut context, execute_after + ACK_WINDOW_SECONDS + 1).await;
+    let acknowledge = acknowledge_receipt_sol_ix(&transfer_account, &recipient.pubkey());
+    let result = send(&mut context, acknowledge, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::AckWindowExpired);
+
+    let sender_before = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    send(&mut context, reclaim(), &[&sender]).await.unwrap();
+    let sender_after = context.banks_client.get_balance(sender.pubkey()).await.unwrap();
+    assert_eq!(sender_after - sender_before, 200_000_000);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.cancelled);
+    assert!(!transfer.executed);
+}
+
+#[tokio::test]
+async fn test_wrapper_program_schedules_from_pda_via_cpi() {
+    let wrapper_program = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_program("wrapper", wrapper_program, processor!(wrapper_schedule));
+    let mut context = program_test.start_with_context().await;
+
+    let (vault, _) = Pubkey::find_program_address(&[b"vault"], &wrapper_program);
+    let recipient = Keypair::new();
+    fund_account(&mut context, &vault, 1_000_000_000).await;
+    let mint = create_mint(&mut context).await;
+    let vault_token_account = create_token_account(&mut context, &mint, &vault).await;
+    mint_tokens(&mut context, &mint, &vault_token_account, 1_000_000).await;
+
+    let nonce = [141u8; 32];
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let transfer_account = transfer_pda(&vault, &nonce);
+    let escrow = escrow_ata(&transfer_account, &mint);
+    let wrapper_ix = Instruction {
+        program_id: wrapper_program,
+        accounts: vec![
+            AccountMeta::new(vault, false),
+            AccountMeta::new(transfer_account, false),
+            AccountMeta::new_readonly(recipient.pubkey(), false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(config_pda(), false),
+            AccountMeta::new_readonly(denylist_pda(), false),
+            AccountMeta::new(recipient_policy_pda(&recipient.pubkey()), false),
+            AccountMeta::new(vault_token_account, false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(anchor_spl::associated_token::ID, false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(solana_program::sysvar::rent::id(), false),
+            AccountMeta::new_readonly(scheduled_transfer::id(), false),
+        ],
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ScheduleTransfer {
+                params: default_schedule_args(600_000, execute_after, nonce),
+            },
+        ),
+    };
+    send(&mut context, wrapper_ix, &[]).await.unwrap();
+
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.sender, vault);
+    assert_eq!(transfer.authority, vault);
+    assert_eq!(transfer.amount, 600_000);
+    assert_eq!(token_balance(&mut context, &escrow).await, 600_000);
+    assert_eq!(token_balance(&mut context, &vault_token_account).await, 400_000);
+}
+
+#[tokio::test]
+async fn test_parse_transfer_info_current_and_legacy_layouts() {
+    // A transfer written before `program_version`, in the original layout
+    let legacy_account = Pubkey::new_unique();
+    let legacy_sender = Pubkey::new_unique();
+    let legacy_recipient = Pubkey::new_unique();
+    let mut legacy_data = ScheduledTransfer::DISCRIMINATOR.to_vec();
+    legacy_sender.serialize(&mut legacy_data).unwrap();
+    legacy_recipient.serialize(&mut legacy_data).unwrap();
+    250_000u64.serialize(&mut legacy_data).unwrap();
+    solana_program::system_program::id().serialize(&mut legacy_data).unwrap();
+    1_000i64.serialize(&mut legacy_data).unwrap(); // execute_after
+    900i64.serialize(&mut legacy_data).unwrap(); // created_at
+    false.serialize(&mut legacy_data).unwrap(); // executed
+    0i64.serialize(&mut legacy_data).unwrap();
+    false.serialize(&mut legacy_data).unwrap(); // cancelled
+    0i64.serialize(&mut legacy_data).unwrap();
+    [7u8; 32].serialize(&mut legacy_data).unwrap(); // nonce
+    "legacy".to_string().serialize(&mut legacy_data).unwrap();
+    255u8.serialize(&mut legacy_data).unwrap(); // bump
+
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_account(
+        legacy_account,
+        solana_sdk::account::Account {
+            lamports: 10_000_000,
+            data: legacy_data,
+            owner: scheduled_transfer::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [142u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    let parsed = parse_transfer_info(&mut context, &transfer_account).await;
+    assert_eq!(parsed, transfer_info(&mut context, &transfer_account).await);
+
+    let legacy = parse_transfer_info(&mut context, &legacy_account).await;
+    assert_eq!(legacy.sender, legacy_sender);
+    assert_eq!(legacy.recipient, legacy_recipient);
+    assert_eq!(legacy.amount, 250_000);
+    assert_eq!(legacy.created_slot, 0);
+    assert_eq!(legacy.last_modified_at, 900);
+    assert_eq!(legacy.memo, "legacy");
+    assert!(legacy.is_executable_now);
+    assert_eq!(legacy.program_version, 0);
+}
+
+#[tokio::test]
+async fn test_sol_surplus_credited_to_recipient() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    let update = ConfigUpdate {
+        credit_surplus_to_recipient: Some(true),
+        ..ConfigUpdate::default()
+    };
+    send(&mut context, update_config_ix(&admin.pubkey(), update), &[&admin]).await.unwrap();
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [143u8; 32];
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(200_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    // Someone sends extra lamports straight to the escrow
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    fund_account(&mut context, &transfer_account, 5_000_000).await;
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let recipient_before = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    let execute = execute_sol_ix_with_config(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+
+    let event = find_event::<SurplusCredited>(&logs).expect("SurplusCredited not emitted");
+    assert_eq!(event.recipient, recipient.pubkey());
+    assert_eq!(event.amount, 5_000_000);
+    assert!(find_event::<SurplusReturned>(&logs).is_none());
+    let recipient_after = context.banks_client.get_balance(recipient.pubkey()).await.unwrap();
+    assert_eq!(recipient_after - recipient_before, 205_000_000);
+}
+
+#[tokio::test]
+async fn test_large_memo_round_trips() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let memo = "x".repeat(1024);
+
+    // The capacity is capped, and the memo must fit the capacity asked for
+    let schedule = schedule_large_memo_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        [144u8; 32],
+        execute_after,
+        memo.clone(),
+        MAX_MEMO_CAPACITY as u16 + 1,
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MemoTooLong);
+    let schedule = schedule_large_memo_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        [144u8; 32],
+        execute_after,
+        memo.clone(),
+        512,
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::MemoTooLong);
+
+    let nonce = [145u8; 32];
+    let schedule = schedule_large_memo_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        nonce,
+        execute_after,
+        memo.clone(),
+        1024,
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert_eq!(transfer.memo, memo);
+    let account = context.banks_client.get_account(transfer_account).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), ScheduledTransfer::space_with_memo_capacity(1024, 0));
+}
+
+#[tokio::test]
+async fn test_transfer_info_unset_times_are_none() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let executed = transfer_pda(&sender.pubkey(), &[146u8; 32]);
+    let cancelled = transfer_pda(&sender.pubkey(), &[147u8; 32]);
+    for nonce in [[146u8; 32], [147u8; 32]] {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(100_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+    }
+
+    let info = transfer_info(&mut context, &executed).await;
+    assert_eq!(info.executed_at, None);
+    assert_eq!(info.cancelled_at, None);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let execute =
+        execute_sol_ix(&executed, &sender.pubkey(), &recipient.pubkey(), &recipient.pubkey());
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    send(&mut context, cancel_sol_ix(&cancelled, &sender.pubkey()), &[&sender]).await.unwrap();
+
+    let info = transfer_info(&mut context, &executed).await;
+    assert_eq!(info.executed_at, Some(execute_after));
+    assert_eq!(info.cancelled_at, None);
+    let info = transfer_info(&mut context, &cancelled).await;
+    assert_eq!(info.executed_at, None);
+    assert_eq!(info.cancelled_at, Some(execute_after));
+}
+
+#[tokio::test]
+async fn test_recipient_cooldown_between_schedules() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let other_recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    send(&mut context, set_recipient_cooldown_ix(&recipient.pubkey(), 3_600), &[&recipient])
+        .await
+        .unwrap();
+
+    let now = current_timestamp(&mut context).await;
+    let schedule = |nonce: [u8; 32], recipient: &Pubkey| {
+        schedule_sol_ix(
+            &sender.pubkey(),
+            recipient,
+            default_schedule_args(10_000_000, now + 86_400, nonce),
+        )
+    };
+    send(&mut context, schedule([148u8; 32], &recipient.pubkey()), &[&sender]).await.unwrap();
+    let result = send(&mut context, schedule([149u8; 32], &recipient.pubkey()), &[&sender]).await;
+    assert_transfer_error(result, TransferError::RecipientCooldownActive);
+
+    // Recipients who never opted in are unaffected
+    send(&mut context, schedule([150u8; 32], &other_recipient.pubkey()), &[&sender])
+        .await
+        .unwrap();
+    send(&mut context, schedule([151u8; 32], &other_recipient.pubkey()), &[&sender])
+        .await
+        .unwrap();
+
+    warp_to_timestamp(&mut context, now + 3_700).await;
+    send(&mut context, schedule([149u8; 32], &recipient.pubkey()), &[&sender]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cancel_and_reschedule_moves_escrow() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let new_recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &new_recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let nonce = [152u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(200_000_000, execute_after, nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+
+    let new_nonce = [153u8; 32];
+    let new_transfer_account = transfer_pda(&sender.pubkey(), &new_nonce);
+
+    // Program-owned SOL recipients still need the sender to vouch for them
+    let to_program_account = cancel_and_reschedule_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &solana_program::sysvar::clock::id(),
+        execute_after + 120,
+        new_nonce,
+        false,
+    );
+    let result = send(&mut context, to_program_account, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidRecipient);
+
+    let reschedule = cancel_and_reschedule_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &new_recipient.pubkey(),
+        execute_after + 120,
+        new_nonce,
+        false,
+    );
+    let logs = send_with_logs(&mut context, reschedule, &[&sender]).await;
+    let cancelled = find_event::<TransferCancelled>(&logs).expect("TransferCancelled not emitted");
+    assert_eq!(cancelled.transfer_id, transfer_account);
+    assert_eq!(cancelled.amount, 0);
+    let event = find_event::<TransferRescheduled>(&logs).expect("TransferRescheduled not emitted");
+    assert_eq!(event.transfer_id, transfer_account);
+    assert_eq!(event.new_transfer_id, new_transfer_account);
+    assert_eq!(event.amount, 200_000_000);
+
+    // The old escrow is left with only its rent; the new one holds the funds
+    let old = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(old.cancelled);
+    let old_lamports = context.banks_client.get_balance(transfer_account).await.unwrap();
+    assert_eq!(old_lamports, old.rent_reserve);
+    let new = fetch_transfer(&mut context, &new_transfer_account).await;
+    assert_eq!(new.recipient, new_recipient.pubkey());
+    assert_eq!(new.amount, 200_000_000);
+    assert_eq!(new.execute_after, execute_after + 120);
+    let new_lamports = context.banks_client.get_balance(new_transfer_account).await.unwrap();
+    assert_eq!(new_lamports, new.rent_reserve + 200_000_000);
+
+    warp_to_timestamp(&mut context, execute_after + 120).await;
+    let before = context.banks_client.get_balance(new_recipient.pubkey()).await.unwrap();
+    let execute = execute_sol_ix(
+        &new_transfer_account,
+        &sender.pubkey(),
+        &new_recipient.pubkey(),
+        &new_recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&new_recipient]).await.unwrap();
+    let after = context.banks_client.get_balance(new_recipient.pubkey()).await.unwrap();
+    assert_eq!(after - before, 200_000_000);
+
+    let vouched_nonce = [172u8; 32];
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after + 600, vouched_nonce),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    let reschedule = cancel_and_reschedule_sol_ix(
+        &transfer_pda(&sender.pubkey(), &vouched_nonce),
+        &sender.pubkey(),
+        &solana_program::sysvar::clock::id(),
+        execute_after + 600,
+        [173u8; 32],
+        true,
+    );
+    send(&mut context, reschedule, &[&sender]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_percentage_keeper_fee_floor_and_ceiling() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 2_000_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let args = |amount: u64, nonce: [u8; 32]| ScheduleTransferParams {
+        keeper_fee_lamports: 1_000_000,
+        keeper_fee_bps: 100,
+        keeper_fee_ceiling_lamports: 5_000_000,
+        ..default_schedule_args(amount, execute_after, nonce)
+    };
+
+    // 1% of 50M is under the floor, of 200M between the bounds, of 900M over the ceiling
+    for (amount, nonce, expected_fee) in [
+        (50_000_000, [154u8; 32], 1_000_000),
+        (200_000_000, [155u8; 32], 2_000_000),
+        (900_000_000, [156u8; 32], 5_000_000),
+    ] {
+        let schedule = schedule_sol_ix(&sender.pubkey(), &recipient.pubkey(), args(amount, nonce));
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+        let transfer = fetch_transfer(&mut context, &transfer_pda(&sender.pubkey(), &nonce)).await;
+        assert_eq!(transfer.keeper_fee_lamports, expected_fee);
+    }
+
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        ScheduleTransferParams {
+            keeper_fee_ceiling_lamports: 500_000,
+            ..args(100_000_000, [157u8; 32])
+        },
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidKeeperFee);
+}
+
+#[tokio::test]
+async fn test_notify_program_receives_funds_callback() {
+    let recipient_program = Pubkey::new_unique();
+    let silent_program = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    );
+    program_test.add_program(
+        "mock_recipient_program",
+        recipient_program,
+        processor!(on_funds_received),
+    );
+    program_test.add_program(
+        "mock_condition_reject",
+        silent_program,
+        processor!(reject_condition),
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let notified_nonce = [158u8; 32];
+    let required_nonce = [159u8; 32];
+    for (nonce, notify_program) in [
+        (notified_nonce, recipient_program),
+        (required_nonce, silent_program),
+    ] {
+        let schedule = schedule_sol_ix(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(100_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+        let transfer_account = transfer_pda(&sender.pubkey(), &nonce);
+        let set_notify = set_notify_program_ix(
+            &transfer_account,
+            &sender.pubkey(),
+            Some(notify_program),
+            true,
+        );
+        send(&mut context, set_notify, &[&sender]).await.unwrap();
+    }
+
+    warp_to_timestamp(&mut context, execute_after).await;
+
+    // The recipient program is told which transfer paid it and how much
+    let transfer_account = transfer_pda(&sender.pubkey(), &notified_nonce);
+    let execute = execute_ix(scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        notify_program: Some(recipient_program),
+        ..execute_sol_accounts(
+            &transfer_account,
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &recipient.pubkey(),
+        )
+    });
+    let logs = send_with_logs(&mut context, execute, &[&recipient]).await;
+    let expected = format!("on_funds_received transfer={} amount=100000000", transfer_account);
+    assert!(logs.iter().any(|log| log.contains(&expected)), "{:?}", logs);
+
+    // A required callback fails without an acknowledgment or without the program
+    let transfer_account = transfer_pda(&sender.pubkey(), &required_nonce);
+    let execute = execute_ix(scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        notify_program: Some(silent_program),
+        ..execute_sol_accounts(
+            &transfer_account,
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            &recipient.pubkey(),
+        )
+    });
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::CallbackFailed);
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    let result = send(&mut context, execute, &[&recipient]).await;
+    assert_transfer_error(result, TransferError::CallbackFailed);
+
+    // Best-effort, the payout goes through either way
+    let set_notify = set_notify_program_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        Some(silent_program),
+        false,
+    );
+    send(&mut context, set_notify, &[&sender]).await.unwrap();
+    let execute = execute_sol_ix(
+        &transfer_account,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    let transfer = fetch_transfer(&mut context, &transfer_account).await;
+    assert!(transfer.executed);
+}
+
+#[tokio::test]
+async fn test_get_last_seq_advances_with_each_funds_movement() {
+    let mut context = start_program_test().await;
+    let admin = Keypair::new();
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    fund_account(&mut context, &admin.pubkey(), 100_000_000).await;
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &recipient.pubkey(), 10_000_000).await;
+
+    send(&mut context, initialize_config_ix(&admin.pubkey()), &[&admin]).await.unwrap();
+    assert_eq!(last_seq(&mut context).await, 0);
+
+    let execute_after = current_timestamp(&mut context).await + 60;
+    for (nonce, expected_seq) in [([160u8; 32], 1), ([161u8; 32], 2)] {
+        let schedule = schedule_sol_ix_with_config(
+            &sender.pubkey(),
+            &recipient.pubkey(),
+            default_schedule_args(100_000_000, execute_after, nonce),
+        );
+        send(&mut context, schedule, &[&sender]).await.unwrap();
+        assert_eq!(last_seq(&mut context).await, expected_seq);
+    }
+
+    // Scheduling cannot skip the count by leaving the config out
+    let schedule = schedule_sol_ix(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [162u8; 32]),
+    );
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+    assert_eq!(last_seq(&mut context).await, 2);
+
+    let schedule = schedule_sol_ix_with_config(
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        default_schedule_args(100_000_000, execute_after, [162u8; 32]),
+    );
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+    assert_eq!(last_seq(&mut context).await, 3);
+
+    warp_to_timestamp(&mut context, execute_after).await;
+    let executed = transfer_pda(&sender.pubkey(), &[160u8; 32]);
+    let execute = execute_sol_ix_with_config(
+        &executed,
+        &sender.pubkey(),
+        &recipient.pubkey(),
+        &recipient.pubkey(),
+    );
+    send(&mut context, execute, &[&recipient]).await.unwrap();
+    assert_eq!(last_seq(&mut context).await, 4);
+
+    let cancelled = transfer_pda(&sender.pubkey(), &[161u8; 32]);
+    let cancel =
+        build_cancel_sol_ix(&cancelled, &sender.pubkey(), None, None, None, Some(config_pda()));
+    send(&mut context, cancel, &[&sender]).await.unwrap();
+    assert_eq!(last_seq(&mut context).await, 5);
+
+    let reduced = transfer_pda(&sender.pubkey(), &[162u8; 32]);
+    let reduce =
+        build_reduce_transfer_ix(&reduced, &sender.pubkey(), None, Some(config_pda()), 40_000_000);
+    send(&mut context, reduce, &[&sender]).await.unwrap();
+    assert_eq!(last_seq(&mut context).await, 6);
+
+    // Funds cannot move unsequenced by leaving the config out
+    let result = send(&mut context, cancel_sol_ix(&reduced, &sender.pubkey()), &[&sender]).await;
+    assert_transfer_error(result, TransferError::ConfigRequired);
+    assert_eq!(last_seq(&mut context).await, 6);
+
+    // Nor can the sequence be reset by re-creating the config
+    let result = send(&mut context, close_config_ix(&admin.pubkey()), &[&admin]).await;
+    assert_transfer_error(result, TransferError::ConfigInUse);
+}
+
+#[tokio::test]
+async fn test_schedule_rejects_non_mint_token_mint() {
+    let mut context = start_program_test().await;
+    let sender = Keypair::new();
+    let recipient = Keypair::new();
+    let not_a_mint = Keypair::new();
+    fund_account(&mut context, &sender.pubkey(), 1_000_000_000).await;
+    fund_account(&mut context, &not_a_mint.pubkey(), 10_000_000).await;
+    let mint = create_mint(&mut context).await;
+
+    // Unconfirmed transfers take no token accounts, so only the mint check stands
+    // between a garbage mint and the stored transfer
+    let execute_after = current_timestamp(&mut context).await + 60;
+    let schedule_unconfirmed = |token_mint: Pubkey, nonce: [u8; 32]| {
+        let accounts = scheduled_transfer::accounts::ScheduleTransfer {
+            transfer_account: transfer_pda(&sender.pubkey(), &nonce),
+            sender: sender.pubkey(),
+            recipient: recipient.pubkey(),
+            token_mint,
+            config: None,
+            config_pda: config_pda(),
+            denylist: denylist_pda(),
+            recipient_policy: recipient_policy_pda(&recipient.pubkey()),
+            registry: None,
+            mint_stats: None,
+            campaign_stats: None,
+            refund_of_transfer: None,
+            sender_token_account: None,
+            escrow_token_account: None,
+            token_program: None,
+            associated_token_program: None,
+            system_program: solana_program::system_program::id(),
+            rent: solana_program::sysvar::rent::id(),
+            memo_program: None,
+        };
+        Instruction {
+            program_id: scheduled_transfer::id(),
+            accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+            data: anchor_lang::InstructionData::data(
+                &scheduled_transfer::instruction::ScheduleTransfer {
+                    params: ScheduleTransferParams {
+                        require_confirmation: true,
+                        ..default_schedule_args(400_000, execute_after, nonce)
+                    },
+                },
+            ),
+        }
+    };
+
+    let schedule = schedule_unconfirmed(not_a_mint.pubkey(), [163u8; 32]);
+    let result = send(&mut context, schedule, &[&sender]).await;
+    assert_transfer_error(result, TransferError::InvalidTokenMint);
+
+    let schedule = schedule_unconfirmed(mint, [164u8; 32]);
+    send(&mut context, schedule, &[&sender]).await.unwrap();
+}
+
+async fn start_program_test() -> ProgramTestContext {
+    ProgramTest::new(
+        "scheduled_transfer",
+        scheduled_transfer::id(),
+        processor!(scheduled_transfer::entry),
+    )
+    .start_with_context()
+    .await
+}
+
+async fn fund_account(context: &mut ProgramTestContext, account: &Pubkey, lamports: u64) {
+    let fund_ix = system_instruction::transfer(&context.payer.pubkey(), account, lamports);
+    send(context, fund_ix, &[]).await.unwrap();
+}
+
+async fn current_timestamp(context: &mut ProgramTestContext) -> i64 {
+    context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .unix_timestamp
+}
+
+async fn warp_to_timestamp(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    context.set_sysvar(&clock);
+}
+
+// Signs with the context payer plus any extra signers
+async fn send(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> std::result::Result<(), BanksClientError> {
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers: Vec<&Keypair> = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await
+}
+
+fn assert_transfer_error(
+    result: std::result::Result<(), BanksClientError>,
+    expected: TransferError,
+) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(error_code),
+        ))) => assert_eq!(error_code, u32::from(expected)),
+        other => panic!("Expected {:?}, got {:?}", expected, other),
+    }
+}
+
+fn transfer_pda(sender: &Pubkey, nonce: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"transfer", sender.as_ref(), nonce.as_ref()],
+        &scheduled_transfer::id(),
+    )
+    .0
+}
+
+// The transfer PDA, recipient and recipient policy `batch_schedule` expects per entry
+fn batch_entry_metas(sender: &Pubkey, entry: &ScheduleParams) -> [AccountMeta; 3] {
+    [
+        AccountMeta::new(transfer_pda(sender, &entry.nonce), false),
+        AccountMeta::new_readonly(entry.recipient, false),
+        AccountMeta::new(recipient_policy_pda(&entry.recipient), false),
+    ]
+}
+
+fn default_schedule_args(
+    amount: u64,
+    execute_after: i64,
+    nonce: [u8; 32],
+) -> ScheduleTransferParams {
+    ScheduleTransferParams {
+        amount: AmountSpec::Absolute(amount),
+        execute_after,
+        nonce,
+        ..Default::default()
+    }
+}
+
+fn schedule_sol_ix_with_registry(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    let registry = registry_pda(sender);
+    build_schedule_sol_ix(sender, recipient, None, Some(registry), None, None, params)
+}
+
+fn schedule_sol_ix_with_config(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    build_schedule_sol_ix(sender, recipient, Some(config_pda()), None, None, None, params)
+}
+
+fn schedule_sol_ix_with_mint_stats(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    let mint_stats = mint_stats_pda(&solana_program::system_program::id());
+    build_schedule_sol_ix(sender, recipient, None, None, Some(mint_stats), None, params)
+}
+
+fn schedule_sol_ix_with_campaign(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    let campaign_stats = campaign_stats_pda(params.campaign_id);
+    build_schedule_sol_ix(sender, recipient, None, None, None, Some(campaign_stats), params)
+}
+
+fn build_schedule_sol_ix(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    config: Option<Pubkey>,
+    registry: Option<Pubkey>,
+    mint_stats: Option<Pubkey>,
+    campaign_stats: Option<Pubkey>,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ScheduleTransfer {
+        transfer_account: transfer_pda(sender, &params.nonce),
+        sender: *sender,
+        recipient: *recipient,
+        token_mint: solana_program::system_program::id(),
+        config,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(recipient),
+        registry,
+        mint_stats,
+        campaign_stats,
+        refund_of_transfer: None,
+        sender_token_account: None,
+        escrow_token_account: None,
+        token_program: None,
+        associated_token_program: None,
+        system_program: solana_program::system_program::id(),
+        rent: solana_program::sysvar::rent::id(),
+        memo_program: None,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ScheduleTransfer { params },
+        ),
+    }
+}
+
+// Schedules a SOL refund with its `refund_of` reference checked on-chain
+fn schedule_sol_refund_ix(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    refund_of_transfer: &Pubkey,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ScheduleTransfer {
+        transfer_account: transfer_pda(sender, &params.nonce),
+        sender: *sender,
+        recipient: *recipient,
+        token_mint: solana_program::system_program::id(),
+        config: None,
+        config_pda: config_pda(),
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(recipient),
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        refund_of_transfer: Some(*refund_of_transfer),
+        sender_token_account: None,
+        escrow_token_account: None,
+        token_program: None,
+        associated_token_program: None,
+        system_program: solana_program::system_program::id(),
+        rent: solana_program::sysvar::rent::id(),
+        memo_program: None,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ScheduleTransfer { params },
+        ),
+    }
+}
+
+fn execute_sol_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    executor: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    build_execute_sol_ix(transfer_account, sender, executor, recipient, None, None, None, None)
+}
+
+fn execute_sol_ix_with_config(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    executor: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    build_execute_sol_ix(
+        transfer_account,
+        sender,
+        executor,
+        recipient,
+        None,
+        Some(config_pda()),
+        None,
+        None,
+    )
+}
+
+fn execute_sol_ix_with_mint_stats(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    executor: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    build_execute_sol_ix(
+        transfer_account,
+        sender,
+        executor,
+        recipient,
+        None,
+        None,
+        None,
+        Some(mint_stats_pda(&solana_program::system_program::id())),
+    )
+}
+
+fn build_execute_sol_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    executor: &Pubkey,
+    recipient: &Pubkey,
+    condition_program: Option<Pubkey>,
+    config: Option<Pubkey>,
+    refund_to: Option<Pubkey>,
+    mint_stats: Option<Pubkey>,
+) -> Instruction {
+    execute_ix(scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        condition_program,
+        config,
+        sender: refund_to,
+        mint_stats,
+        ..execute_sol_accounts(transfer_account, sender, executor, recipient)
+    })
+}
+
+fn execute_sol_ix_with_campaign(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    executor: &Pubkey,
+    recipient: &Pubkey,
+    campaign_id: u64,
+) -> Instruction {
+    execute_ix(scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        campaign_stats: Some(campaign_stats_pda(campaign_id)),
+        ..execute_sol_accounts(transfer_account, sender, executor, recipient)
+    })
+}
+
+/// SOL execute accounts with every optional account omitted
+fn execute_sol_accounts(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    executor: &Pubkey,
+    recipient: &Pubkey,
+) -> scheduled_transfer::accounts::ExecuteScheduledTransfer {
+    scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        transfer_account: *transfer_account,
+        executor: *executor,
+        recipient: *recipient,
+        escrow_token_account: None,
+        recipient_token_account: None,
+        recipient_output_token_account: None,
+        new_recipient_token_account: None,
+        token_mint: None,
+        sender: None,
+        sender_token_account: None,
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        registry: None,
+        registry_pda: registry_pda(sender),
+        mint_stats: None,
+        campaign_stats: None,
+        previous_transfer: None,
+        condition_program: None,
+        swap_program: None,
+        notify_program: None,
+        token_program: None,
+        system_program: solana_program::system_program::id(),
+        memo_program: None,
+        receipt_mint: None,
+        receipt_token_account: None,
+        receipt_metadata: None,
+        associated_token_program: None,
+        metadata_program: None,
+        rent: None,
+    }
+}
+
+fn execute_ix(accounts: scheduled_transfer::accounts::ExecuteScheduledTransfer) -> Instruction {
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ExecuteScheduledTransfer {},
+        ),
+    }
+}
+
+fn cancel_sol_ix(transfer_account: &Pubkey, sender: &Pubkey) -> Instruction {
+    build_cancel_sol_ix(transfer_account, sender, None, None, None, None)
+}
+
+fn cancel_sol_ix_with_mint_stats(transfer_account: &Pubkey, sender: &Pubkey) -> Instruction {
+    let mint_stats = mint_stats_pda(&solana_program::system_program::id());
+    build_cancel_sol_ix(transfer_account, sender, None, None, Some(mint_stats), None)
+}
+
+fn build_cancel_sol_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    registry: Option<Pubkey>,
+    previous_transfer: Option<Pubkey>,
+    mint_stats: Option<Pubkey>,
+    config: Option<Pubkey>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::CancelScheduledTransfer {
+        transfer_account: *transfer_account,
+        sender: *sender,
+        sender_token_account: None,
+        escrow_token_account: None,
+        registry,
+        mint_stats,
+        previous_transfer,
+        config,
+        config_pda: config_pda(),
+        recipient_token_account: None,
+        token_program: None,
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::CancelScheduledTransfer {},
+        ),
+    }
+}
+
+fn mark_unexecutable_sol_ix(transfer_account: &Pubkey, sender: &Pubkey) -> Instruction {
+    build_mark_unexecutable_ix(transfer_account, sender, None)
+}
+
+fn mark_unexecutable_spl_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    sender_token_account: &Pubkey,
+    escrow_token_account: &Pubkey,
+) -> Instruction {
+    let token_accounts = Some((*sender_token_account, *escrow_token_account));
+    build_mark_unexecutable_ix(transfer_account, sender, token_accounts)
+}
+
+fn build_mark_unexecutable_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    token_accounts: Option<(Pubkey, Pubkey)>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::CancelScheduledTransfer {
+        transfer_account: *transfer_account,
+        sender: *sender,
+        sender_token_account: token_accounts.map(|(sender_token, _)| sender_token),
+        escrow_token_account: token_accounts.map(|(_, escrow)| escrow),
+        registry: None,
+        mint_stats: None,
+        previous_transfer: None,
+        config: None,
+        config_pda: config_pda(),
+        recipient_token_account: None,
+        token_program: token_accounts.map(|_| spl_token::id()),
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::MarkUnexecutable {},
+        ),
+    }
+}
+
+fn reclaim_undeliverable_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    sender_token_account: &Pubkey,
+    escrow_token_account: &Pubkey,
+    recipient_token_account: &Pubkey,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::CancelScheduledTransfer {
+        transfer_account: *transfer_account,
+        sender: *sender,
+        sender_token_account: Some(*sender_token_account),
+        escrow_token_account: Some(*escrow_token_account),
+        registry: None,
+        mint_stats: None,
+        previous_transfer: None,
+        config: None,
+        config_pda: config_pda(),
+        recipient_token_account: Some(*recipient_token_account),
+        token_program: Some(spl_token::id()),
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ReclaimUndeliverable {},
+        ),
+    }
+}
+
+fn acknowledge_receipt_sol_ix(transfer_account: &Pubkey, payee: &Pubkey) -> Instruction {
+    let accounts = scheduled_transfer::accounts::AcknowledgeReceipt {
+        transfer_account: *transfer_account,
+        payee: *payee,
+        escrow_token_account: None,
+        payee_token_account: None,
+        registry: None,
+        mint_stats: None,
+        previous_transfer: None,
+        config: None,
+        config_pda: config_pda(),
+        token_program: None,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::AcknowledgeReceipt {},
+        ),
+    }
+}
+
+fn reclaim_unacknowledged_sol_ix(transfer_account: &Pubkey, sender: &Pubkey) -> Instruction {
+    Instruction {
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ReclaimUnacknowledged {},
+        ),
+        ..cancel_sol_ix(transfer_account, sender)
+    }
+}
+
+fn cancel_and_reschedule_sol_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    execute_after: i64,
+    nonce: [u8; 32],
+    recipient_is_pda: bool,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::CancelAndReschedule {
+        transfer_account: *transfer_account,
+        new_transfer_account: transfer_pda(sender, &nonce),
+        sender: *sender,
+        recipient: *recipient,
+        token_mint: solana_program::system_program::id(),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(recipient),
+        registry: None,
+        registry_pda: registry_pda(sender),
+        previous_transfer: None,
+        escrow_token_account: None,
+        new_escrow_token_account: None,
+        token_program: None,
+        associated_token_program: None,
+        system_program: solana_program::system_program::id(),
+        rent: solana_program::sysvar::rent::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::CancelAndReschedule {
+                execute_after,
+                nonce,
+                memo: String::new(),
+                recipient_is_pda,
+            },
+        ),
+    }
+}
+
+fn attach_milestones_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    arbiter: &Pubkey,
+    amounts: Vec<u64>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::AttachMilestones {
+        transfer_account: *transfer_account,
+        sender: *sender,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::AttachMilestones {
+                arbiter: *arbiter,
+                amounts,
+            },
+        ),
+    }
+}
+
+fn settle_milestone_sol_ix(
+    transfer_account: &Pubkey,
+    arbiter: &Pubkey,
+    destination: &Pubkey,
+    index: u8,
+    approve: bool,
+) -> Instruction {
+    build_settle_milestone_sol_ix(transfer_account, arbiter, destination, index, approve, None)
+}
+
+fn build_settle_milestone_sol_ix(
+    transfer_account: &Pubkey,
+    arbiter: &Pubkey,
+    destination: &Pubkey,
+    index: u8,
+    approve: bool,
+    registry: Option<Pubkey>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::SettleMilestone {
+        transfer_account: *transfer_account,
+        arbiter: *arbiter,
+        destination: *destination,
+        escrow_token_account: None,
+        destination_token_account: None,
+        registry,
+        mint_stats: None,
+        previous_transfer: None,
+        config: None,
+        config_pda: config_pda(),
+        token_program: None,
+    };
+    let data = if approve {
+        anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ReleaseMilestone { index },
+        )
+    } else {
+        anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::RejectMilestone { index },
+        )
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data,
+    }
+}
+
+fn close_stale_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    escrow_token_account: Option<Pubkey>,
+    sender_token_account: Option<Pubkey>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::CloseStale {
+        transfer_account: *transfer_account,
+        sender: *sender,
+        escrow_token_account,
+        sender_token_account,
+        config: config_pda(),
+        token_program: escrow_token_account.map(|_| spl_token::id()),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(&scheduled_transfer::instruction::CloseStale {}),
+    }
+}
+
+fn migrate_to_program_ix(
+    transfer_account: &Pubkey,
+    admin: &Pubkey,
+    sender: &Pubkey,
+    escrow_token_account: &Pubkey,
+    destination_token_account: &Pubkey,
+    owner_seeds: Vec<Vec<u8>>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::MigrateToProgram {
+        transfer_account: *transfer_account,
+        config: config_pda(),
+        admin: *admin,
+        sender: *sender,
+        escrow_token_account: *escrow_token_account,
+        destination_token_account: *destination_token_account,
+        registry: None,
+        mint_stats: None,
+        previous_transfer: None,
+        token_program: spl_token::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::MigrateToProgram { owner_seeds },
+        ),
+    }
+}
+
+// Allocated length of a transfer account and the Borsh length of its contents
+async fn transfer_sizes(
+    context: &mut ProgramTestContext,
+    transfer_account: &Pubkey,
+) -> (usize, usize) {
+    let account = context
+        .banks_client
+        .get_account(*transfer_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let transfer = ScheduledTransfer::try_deserialize(&mut account.data.as_slice()).unwrap();
+    (account.data.len(), transfer.try_to_vec().unwrap().len())
+}
+
+async fn fetch_transfer(
+    context: &mut ProgramTestContext,
+    transfer_account: &Pubkey,
+) -> ScheduledTransfer {
+    let account = context
+        .banks_client
+        .get_account(*transfer_account)
+        .await
+        .unwrap()
+        .unwrap();
+    ScheduledTransfer::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+fn reassign_recipient_ix(
+    transfer_account: &Pubkey,
+    recipient: &Pubkey,
+    new_recipient: &Pubkey,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ReassignRecipient {
+        transfer_account: *transfer_account,
+        recipient: *recipient,
+        new_recipient_account: *new_recipient,
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ReassignRecipient {
+                new_recipient: *new_recipient,
+                recipient_is_pda: false,
+            },
+        ),
+    }
+}
+
+fn authorize_execution_ix(
+    transfer_account: &Pubkey,
+    recipient: &Pubkey,
+    until: i64,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::AuthorizeExecution {
+        transfer_account: *transfer_account,
+        recipient: *recipient,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::AuthorizeExecution { until },
+        ),
+    }
+}
+
+fn consolidate_sol_ix(
+    transfer_account: &Pubkey,
+    authority: &Pubkey,
+    sources: &[Pubkey],
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ConsolidateTransfers {
+        transfer_account: *transfer_account,
+        authority: *authority,
+        escrow_token_account: None,
+        config: None,
+        config_pda: config_pda(),
+        token_program: None,
+    };
+    let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    account_metas.extend(sources.iter().map(|source| AccountMeta::new(*source, false)));
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: account_metas,
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ConsolidateTransfers {},
+        ),
+    }
+}
+
+fn transfer_ownership_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    build_transfer_ownership_ix(transfer_account, sender, authority, new_authority, None)
+}
+
+fn build_transfer_ownership_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    authority: &Pubkey,
+    new_authority: &Pubkey,
+    registry: Option<Pubkey>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::TransferOwnership {
+        transfer_account: *transfer_account,
+        authority: *authority,
+        registry,
+        registry_pda: registry_pda(sender),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::TransferOwnership {
+                new_authority: *new_authority,
+            },
+        ),
+    }
+}
+
+async fn send_with_logs(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> Vec<String> {
+    let (result, logs) = process_with_logs(context, instruction, signers).await;
+    assert!(result.is_ok(), "Transaction failed: {:?}", result);
+    logs
+}
+
+// Like `send_with_logs`, but returns the result instead of requiring success
+async fn process_with_logs(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> (Result<(), TransactionError>, Vec<String>) {
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers: Vec<&Keypair> = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        recent_blockhash,
+    );
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .unwrap();
+    (result.result, result.metadata.unwrap().log_messages)
+}
+
+// Decodes the first Anchor event of type `E` from `Program data:` log lines
+fn find_event<E: anchor_lang::Event>(logs: &[String]) -> Option<E> {
+    use base64::Engine;
+
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+        .find(|bytes| {
+            bytes.len() >= 8 && bytes[..8] == <E as anchor_lang::Discriminator>::discriminator()
+        })
+        .and_then(|bytes| E::deserialize(&mut &bytes[8..]).ok())
+}
+
+fn config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &scheduled_transfer::id()).0
+}
+
+async fn fetch_config(context: &mut ProgramTestContext) -> Option<ProgramConfig> {
+    let account = context.banks_client.get_account(config_pda()).await.unwrap()?;
+    ProgramConfig::try_deserialize(&mut account.data.as_slice()).ok()
+}
+
+fn initialize_config_ix(admin: &Pubkey) -> Instruction {
+    let accounts = scheduled_transfer::accounts::InitializeConfig {
+        config: config_pda(),
+        admin: *admin,
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::InitializeConfig {},
+        ),
+    }
+}
+
+fn recipient_policy_pda(recipient: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"recipient_policy", recipient.as_ref()],
+        &scheduled_transfer::id(),
+    )
+    .0
+}
+
+fn set_recipient_cooldown_ix(recipient: &Pubkey, cooldown_seconds: i64) -> Instruction {
+    let accounts = scheduled_transfer::accounts::SetRecipientCooldown {
+        recipient_policy: recipient_policy_pda(recipient),
+        recipient: *recipient,
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::SetRecipientCooldown { cooldown_seconds },
+        ),
+    }
+}
+
+fn denylist_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"denylist"], &scheduled_transfer::id()).0
+}
+
+fn initialize_denylist_ix(admin: &Pubkey) -> Instruction {
+    let accounts = scheduled_transfer::accounts::InitializeDenylist {
+        denylist: denylist_pda(),
+        config: config_pda(),
+        admin: *admin,
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::InitializeDenylist {},
+        ),
+    }
+}
+
+// `deny` selects `add_denied` or `remove_denied`
+fn manage_denylist_ix(admin: &Pubkey, address: &Pubkey, deny: bool) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ManageDenylist {
+        denylist: denylist_pda(),
+        config: config_pda(),
+        admin: *admin,
+    };
+    let data = if deny {
+        anchor_lang::InstructionData::data(&scheduled_transfer::instruction::AddDenied {
+            address: *address,
+        })
+    } else {
+        anchor_lang::InstructionData::data(&scheduled_transfer::instruction::RemoveDenied {
+            address: *address,
+        })
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data,
+    }
+}
+
+fn close_config_ix(admin: &Pubkey) -> Instruction {
+    let accounts = scheduled_transfer::accounts::CloseConfig {
+        config: config_pda(),
+        admin: *admin,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::CloseConfig {},
+        ),
+    }
+}
+
+fn update_config_ix(admin: &Pubkey, update: ConfigUpdate) -> Instruction {
+    let accounts = scheduled_transfer::accounts::UpdateConfig {
+        config: config_pda(),
+        admin: *admin,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::UpdateConfig { update },
+        ),
+    }
+}
+
+// `token_accounts` is `(sender_token_account, escrow_token_account)` for SPL transfers
+fn reduce_transfer_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    token_accounts: Option<(Pubkey, Pubkey)>,
+    reduce_by: u64,
+) -> Instruction {
+    build_reduce_transfer_ix(transfer_account, sender, token_accounts, None, reduce_by)
+}
+
+fn build_reduce_transfer_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    token_accounts: Option<(Pubkey, Pubkey)>,
+    config: Option<Pubkey>,
+    reduce_by: u64,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ReduceTransfer {
+        transfer_account: *transfer_account,
+        sender: *sender,
+        mint_stats: None,
+        sender_token_account: token_accounts.map(|(sender_token, _)| sender_token),
+        escrow_token_account: token_accounts.map(|(_, escrow)| escrow),
+        config,
+        config_pda: config_pda(),
+        token_program: token_accounts.map(|_| spl_token::id()),
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ReduceTransfer { reduce_by },
+        ),
+    }
+}
+
+fn schedule_large_memo_ix(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    nonce: [u8; 32],
+    execute_after: i64,
+    memo: String,
+    memo_capacity: u16,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ScheduleTransferLargeMemo {
+        transfer_account: transfer_pda(sender, &nonce),
+        sender: *sender,
+        recipient: *recipient,
+        token_mint: solana_program::system_program::id(),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(recipient),
+        registry: None,
+        mint_stats: None,
+        sender_token_account: None,
+        escrow_token_account: None,
+        token_program: None,
+        associated_token_program: None,
+        system_program: solana_program::system_program::id(),
+        rent: solana_program::sysvar::rent::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ScheduleTransferLargeMemo {
+                amount: 100_000_000,
+                execute_after,
+                nonce,
+                memo,
+                memo_capacity,
+            },
+        ),
+    }
+}
+
+fn schedule_spl_ix(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    sender_token_account: &Pubkey,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    build_schedule_spl_ix(sender, recipient, mint, sender_token_account, None, params)
+}
+
+fn schedule_spl_ix_with_config(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    sender_token_account: &Pubkey,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    build_schedule_spl_ix(sender, recipient, mint, sender_token_account, Some(config_pda()), params)
+}
+
+fn build_schedule_spl_ix(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    sender_token_account: &Pubkey,
+    config: Option<Pubkey>,
+    params: ScheduleTransferParams,
+) -> Instruction {
+    let transfer_account = transfer_pda(sender, &params.nonce);
+    let accounts = scheduled_transfer::accounts::ScheduleTransfer {
+        transfer_account,
+        sender: *sender,
+        recipient: *recipient,
+        token_mint: *mint,
+        config,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(recipient),
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        refund_of_transfer: None,
+        sender_token_account: Some(*sender_token_account),
+        escrow_token_account: Some(escrow_ata(&transfer_account, mint)),
+        token_program: Some(spl_token::id()),
+        associated_token_program: Some(anchor_spl::associated_token::ID),
+        system_program: solana_program::system_program::id(),
+        rent: solana_program::sysvar::rent::id(),
+        memo_program: None,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ScheduleTransfer { params },
+        ),
+    }
+}
+
+// Creates a 6-decimal mint whose mint and freeze authority is the context payer
+async fn create_mint(context: &mut ProgramTestContext) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+
+    let create = system_instruction::create_account(
+        &payer,
+        &mint.pubkey(),
+        rent.minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN as u64,
+        &spl_token::id(),
+    );
+    let initialize =
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &payer,
+            Some(&payer),
+            6,
+        )
+        .unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[create, initialize],
+        Some(&payer),
+        &[&context.payer, &mint],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    mint.pubkey()
+}
+
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let token_account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+
+    let create = system_instruction::create_account(
+        &payer,
+        &token_account.pubkey(),
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id(),
+    );
+    let initialize = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &token_account.pubkey(),
+        mint,
+        owner,
+    )
+    .unwrap();
+
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[create, initialize],
+        Some(&payer),
+        &[&context.payer, &token_account],
+        recent_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    token_account.pubkey()
+}
+
+async fn mint_tokens(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) {
+    let mint_to = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        mint,
+        destination,
+        &context.payer.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    send(context, mint_to, &[]).await.unwrap();
+}
+
+async fn token_balance(context: &mut ProgramTestContext, token_account: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
+
+fn registry_pda(sender: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"registry", sender.as_ref()], &scheduled_transfer::id()).0
+}
+
+async fn fetch_registry(context: &mut ProgramTestContext, sender: &Pubkey) -> SenderRegistry {
+    let account = context
+        .banks_client
+        .get_account(registry_pda(sender))
+        .await
+        .unwrap()
+        .unwrap();
+    SenderRegistry::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+async fn fetch_transfers(
+    context: &mut ProgramTestContext,
+    transfer_accounts: &[Pubkey],
+) -> std::collections::HashMap<Pubkey, ScheduledTransfer> {
+    let mut transfers = std::collections::HashMap::new();
+    for transfer_account in transfer_accounts {
+        transfers.insert(*transfer_account, fetch_transfer(context, transfer_account).await);
+    }
+    transfers
+}
+
+// `emergency_freeze` and `unfreeze` share their accounts
+fn freeze_ix(sender: &Pubkey, data: Vec<u8>) -> Instruction {
+    let accounts = scheduled_transfer::accounts::FreezeSender {
+        registry: registry_pda(sender),
+        sender: *sender,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data,
+    }
+}
+
+fn initialize_registry_ix(sender: &Pubkey) -> Instruction {
+    let accounts = scheduled_transfer::accounts::InitializeRegistry {
+        registry: registry_pda(sender),
+        sender: *sender,
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::InitializeRegistry {},
+        ),
+    }
+}
+
+fn approve_condition(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    assert_eq!(instruction_data, scheduled_transfer::CHECK_CONDITION_DISCRIMINATOR);
+    solana_program::program::set_return_data(&[1]);
+    Ok(())
+}
+
+// Stands in for a program that schedules SPL transfers out of its `[b"vault"]`
+// PDA; the instruction data is `schedule_transfer`'s, forwarded as-is
+fn wrapper_schedule(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    let args = scheduled_transfer::instruction::ScheduleTransfer::try_from_slice(
+        &instruction_data[8..],
+    )
+    .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let [
+        vault,
+        transfer_account,
+        recipient,
+        token_mint,
+        config,
+        denylist,
+        recipient_policy,
+        sender_token_account,
+        escrow_token_account,
+        token_program,
+        associated_token_program,
+        system_program,
+        rent,
+        scheduled_transfer_program,
+    ] = accounts else {
+        panic!("unexpected wrapper accounts");
+    };
+    let schedule_accounts = scheduled_transfer::accounts::ScheduleTransfer {
+        transfer_account: *transfer_account.key,
+        sender: *vault.key,
+        recipient: *recipient.key,
+        token_mint: *token_mint.key,
+        config: None,
+        config_pda: *config.key,
+        denylist: *denylist.key,
+        recipient_policy: *recipient_policy.key,
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        refund_of_transfer: None,
+        sender_token_account: Some(*sender_token_account.key),
+        escrow_token_account: Some(*escrow_token_account.key),
+        token_program: Some(*token_program.key),
+        associated_token_program: Some(*associated_token_program.key),
+        system_program: *system_program.key,
+        rent: *rent.key,
+        memo_program: None,
+    };
+    assert_eq!(scheduled_transfer_program.key, &scheduled_transfer::id());
+
+    let (_, bump) = Pubkey::find_program_address(&[b"vault"], program_id);
+    scheduled_transfer::schedule_transfer_cpi(
+        schedule_accounts,
+        accounts,
+        args,
+        &[&[b"vault", &[bump]]],
+    )?;
+    Ok(())
+}
+
+// Stands in for the token metadata program, which is not built into the test validator
+fn accept_metadata(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    Ok(())
+}
+
+// Stands in for a swap program: moves `amount_in` into its vault and mints twice
+// that of the output mint, whose mint authority is this program's `[b"mint"]` PDA
+fn mock_swap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    assert_eq!(&instruction_data[..8], scheduled_transfer::SWAP_DISCRIMINATOR);
+    let amount_in = u64::from_le_bytes(instruction_data[8..16].try_into().unwrap());
+    let [authority, source, destination, token_program, vault, output_mint, mint_authority] =
+        accounts
+    else {
+        panic!("unexpected swap accounts");
+    };
+
+    solana_program::program::invoke(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            source.key,
+            vault.key,
+            authority.key,
+            &[],
+            amount_in,
+        )?,
+        &[source.clone(), vault.clone(), authority.clone(), token_program.clone()],
+    )?;
+
+    let (_, bump) = Pubkey::find_program_address(&[b"mint"], program_id);
+    solana_program::program::invoke_signed(
+        &spl_token::instruction::mint_to(
+            token_program.key,
+            output_mint.key,
+            destination.key,
+            mint_authority.key,
+            &[],
+            amount_in * 2,
+        )?,
+        &[output_mint.clone(), destination.clone(), mint_authority.clone(), token_program.clone()],
+        &[&[b"mint", &[bump]]],
+    )
+}
+
+fn reject_condition(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    _instruction_data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    solana_program::program::set_return_data(&[0]);
+    Ok(())
+}
+
+// Stands in for a lending protocol that wants to hear about incoming funds
+fn on_funds_received(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> solana_program::entrypoint::ProgramResult {
+    let (discriminator, payload) = instruction_data.split_at(8);
+    assert_eq!(discriminator, scheduled_transfer::ON_FUNDS_RECEIVED_DISCRIMINATOR);
+    let transfer_id = Pubkey::try_from(&payload[..32]).unwrap();
+    let amount = u64::from_le_bytes(payload[32..40].try_into().unwrap());
+    msg!("on_funds_received transfer={} amount={}", transfer_id, amount);
+    solana_program::program::set_return_data(&[1]);
+    Ok(())
+}
+
+fn set_notify_program_ix(
+    transfer_account: &Pubkey,
+    authority: &Pubkey,
+    notify_program: Option<Pubkey>,
+    require_callback_success: bool,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::SetNotifyProgram {
+        transfer_account: *transfer_account,
+        authority: *authority,
+    };
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::SetNotifyProgram {
+                notify_program,
+                require_callback_success,
+            },
+        ),
+    }
+}
+
+fn assert_nonce_available_ix(sender: &Pubkey, nonce: [u8; 32]) -> Instruction {
+    let accounts = scheduled_transfer::accounts::AssertNonceAvailable {
+        sender: *sender,
+        transfer_account: transfer_pda(sender, &nonce),
+    };
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::AssertNonceAvailable { nonce },
+        ),
+    }
+}
+
+fn mint_stats_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mint_stats", mint.as_ref()], &scheduled_transfer::id()).0
+}
+
+fn initialize_mint_stats_ix(payer: &Pubkey, mint: &Pubkey) -> Instruction {
+    let accounts = scheduled_transfer::accounts::InitializeMintStats {
+        mint_stats: mint_stats_pda(mint),
+        token_mint: *mint,
+        payer: *payer,
+        system_program: solana_program::system_program::id(),
+    };
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::InitializeMintStats {},
+        ),
+    }
+}
+
+fn campaign_stats_pda(campaign_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"campaign", campaign_id.to_le_bytes().as_ref()],
+        &scheduled_transfer::id(),
+    )
+    .0
+}
+
+fn initialize_campaign_stats_ix(payer: &Pubkey, campaign_id: u64) -> Instruction {
+    let accounts = scheduled_transfer::accounts::InitializeCampaignStats {
+        campaign_stats: campaign_stats_pda(campaign_id),
+        payer: *payer,
+        system_program: solana_program::system_program::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::InitializeCampaignStats { campaign_id },
+        ),
+    }
+}
+
+async fn campaign_stats(context: &mut ProgramTestContext, campaign_id: u64) -> CampaignStats {
+    let accounts = scheduled_transfer::accounts::GetCampaignStats {
+        campaign_stats: campaign_stats_pda(campaign_id),
+    };
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetCampaignStats {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    CampaignStats::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn reserves(context: &mut ProgramTestContext, mints: &[Pubkey]) -> Vec<MintReserve> {
+    let accounts = scheduled_transfer::accounts::GetReserves {};
+    let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    account_metas.extend(
+        mints
+            .iter()
+            .map(|mint| AccountMeta::new_readonly(mint_stats_pda(mint), false)),
+    );
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: account_metas,
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetReserves {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    Vec::<MintReserve>::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+fn delegation_pda(owner: &Pubkey, delegate: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"delegation", owner.as_ref(), delegate.as_ref()],
+        &scheduled_transfer::id(),
+    )
+    .0
+}
+
+fn create_delegation_ix(
+    owner: &Pubkey,
+    delegate: &Pubkey,
+    owner_token_account: &Pubkey,
+    budget: u64,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::CreateDelegation {
+        delegation: delegation_pda(owner, delegate),
+        owner: *owner,
+        delegate: *delegate,
+        owner_token_account: *owner_token_account,
+        token_program: spl_token::id(),
+        system_program: solana_program::system_program::id(),
+    };
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::CreateDelegation { budget },
+        ),
+    }
+}
+
+fn schedule_transfer_for_ix(
+    owner: &Pubkey,
+    delegate: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    owner_token_account: &Pubkey,
+    args: scheduled_transfer::instruction::ScheduleTransferFor,
+) -> Instruction {
+    let transfer_account = transfer_pda(owner, &args.nonce);
+    let accounts = scheduled_transfer::accounts::ScheduleTransferFor {
+        transfer_account,
+        delegation: delegation_pda(owner, delegate),
+        owner: *owner,
+        delegate: *delegate,
+        recipient: *recipient,
+        token_mint: *mint,
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(recipient),
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        owner_token_account: *owner_token_account,
+        escrow_token_account: escrow_ata(&transfer_account, mint),
+        token_program: spl_token::id(),
+        associated_token_program: anchor_spl::associated_token::ID,
+        system_program: solana_program::system_program::id(),
+        rent: solana_program::sysvar::rent::id(),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(&args),
+    }
+}
+
+async fn fetch_delegation(
+    context: &mut ProgramTestContext,
+    owner: &Pubkey,
+    delegate: &Pubkey,
+) -> Delegation {
+    let account = context
+        .banks_client
+        .get_account(delegation_pda(owner, delegate))
+        .await
+        .unwrap()
+        .unwrap();
+    Delegation::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+fn confirm_sol_ix(transfer_account: &Pubkey, sender: &Pubkey) -> Instruction {
+    build_confirm_sol_ix(transfer_account, sender, None)
+}
+
+fn build_confirm_sol_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    mint_stats: Option<Pubkey>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ConfirmSchedule {
+        transfer_account: *transfer_account,
+        sender: *sender,
+        sender_token_account: None,
+        escrow_token_account: None,
+        mint_stats,
+        campaign_stats: None,
+        config: None,
+        config_pda: config_pda(),
+        token_program: None,
+        system_program: solana_program::system_program::id(),
+    };
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ConfirmSchedule {},
+        ),
+    }
+}
+
+async fn due_count(context: &mut ProgramTestContext, transfer_accounts: &[Pubkey]) -> u32 {
+    let accounts = scheduled_transfer::accounts::GetDueCount {
+        clock: solana_program::sysvar::clock::id(),
+    };
+    let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    account_metas.extend(
+        transfer_accounts
+            .iter()
+            .map(|transfer_account| AccountMeta::new_readonly(*transfer_account, false)),
+    );
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: account_metas,
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetDueCount {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    u32::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn claimable_total(
+    context: &mut ProgramTestContext,
+    recipient: &Pubkey,
+    transfer_accounts: &[Pubkey],
+) -> Vec<ClaimableTotal> {
+    let accounts = scheduled_transfer::accounts::GetClaimableTotal {
+        clock: solana_program::sysvar::clock::id(),
+    };
+    let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    account_metas.extend(
+        transfer_accounts
+            .iter()
+            .map(|transfer_account| AccountMeta::new_readonly(*transfer_account, false)),
+    );
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: account_metas,
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetClaimableTotal {
+                recipient: *recipient,
+            },
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    Vec::<ClaimableTotal>::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+// Decodes the program's `Program return:` log line
+fn return_data(logs: &[String]) -> Vec<u8> {
+    use base64::Engine;
+
+    let prefix = format!("Program return: {} ", scheduled_transfer::id());
+    let encoded = logs
+        .iter()
+        .find_map(|log| log.strip_prefix(&prefix))
+        .expect("no return data logged");
+    base64::engine::general_purpose::STANDARD.decode(encoded).unwrap()
+}
+
+async fn program_config(context: &mut ProgramTestContext) -> ProgramConfig {
+    let accounts = scheduled_transfer::accounts::GetProgramConfig {
+        config: config_pda(),
+    };
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetProgramConfig {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    ProgramConfig::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn last_seq(context: &mut ProgramTestContext) -> u64 {
+    let accounts = scheduled_transfer::accounts::GetProgramConfig {
+        config: config_pda(),
+    };
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(&scheduled_transfer::instruction::GetLastSeq {}),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    u64::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn transfer_info(
+    context: &mut ProgramTestContext,
+    transfer_account: &Pubkey,
+) -> TransferInfo {
+    let accounts = scheduled_transfer::accounts::GetTransferInfo {
+        transfer_account: *transfer_account,
+    };
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetTransferInfo {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    TransferInfo::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn parse_transfer_info(
+    context: &mut ProgramTestContext,
+    transfer_account: &Pubkey,
+) -> TransferInfo {
+    let accounts = scheduled_transfer::accounts::ParseTransferInfo {
+        transfer_account: *transfer_account,
+    };
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ParseTransferInfo {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    TransferInfo::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn escrow_address(context: &mut ProgramTestContext, transfer_account: &Pubkey) -> Pubkey {
+    let accounts = scheduled_transfer::accounts::GetEscrowAddress {
+        transfer_account: *transfer_account,
+    };
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetEscrowAddress {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    Pubkey::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn transfers_batch(
+    context: &mut ProgramTestContext,
+    transfer_accounts: &[Pubkey],
+) -> Vec<TransferInfo> {
+    let accounts = scheduled_transfer::accounts::GetTransfersBatch {};
+    let mut account_metas = anchor_lang::ToAccountMetas::to_account_metas(&accounts, None);
+    account_metas.extend(
+        transfer_accounts
+            .iter()
+            .map(|transfer_account| AccountMeta::new_readonly(*transfer_account, false)),
+    );
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: account_metas,
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::GetTransfersBatch {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    Vec::<TransferInfo>::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+async fn verify_escrow_integrity(
+    context: &mut ProgramTestContext,
+    transfer_account: &Pubkey,
+) -> IntegrityReport {
+    let accounts = scheduled_transfer::accounts::VerifyEscrowIntegrity {
+        transfer_account: *transfer_account,
+        escrow_token_account: None,
+    };
+    let instruction = Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::VerifyEscrowIntegrity {},
+        ),
+    };
+
+    let logs = send_with_logs(context, instruction, &[]).await;
+    IntegrityReport::try_from_slice(&return_data(&logs)).unwrap()
+}
+
+// Executed by the recipient; `refund_accounts` is `(sender, sender_token_account)`
+fn build_execute_spl_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    escrow_token_account: &Pubkey,
+    recipient_token_account: &Pubkey,
+    refund_accounts: Option<(Pubkey, Pubkey)>,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        transfer_account: *transfer_account,
+        executor: *recipient,
+        recipient: *recipient,
+        escrow_token_account: Some(*escrow_token_account),
+        recipient_token_account: Some(*recipient_token_account),
+        recipient_output_token_account: None,
+        new_recipient_token_account: None,
+        token_mint: None,
+        sender: refund_accounts.map(|(sender, _)| sender),
+        sender_token_account: refund_accounts.map(|(_, sender_token)| sender_token),
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        registry: None,
+        registry_pda: registry_pda(sender),
+        mint_stats: None,
+        campaign_stats: None,
+        previous_transfer: None,
+        condition_program: None,
+        swap_program: None,
+        notify_program: None,
+        token_program: Some(spl_token::id()),
+        system_program: solana_program::system_program::id(),
+        memo_program: None,
+        receipt_mint: None,
+        receipt_token_account: None,
+        receipt_metadata: None,
+        associated_token_program: None,
+        metadata_program: None,
+        rent: None,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ExecuteScheduledTransfer {},
+        ),
+    }
+}
+
+// Executes an SPL transfer to a payee without a token account, relying on the
+// treasury to sponsor their ATA
+fn build_execute_sponsored_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        transfer_account: *transfer_account,
+        executor: *recipient,
+        recipient: *recipient,
+        escrow_token_account: Some(escrow_ata(transfer_account, mint)),
+        recipient_token_account: None,
+        recipient_output_token_account: None,
+        new_recipient_token_account: Some(get_associated_token_address(recipient, mint)),
+        token_mint: Some(*mint),
+        sender: None,
+        sender_token_account: None,
+        config: Some(config_pda()),
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        registry: None,
+        registry_pda: registry_pda(sender),
+        mint_stats: None,
+        campaign_stats: None,
+        previous_transfer: None,
+        condition_program: None,
+        swap_program: None,
+        notify_program: None,
+        token_program: Some(spl_token::id()),
+        system_program: solana_program::system_program::id(),
+        memo_program: None,
+        receipt_mint: None,
+        receipt_token_account: None,
+        receipt_metadata: None,
+        associated_token_program: Some(anchor_spl::associated_token::ID),
+        metadata_program: None,
+        rent: None,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ExecuteScheduledTransfer {},
+        ),
+    }
+}
+
+// Creates the canonical escrow ATA for a (possibly not yet created) transfer PDA
+async fn create_escrow_ata(
+    context: &mut ProgramTestContext,
+    transfer_account: &Pubkey,
+    mint: &Pubkey,
+) -> Pubkey {
+    create_ata(context, transfer_account, mint).await
+}
+
+async fn create_ata(context: &mut ProgramTestContext, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let ata = get_associated_token_address(owner, mint);
+    let create = Instruction {
+        program_id: anchor_spl::associated_token::ID,
+        accounts: vec![
+            AccountMeta::new(context.payer.pubkey(), true),
+            AccountMeta::new(ata, false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: vec![],
+    };
+    send(context, create, &[]).await.unwrap();
+    ata
+}
+
+fn schedule_with_escrow_ix(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    sender_token_account: &Pubkey,
+    escrow_token_account: &Pubkey,
+    amount: u64,
+    execute_after: i64,
+    nonce: [u8; 32],
+) -> Instruction {
+    let accounts = scheduled_transfer::accounts::ScheduleTransferWithEscrow {
+        transfer_account: transfer_pda(sender, &nonce),
+        sender: *sender,
+        recipient: *recipient,
+        token_mint: *mint,
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        recipient_policy: recipient_policy_pda(recipient),
+        registry: None,
+        mint_stats: None,
+        campaign_stats: None,
+        refund_of_transfer: None,
+        sender_token_account: *sender_token_account,
+        escrow_token_account: *escrow_token_account,
+        token_program: spl_token::id(),
+        system_program: solana_program::system_program::id(),
+        memo_program: None,
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ScheduleTransferWithEscrow {
+                params: default_schedule_args(amount, execute_after, nonce),
+            },
+        ),
+    }
+}
+
+// Executed by the recipient with every receipt account supplied
+fn build_execute_receipt_ix(
+    transfer_account: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    receipt_mint: &Pubkey,
+) -> Instruction {
+    let (receipt_metadata, _) = Pubkey::find_program_address(
+        &[b"metadata", anchor_spl::metadata::ID.as_ref(), receipt_mint.as_ref()],
+        &anchor_spl::metadata::ID,
+    );
+    let accounts = scheduled_transfer::accounts::ExecuteScheduledTransfer {
+        transfer_account: *transfer_account,
+        executor: *recipient,
+        recipient: *recipient,
+        escrow_token_account: None,
+        recipient_token_account: None,
+        recipient_output_token_account: None,
+        new_recipient_token_account: None,
+        token_mint: None,
+        sender: None,
+        sender_token_account: None,
+        config: None,
+        config_pda: config_pda(),
+        denylist: denylist_pda(),
+        registry: None,
+        registry_pda: registry_pda(sender),
+        mint_stats: None,
+        campaign_stats: None,
+        previous_transfer: None,
+        condition_program: None,
+        swap_program: None,
+        notify_program: None,
+        token_program: Some(spl_token::id()),
+        system_program: solana_program::system_program::id(),
+        memo_program: None,
+        receipt_mint: Some(*receipt_mint),
+        receipt_token_account: Some(get_associated_token_address(recipient, receipt_mint)),
+        receipt_metadata: Some(receipt_metadata),
+        associated_token_program: Some(anchor_spl::associated_token::ID),
+        metadata_program: Some(anchor_spl::metadata::ID),
+        rent: Some(solana_program::sysvar::rent::id()),
+    };
+
+    Instruction {
+        program_id: scheduled_transfer::id(),
+        accounts: anchor_lang::ToAccountMetas::to_account_metas(&accounts, None),
+        data: anchor_lang::InstructionData::data(
+            &scheduled_transfer::instruction::ExecuteScheduledTransfer {},
+        ),
+    }
+}